@@ -0,0 +1,12 @@
+#[tokio::main]
+async fn main() {
+    #[cfg(feature = "env_logger")]
+    env_logger::init();
+    use debbugs::Debbugs;
+    let debbugs = Debbugs::default();
+    let report = debbugs
+        .get_bug_mbox(1000, debbugs::mbox::MboxKind::Full)
+        .await
+        .unwrap();
+    println!("{:#?}", report);
+}
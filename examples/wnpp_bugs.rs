@@ -4,17 +4,16 @@ async fn main() {
     env_logger::init();
     use debbugs::Debbugs;
     let debbugs = Debbugs::default();
-    let query = debbugs::SearchQuery {
-        package: Some("wnpp"),
-        ..Default::default()
-    };
-    for ids in debbugs.get_bugs(&query).await.unwrap().chunks(50) {
-        for (id, report) in debbugs.get_status(ids).await.unwrap() {
-            println!(
-                "{}: {}",
-                id,
-                report.subject.unwrap_or("<no title>".to_string())
-            );
-        }
+    for bug in debbugs.wnpp_bugs(&[]).await.unwrap() {
+        println!(
+            "{}: [{}] {}: {}",
+            bug.bug,
+            bug.subject.kind,
+            bug.subject.package,
+            bug.subject
+                .description
+                .as_deref()
+                .unwrap_or("<no description>")
+        );
     }
 }
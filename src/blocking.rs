@@ -1,5 +1,7 @@
 use log::debug;
 
+use crate::instance::Instance;
+use crate::mbox::MboxKind;
 use crate::{BugId, Error, SoapResponse, DEFAULT_USER_AGENT};
 
 impl Debbugs {
@@ -9,7 +11,7 @@ impl Debbugs {
         debug!("SOAP Request: {}", String::from_utf8_lossy(&body));
         let req = self
             .client
-            .post(&self.url)
+            .post(&self.instance.soap_url)
             .body(body)
             .header("Content-Type", "text/xml")
             .header("Soapaction", action);
@@ -33,7 +35,7 @@ impl Default for Debbugs {
     ///
     /// Uses the official Debian bug tracking system at bugs.debian.org
     fn default() -> Self {
-        Self::new(crate::DEFAULT_URL)
+        Self::from_instance(&Instance::debian())
     }
 }
 
@@ -55,6 +57,28 @@ impl Debbugs {
         Self::with_user_agent(url, DEFAULT_USER_AGENT)
     }
 
+    /// Creates a new blocking Debbugs client for a known debbugs deployment
+    ///
+    /// Requests for features the deployment does not have, such as
+    /// `get_versions` on an instance without version tracking, fail with
+    /// [`Error::Unsupported`] without contacting the server.
+    ///
+    /// # Arguments
+    ///
+    /// * `instance` - The profile of the deployment, see [`Instance`]
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use debbugs::blocking::Debbugs;
+    /// use debbugs::instance::Instance;
+    ///
+    /// let client = Debbugs::from_instance(&Instance::gnu());
+    /// ```
+    pub fn from_instance(instance: &Instance) -> Self {
+        Self::with_instance(instance.clone(), DEFAULT_USER_AGENT)
+    }
+
     /// Creates a new blocking Debbugs client with a custom user agent string
     ///
     /// # Arguments
@@ -62,14 +86,23 @@ impl Debbugs {
     /// * `url` - The URL of the Debbugs SOAP endpoint
     /// * `user_agent` - The User-Agent header to send with requests
     pub fn with_user_agent<S: Into<String>>(url: S, user_agent: &str) -> Self {
+        Self::with_instance(Instance::from_soap_url(&url.into()), user_agent)
+    }
+
+    fn with_instance(instance: Instance, user_agent: &str) -> Self {
         Debbugs {
             client: reqwest::blocking::Client::builder()
                 .user_agent(user_agent)
                 .build()
                 .expect("failed to build HTTP client"),
-            url: url.into(),
+            instance,
         }
     }
+
+    /// Returns the profile of the deployment this client talks to
+    pub fn instance(&self) -> &Instance {
+        &self.instance
+    }
 }
 
 /// Blocking client for the Debian Bug Tracking System (Debbugs)
@@ -91,7 +124,7 @@ impl Debbugs {
 /// ```
 pub struct Debbugs {
     client: reqwest::blocking::Client,
-    url: String,
+    instance: Instance,
 }
 
 impl Debbugs {
@@ -152,6 +185,46 @@ impl Debbugs {
         crate::soap::parse_get_bug_log_response(&response).map_err(Error::XmlError)
     }
 
+    /// Retrieves the messages of a bug as an mbox, including attachments
+    ///
+    /// Unlike [`Debbugs::get_bug_log`], this downloads the mbox export from
+    /// `bugreport.cgi` of the instance, so the message bodies contain their
+    /// full MIME structure.
+    ///
+    /// # Arguments
+    ///
+    /// * `bug_id` - The ID of the bug to retrieve the mbox for
+    /// * `kind` - Which mbox variant to retrieve
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use debbugs::blocking::Debbugs;
+    /// use debbugs::mbox::MboxKind;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = Debbugs::default();
+    ///     let logs = client.get_bug_mbox(12345, MboxKind::Full)?;
+    ///     println!("Found {} messages", logs.len());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn get_bug_mbox(
+        &self,
+        bug_id: BugId,
+        kind: MboxKind,
+    ) -> Result<Vec<crate::soap::BugLog>, Error> {
+        if !self.instance.capabilities.mbox {
+            return Err(self.instance.unsupported("Downloading mboxes"));
+        }
+        let url = self.instance.mbox_url(bug_id, kind);
+        debug!("Fetching mbox from {}", url);
+        let res = self.client.get(&url).send()?.error_for_status()?;
+        let data = res.bytes()?;
+
+        crate::mbox::parse_mbox(&data)
+    }
+
     /// Searches for bugs matching the specified criteria
     ///
     /// Returns a list of bug IDs that match the search query. Use `SearchQuery`
@@ -256,9 +329,400 @@ impl Debbugs {
         email: &str,
         usertags: &[&str],
     ) -> Result<std::collections::HashMap<String, Vec<BugId>>, Error> {
+        if !self.instance.capabilities.usertags {
+            return Err(self.instance.unsupported("get_usertag"));
+        }
         let request = crate::soap::get_usertag_request(email, usertags);
         let (_status, response) = self.send_soap_request(&request, "get_usertag")?;
 
         crate::soap::parse_get_usertag_response(&response).map_err(Error::XmlError)
     }
+
+    /// Retrieves the versions of a package in the archive
+    ///
+    /// # Arguments
+    ///
+    /// * `package` - The name of the package
+    /// * `dists` - The distributions to look in, e.g. "unstable" or "trixie" (empty slice for all)
+    /// * `archs` - The architectures to look at, e.g. "source" or "amd64" (empty slice for all)
+    ///
+    /// # Returns
+    ///
+    /// The distinct versions of the package, sorted from oldest to newest.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use debbugs::blocking::Debbugs;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = Debbugs::default();
+    ///     let versions = client.get_versions("debbugs", &["unstable"], &["source"])?;
+    ///     println!("debbugs in unstable: {:?}", versions);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn get_versions(
+        &self,
+        package: &str,
+        dists: &[&str],
+        archs: &[&str],
+    ) -> Result<Vec<debversion::Version>, Error> {
+        if !self.instance.capabilities.versions {
+            return Err(self.instance.unsupported("get_versions"));
+        }
+        let request = crate::soap::get_versions_request(package, dists, archs);
+        let (_status, response) = self.send_soap_request(&request, "get_versions")?;
+
+        crate::soap::parse_get_versions_response(&response).map_err(Error::XmlError)
+    }
+
+    /// Retrieves the versions of a source package in each of the given suites
+    ///
+    /// The result can be used with [`crate::suites::applicability`] to find out
+    /// which suites a bug affects.
+    ///
+    /// # Arguments
+    ///
+    /// * `package` - The name of the source package
+    /// * `suites` - The suites to look in
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use debbugs::blocking::Debbugs;
+    /// use debbugs::suites::Suite;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = Debbugs::default();
+    ///     let suites = [Suite::new("unstable", "sid"), Suite::new("testing", "forky")];
+    ///     for entry in client.get_suite_versions("debbugs", &suites)? {
+    ///         println!("{}: {:?}", entry.suite.name, entry.versions);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn get_suite_versions(
+        &self,
+        package: &str,
+        suites: &[crate::suites::Suite],
+    ) -> Result<Vec<crate::suites::SuiteVersions>, Error> {
+        let mut ret = Vec::new();
+        for suite in suites {
+            let versions = self.get_versions(package, &[suite.name.as_str()], &["source"])?;
+            ret.push(crate::suites::SuiteVersions {
+                suite: suite.clone(),
+                versions,
+            });
+        }
+        Ok(ret)
+    }
+
+    /// Retrieves the release-critical bugs of source packages
+    ///
+    /// See [`crate::rc`] for which bugs count as release-critical.
+    ///
+    /// # Arguments
+    ///
+    /// * `packages` - The names of the source packages
+    /// * `suites` - The suites the bugs have to affect (empty slice to ignore versions)
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use debbugs::blocking::Debbugs;
+    /// use debbugs::suites::Suite;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = Debbugs::default();
+    ///     let testing = [Suite::new("testing", "forky")];
+    ///     for bug in client.rc_bugs_for_packages(&["debbugs"], &testing)? {
+    ///         println!("#{}: {:?}", bug.bug, bug.reasons);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn rc_bugs_for_packages(
+        &self,
+        packages: &[&str],
+        suites: &[crate::suites::Suite],
+    ) -> Result<Vec<crate::rc::RcBug>, Error> {
+        let mut bugs = Vec::new();
+        for package in packages {
+            let query = crate::SearchQuery {
+                src: Some(package),
+                ..Default::default()
+            };
+            bugs.extend(self.get_bugs(&query)?);
+        }
+        bugs.sort();
+        bugs.dedup();
+        self.rc_bugs_among(&bugs, suites)
+    }
+
+    /// Retrieves the release-critical bugs in the packages of a maintainer
+    ///
+    /// # Arguments
+    ///
+    /// * `maintainer` - The email address of the maintainer
+    /// * `suites` - The suites the bugs have to affect (empty slice to ignore versions)
+    pub fn rc_bugs_for_maintainer(
+        &self,
+        maintainer: &str,
+        suites: &[crate::suites::Suite],
+    ) -> Result<Vec<crate::rc::RcBug>, Error> {
+        let query = crate::SearchQuery {
+            maintainer: Some(maintainer),
+            ..Default::default()
+        };
+        let bugs = self.get_bugs(&query)?;
+        self.rc_bugs_among(&bugs, suites)
+    }
+
+    fn rc_bugs_among(
+        &self,
+        bugs: &[BugId],
+        suites: &[crate::suites::Suite],
+    ) -> Result<Vec<crate::rc::RcBug>, Error> {
+        if bugs.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut reports = std::collections::HashMap::new();
+        for batch in bugs.chunks(crate::STATUS_BATCH_SIZE) {
+            reports.extend(self.get_status(batch)?);
+        }
+        let mut versions = std::collections::HashMap::new();
+        if !suites.is_empty() {
+            // Only look up versions for the packages that have candidates
+            let packages: std::collections::BTreeSet<String> = reports
+                .values()
+                .filter(|report| crate::rc::is_rc_candidate(report))
+                .filter_map(crate::rc::source_package)
+                .collect();
+            for package in packages {
+                let suite_versions = self.get_suite_versions(&package, suites)?;
+                versions.insert(package, suite_versions);
+            }
+        }
+        Ok(crate::rc::rc_bugs(&reports, &versions, false, None))
+    }
+
+    /// Checks the bugs an upload closes against their status in the BTS
+    ///
+    /// Reports bugs that do not exist, are filed against other packages, have
+    /// already been closed or archived, or are merged with other bugs.
+    ///
+    /// # Arguments
+    ///
+    /// * `package` - The source package being uploaded
+    /// * `bugs` - The bugs the upload closes, see [`crate::closes::parse_closes`]
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use debbugs::blocking::Debbugs;
+    /// use debbugs::closes::parse_closes;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = Debbugs::default();
+    ///     let bugs = parse_closes("  * Fix crash. Closes: #1000");
+    ///     for issue in client.check_closes("foo", &bugs)? {
+    ///         println!("{}", issue);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn check_closes(
+        &self,
+        package: &str,
+        bugs: &[BugId],
+    ) -> Result<Vec<crate::closes::ClosesIssue>, Error> {
+        if bugs.is_empty() {
+            return Ok(vec![]);
+        }
+        let reports = self.get_status(bugs)?;
+        Ok(crate::closes::check_closes(package, bugs, &reports))
+    }
+
+    /// Groups bug reports into clusters of merged bugs
+    ///
+    /// The status of merge partners that are missing from `reports` is
+    /// retrieved and added to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `reports` - Bug reports, as returned by `get_status`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use debbugs::blocking::Debbugs;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = Debbugs::default();
+    ///     let mut reports = client.get_status(&[12345, 67890])?;
+    ///     let groups = client.resolve_merged(&mut reports)?;
+    ///     for group in groups.groups() {
+    ///         println!("{:?}", group);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn resolve_merged(
+        &self,
+        reports: &mut std::collections::HashMap<BugId, crate::BugReport>,
+    ) -> Result<crate::merged::MergedGroups, Error> {
+        let mut requested = std::collections::HashSet::new();
+        loop {
+            let groups = crate::merged::MergedGroups::from_reports(reports);
+            // Bugs that were requested but not returned do not exist
+            let missing: Vec<BugId> = groups
+                .missing(reports)
+                .into_iter()
+                .filter(|bug| requested.insert(*bug))
+                .collect();
+            if missing.is_empty() {
+                return Ok(groups);
+            }
+            for batch in missing.chunks(crate::STATUS_BATCH_SIZE) {
+                reports.extend(self.get_status(batch)?);
+            }
+        }
+    }
+
+    /// Builds the graph of blocking relations around a set of bugs
+    ///
+    /// Starting from `bugs`, the bugs that block or are blocked by them are
+    /// followed up to `max_depth` steps away. Bugs further away appear in the
+    /// graph without their reports.
+    ///
+    /// # Arguments
+    ///
+    /// * `bugs` - The bugs to start from
+    /// * `max_depth` - How many steps to follow relations (0 for just `bugs`)
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use debbugs::blocking::Debbugs;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = Debbugs::default();
+    ///     let graph = client.crawl_blocks(&[12345], 2)?;
+    ///     println!("{}", graph.to_dot());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn crawl_blocks(
+        &self,
+        bugs: &[BugId],
+        max_depth: usize,
+    ) -> Result<crate::graph::BlockGraph, Error> {
+        let mut graph = crate::graph::BlockGraph::new();
+        let mut requested = std::collections::HashSet::new();
+        let mut frontier = bugs.to_vec();
+        for _ in 0..=max_depth {
+            frontier.retain(|bug| requested.insert(*bug));
+            if frontier.is_empty() {
+                break;
+            }
+            for batch in frontier.chunks(crate::STATUS_BATCH_SIZE) {
+                for (bug, report) in self.get_status(batch)? {
+                    graph.add_report(bug, report);
+                }
+            }
+            frontier = graph.unresolved();
+        }
+        Ok(graph)
+    }
+
+    /// Retrieves the open WNPP bugs of the given kinds
+    ///
+    /// Bugs against the wnpp pseudo-package whose subject can not be parsed
+    /// are skipped. The bugs are ordered by bug number.
+    ///
+    /// # Arguments
+    ///
+    /// * `kinds` - The kinds of bugs to return (empty slice for all)
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use debbugs::blocking::Debbugs;
+    /// use debbugs::wnpp::{orphaned_by, WnppKind};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = Debbugs::default();
+    ///     let bugs = client.wnpp_bugs(&[WnppKind::O, WnppKind::Rfa])?;
+    ///     for bug in orphaned_by(&bugs, "jane@example.com") {
+    ///         println!("#{}: {}", bug.bug, bug.subject);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn wnpp_bugs(
+        &self,
+        kinds: &[crate::wnpp::WnppKind],
+    ) -> Result<Vec<crate::wnpp::WnppBug>, Error> {
+        let query = crate::SearchQuery {
+            package: Some(crate::wnpp::WNPP_PACKAGE),
+            status: Some(crate::BugStatus::Open),
+            ..Default::default()
+        };
+        let bugs = self.get_bugs(&query)?;
+        let mut ret = Vec::new();
+        for batch in bugs.chunks(crate::STATUS_BATCH_SIZE) {
+            for (bug, report) in self.get_status(batch)? {
+                if let Some(wnpp) = crate::wnpp::WnppBug::from_report(bug, report) {
+                    if kinds.is_empty() || kinds.contains(&wnpp.subject.kind) {
+                        ret.push(wnpp);
+                    }
+                }
+            }
+        }
+        ret.sort_by_key(|wnpp| wnpp.bug);
+        Ok(ret)
+    }
+
+    /// Retrieves the usertags of users into an index, for looking them up by bug
+    ///
+    /// Users that are already in the index are skipped, so the index can be
+    /// kept around and reused to look up many bugs.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index to add the usertags to
+    /// * `users` - The email addresses of the users
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use debbugs::blocking::Debbugs;
+    /// use debbugs::usertags::UsertagIndex;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = Debbugs::default();
+    ///     let mut index = UsertagIndex::new();
+    ///     let users = ["release.debian.org@packages.debian.org", "debian-qa@lists.debian.org"];
+    ///     client.load_usertags(&mut index, &users)?;
+    ///     for (user, tags) in index.lookup(12345) {
+    ///         println!("{}: {}", user, tags.join(", "));
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn load_usertags(
+        &self,
+        index: &mut crate::usertags::UsertagIndex,
+        users: &[&str],
+    ) -> Result<(), Error> {
+        for user in users {
+            if index.contains_user(user) {
+                continue;
+            }
+            let usertags = self.get_usertag(user, &[])?;
+            index.insert(user, &usertags);
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,236 @@
+//! Classification of bug log messages
+//!
+//! Bug logs mix the original report, commands for control@, replies and closures
+//! with messages generated by the BTS itself. [`BugLog::kind`] tells them apart,
+//! so that summaries can skip acknowledgements and other noise.
+
+use crate::BugLog;
+use lazy_regex::{regex_captures, regex_is_match};
+
+/// The kind of a message in a bug log
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum MessageKind {
+    /// The original report, sent to submit@ (or maintonly@/quiet@)
+    Report,
+    /// Commands sent to control@
+    Control,
+    /// A follow-up to the bug, sent to nnn@ or one of its variants
+    Reply,
+    /// A message closing the bug, sent to nnn-done@ (or nnn-close@)
+    Done,
+    /// A message generated by the BTS, such as an acknowledgement or a
+    /// "Processed:" transcript of control commands
+    Ack,
+}
+
+impl MessageKind {
+    /// Returns whether messages of this kind were generated by the BTS itself
+    pub fn is_automated(&self) -> bool {
+        *self == MessageKind::Ack
+    }
+}
+
+impl std::fmt::Display for MessageKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MessageKind::Report => f.write_str("report"),
+            MessageKind::Control => f.write_str("control"),
+            MessageKind::Reply => f.write_str("reply"),
+            MessageKind::Done => f.write_str("done"),
+            MessageKind::Ack => f.write_str("ack"),
+        }
+    }
+}
+
+/// Classifies the debbugs address a message was sent to, e.g. "submit" or "1000-done"
+fn classify_address(local_part: &str) -> Option<MessageKind> {
+    let local_part = local_part.to_ascii_lowercase();
+    match local_part.as_str() {
+        "submit" | "maintonly" | "quiet" => Some(MessageKind::Report),
+        "control" => Some(MessageKind::Control),
+        _ => match regex_captures!(r"^\d+(?:-([a-z]+))?$", &local_part) {
+            Some((_, "done")) | Some((_, "close")) => Some(MessageKind::Done),
+            Some(_) => Some(MessageKind::Reply),
+            None => None,
+        },
+    }
+}
+
+impl BugLog {
+    /// Determines the kind of this message
+    ///
+    /// The classification uses, in order: the `X-Debian-PR-Message` header the
+    /// BTS adds to the messages it sends, markers of BTS-generated messages
+    /// (acknowledgement Message-IDs and "Processed:" subjects), the address
+    /// recorded in the `Received: (at ...)` header the BTS adds to incoming
+    /// messages, and finally the `To` and `Cc` headers.
+    ///
+    /// Messages that cannot be classified otherwise are considered replies.
+    pub fn kind(&self) -> MessageKind {
+        if let Some(pr_message) = self.raw_header("X-Debian-PR-Message") {
+            match pr_message.split_whitespace().next() {
+                Some("report") => return MessageKind::Report,
+                Some("followup") => return MessageKind::Reply,
+                Some(_) => return MessageKind::Ack,
+                None => {}
+            }
+        }
+
+        if let Some(msgid) = self.raw_header("Message-ID") {
+            if regex_is_match!(r"^<?handler\.[^@]+\.ack", &msgid) {
+                return MessageKind::Ack;
+            }
+        }
+
+        if let Some(subject) = self.raw_header("Subject") {
+            if regex_is_match!(r"^Processed(?: \(with \d+ errors?\))?:", &subject) {
+                return MessageKind::Ack;
+            }
+        }
+
+        // The BTS prepends a Received header recording the address it received the message at
+        if let Some((_, address)) = self
+            .header
+            .lines()
+            .filter_map(|line| regex_captures!(r"^Received: \(at ([^)\s]+)\)", line))
+            .next()
+        {
+            if let Some(kind) = classify_address(address) {
+                return kind;
+            }
+        }
+
+        for name in ["To", "Cc"] {
+            if let Some(value) = self.raw_header(name) {
+                // Prefer the most specific classification among all recipients
+                let kinds: Vec<MessageKind> = value
+                    .split(',')
+                    .filter_map(|addr| {
+                        let (_, local_part) = regex_captures!(r"([^\s<>]+)@", addr)?;
+                        classify_address(local_part)
+                    })
+                    .collect();
+                for kind in [
+                    MessageKind::Control,
+                    MessageKind::Done,
+                    MessageKind::Report,
+                    MessageKind::Reply,
+                ] {
+                    if kinds.contains(&kind) {
+                        return kind;
+                    }
+                }
+            }
+        }
+
+        MessageKind::Reply
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(header: &str) -> MessageKind {
+        BugLog {
+            header: header.to_string(),
+            msgnum: 5,
+            body: "".to_string(),
+        }
+        .kind()
+    }
+
+    #[test]
+    fn test_classify_received() {
+        assert_eq!(
+            kind("Received: (at submit) by bugs.debian.org; 1 Jan 2024 00:00:00 +0000\nFrom: a@example.com"),
+            MessageKind::Report
+        );
+        assert_eq!(
+            kind("Received: (at maintonly) by bugs.debian.org; 1 Jan 2024 00:00:00 +0000"),
+            MessageKind::Report
+        );
+        assert_eq!(
+            kind("Received: (at control) by bugs.debian.org; 1 Jan 2024 00:00:00 +0000"),
+            MessageKind::Control
+        );
+        assert_eq!(
+            kind("Received: (at 1000-done) by bugs.debian.org; 1 Jan 2024 00:00:00 +0000"),
+            MessageKind::Done
+        );
+        assert_eq!(
+            kind("Received: (at 1000-close) by bugs.debian.org; 1 Jan 2024 00:00:00 +0000"),
+            MessageKind::Done
+        );
+        assert_eq!(
+            kind("Received: (at 1000) by bugs.debian.org; 1 Jan 2024 00:00:00 +0000"),
+            MessageKind::Reply
+        );
+        assert_eq!(
+            kind("Received: (at 1000-submitter) by bugs.debian.org; 1 Jan 2024 00:00:00 +0000"),
+            MessageKind::Reply
+        );
+    }
+
+    #[test]
+    fn test_classify_ack() {
+        assert_eq!(
+            kind("From: owner@bugs.debian.org (Debian Bug Tracking System)\nSubject: Processed: tagging 1000\nMessage-ID: <handler.s.C.1234.transcript@bugs.debian.org>"),
+            MessageKind::Ack
+        );
+        assert_eq!(
+            kind("Subject: Processed (with 1 error): fix"),
+            MessageKind::Ack
+        );
+        assert_eq!(
+            kind("Message-ID: <handler.1000.B.123456.ack@bugs.debian.org>"),
+            MessageKind::Ack
+        );
+        assert_eq!(
+            kind("X-Debian-PR-Message: transcript\nReceived: (at control) by bugs.debian.org"),
+            MessageKind::Ack
+        );
+        assert_eq!(
+            kind("X-Debian-PR-Message: they-closed 1000"),
+            MessageKind::Ack
+        );
+        assert!(MessageKind::Ack.is_automated());
+        assert!(!MessageKind::Reply.is_automated());
+    }
+
+    #[test]
+    fn test_classify_pr_message() {
+        assert_eq!(
+            kind("X-Debian-PR-Message: report 1000"),
+            MessageKind::Report
+        );
+        assert_eq!(
+            kind("X-Debian-PR-Message: followup 1000"),
+            MessageKind::Reply
+        );
+    }
+
+    #[test]
+    fn test_classify_recipients() {
+        assert_eq!(kind("To: submit@bugs.debian.org"), MessageKind::Report);
+        assert_eq!(
+            kind("To: 1000@bugs.debian.org\nCc: control@bugs.debian.org"),
+            MessageKind::Reply
+        );
+        assert_eq!(
+            kind("To: Someone <someone@example.com>, 1000-done@bugs.debian.org"),
+            MessageKind::Done
+        );
+        assert_eq!(
+            kind("To: control@bugs.debian.org, 1000@bugs.debian.org"),
+            MessageKind::Control
+        );
+        assert_eq!(kind("Subject: hello"), MessageKind::Reply);
+    }
+
+    #[test]
+    fn test_message_kind_display() {
+        assert_eq!(MessageKind::Report.to_string(), "report");
+        assert_eq!(MessageKind::Ack.to_string(), "ack");
+    }
+}
@@ -0,0 +1,324 @@
+//! Bugs closed by uploads
+//!
+//! Uploads close bugs through `Closes: #NNN` entries in `debian/changelog`,
+//! and Ubuntu uploads close Launchpad bugs through `LP: #NNN`. The entries are
+//! recognised with the same regular expressions dpkg uses, so what is found
+//! here is what the archive will act on.
+//!
+//! [`check_closes`] compares the referenced bugs with their status in the
+//! BTS, to catch typos before uploading; the clients provide `check_closes`
+//! to fetch that status.
+//!
+//! # Examples
+//!
+//! ```
+//! use debbugs::closes::parse_closes;
+//!
+//! let text = "  * Fix crash on startup. Closes: #1000, #1001\n  * Closes: Bug#1002\n";
+//! assert_eq!(parse_closes(text), vec![1000, 1001, 1002]);
+//! ```
+
+use crate::versions::changelog_entries;
+use crate::{BugId, BugReport, Error};
+use debversion::Version;
+use lazy_regex::regex;
+use std::collections::HashMap;
+
+/// Returns the Debian bugs closed by a changelog text, in order of appearance
+///
+/// Bugs that are mentioned more than once are only returned once.
+pub fn parse_closes(text: &str) -> Vec<BugId> {
+    let mut ret = vec![];
+    for closes in regex!(r"(?i)closes:\s*(?:bug)?#?\s?\d+(?:,\s*(?:bug)?#?\s?\d+)*").find_iter(text)
+    {
+        for bug in regex!(r"#?\s?(\d+)").captures_iter(closes.as_str()) {
+            if let Ok(bug) = bug[1].parse::<BugId>() {
+                if !ret.contains(&bug) {
+                    ret.push(bug);
+                }
+            }
+        }
+    }
+    ret
+}
+
+/// Returns the Launchpad bugs closed by a changelog text, in order of appearance
+pub fn parse_lp_bugs(text: &str) -> Vec<u32> {
+    let mut ret = vec![];
+    for lp in regex!(r"(?i)lp:\s+#\d+(?:,\s*#\d+)*").find_iter(text) {
+        for bug in regex!(r"#(\d+)").captures_iter(lp.as_str()) {
+            if let Ok(bug) = bug[1].parse::<u32>() {
+                if !ret.contains(&bug) {
+                    ret.push(bug);
+                }
+            }
+        }
+    }
+    ret
+}
+
+/// The bugs closed by one entry of a `debian/changelog` file
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ChangelogCloses {
+    /// The source package of the entry
+    pub package: String,
+    /// The version of the entry
+    pub version: Version,
+    /// The Debian bugs closed by the entry
+    pub closes: Vec<BugId>,
+    /// The Launchpad bugs closed by the entry
+    pub lp_bugs: Vec<u32>,
+}
+
+/// Returns the bugs closed by each entry of a `debian/changelog` file, newest first
+pub fn parse_changelog_closes(changelog: &str) -> Result<Vec<ChangelogCloses>, Error> {
+    Ok(changelog_entries(changelog)?
+        .into_iter()
+        .map(|(package, version, text)| ChangelogCloses {
+            package,
+            version,
+            closes: parse_closes(&text),
+            lp_bugs: parse_lp_bugs(&text),
+        })
+        .collect())
+}
+
+/// A problem with a bug that an upload closes
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ClosesIssue {
+    /// The bug does not exist
+    NotFound(BugId),
+    /// The bug is filed against other packages
+    WrongPackage {
+        /// The bug number
+        bug: BugId,
+        /// The package the bug is filed against
+        package: String,
+    },
+    /// The bug has already been closed
+    AlreadyClosed {
+        /// The bug number
+        bug: BugId,
+        /// Who closed the bug
+        done: String,
+    },
+    /// The bug has been archived
+    Archived(BugId),
+    /// The bug is merged with other bugs, which will be closed as well
+    Merged {
+        /// The bug number
+        bug: BugId,
+        /// The bugs it is merged with
+        with: Vec<BugId>,
+    },
+}
+
+impl ClosesIssue {
+    /// Returns the bug the issue is about
+    pub fn bug(&self) -> BugId {
+        match self {
+            ClosesIssue::NotFound(bug) | ClosesIssue::Archived(bug) => *bug,
+            ClosesIssue::WrongPackage { bug, .. }
+            | ClosesIssue::AlreadyClosed { bug, .. }
+            | ClosesIssue::Merged { bug, .. } => *bug,
+        }
+    }
+}
+
+impl std::fmt::Display for ClosesIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ClosesIssue::NotFound(bug) => write!(f, "#{} does not exist", bug),
+            ClosesIssue::WrongPackage { bug, package } => {
+                write!(f, "#{} is filed against {}", bug, package)
+            }
+            ClosesIssue::AlreadyClosed { bug, done } => {
+                write!(f, "#{} has already been closed by {}", bug, done)
+            }
+            ClosesIssue::Archived(bug) => write!(f, "#{} has been archived", bug),
+            ClosesIssue::Merged { bug, with } => write!(
+                f,
+                "#{} is merged with {}",
+                bug,
+                with.iter()
+                    .map(|b| format!("#{}", b))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// Returns whether a bug is filed against a source package or one of its binaries
+fn belongs_to(report: &BugReport, package: &str) -> bool {
+    if report.source.as_deref() == Some(package) {
+        return true;
+    }
+    report
+        .package
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(|p| p.trim())
+        .any(|p| p == package || p.strip_prefix("src:") == Some(package))
+}
+
+/// Checks the bugs an upload of the source package `package` closes against
+/// their status in the BTS
+///
+/// `reports` holds the status of the bugs, as returned by `get_status`; bugs
+/// that are missing from it do not exist.
+pub fn check_closes(
+    package: &str,
+    bugs: &[BugId],
+    reports: &HashMap<BugId, BugReport>,
+) -> Vec<ClosesIssue> {
+    let mut issues = vec![];
+    for bug in bugs {
+        let report = match reports.get(bug) {
+            Some(report) => report,
+            None => {
+                issues.push(ClosesIssue::NotFound(*bug));
+                continue;
+            }
+        };
+        if !belongs_to(report, package) {
+            issues.push(ClosesIssue::WrongPackage {
+                bug: *bug,
+                package: report.package.clone().unwrap_or_default(),
+            });
+        }
+        if report.archived == Some(true) {
+            issues.push(ClosesIssue::Archived(*bug));
+        } else if let Some(done) = report.done.as_deref().filter(|d| !d.is_empty()) {
+            issues.push(ClosesIssue::AlreadyClosed {
+                bug: *bug,
+                done: done.to_string(),
+            });
+        }
+        if let Some(with) = report.mergedwith.as_ref().filter(|m| !m.is_empty()) {
+            issues.push(ClosesIssue::Merged {
+                bug: *bug,
+                with: with.clone(),
+            });
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_closes() {
+        assert_eq!(parse_closes("Closes: #1000"), vec![1000]);
+        assert_eq!(
+            parse_closes("closes:1000,bug#1001, #1002,  # 1003"),
+            vec![1000, 1001, 1002, 1003]
+        );
+        assert_eq!(parse_closes("CLOSES: Bug# 1000, 1001"), vec![1000, 1001]);
+        assert_eq!(
+            parse_closes("Closes: #1000\n  * More. (Closes: #1001, #1000)"),
+            vec![1000, 1001]
+        );
+        assert!(parse_closes("See #1000. Closes nothing").is_empty());
+    }
+
+    #[test]
+    fn test_parse_lp_bugs() {
+        assert_eq!(parse_lp_bugs("(LP: #123456, #7)"), vec![123456, 7]);
+        assert!(parse_lp_bugs("LP:#123").is_empty());
+    }
+
+    #[test]
+    fn test_parse_changelog_closes() {
+        let changelog = "\
+foo (1.1-1) unstable; urgency=medium
+
+  * New upstream release. Closes: #1000
+  * Fix crash. (Closes: #1001) (LP: #42)
+
+ -- Jane <jane@example.com>  Mon, 01 Jan 2024 00:00:00 +0000
+
+foo (1.0-1) unstable; urgency=low
+
+  * Initial release. (Closes: #900)
+
+ -- Jane <jane@example.com>  Sun, 01 Jan 2023 00:00:00 +0000
+";
+        let entries = parse_changelog_closes(changelog).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                ChangelogCloses {
+                    package: "foo".to_string(),
+                    version: "1.1-1".parse().unwrap(),
+                    closes: vec![1000, 1001],
+                    lp_bugs: vec![42],
+                },
+                ChangelogCloses {
+                    package: "foo".to_string(),
+                    version: "1.0-1".parse().unwrap(),
+                    closes: vec![900],
+                    lp_bugs: vec![],
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_closes() {
+        let reports = HashMap::from([
+            (
+                1,
+                BugReport::from_fields("<package>foo-utils</package><source>foo</source>"),
+            ),
+            (2, BugReport::from_fields("<package>src:foo</package>")),
+            (
+                3,
+                BugReport::from_fields("<package>bar</package><source>bar</source>"),
+            ),
+            (
+                4,
+                BugReport::from_fields(
+                    "<package>foo</package><done>Jane &lt;jane@example.com&gt;</done>",
+                ),
+            ),
+            (
+                5,
+                BugReport::from_fields(
+                    "<package>foo</package><archived>1</archived><done>jane@example.com</done>",
+                ),
+            ),
+            (
+                6,
+                BugReport::from_fields("<package>foo,bar</package><mergedwith>7</mergedwith>"),
+            ),
+        ]);
+        assert!(check_closes("foo", &[1, 2], &reports).is_empty());
+        let issues = check_closes("foo", &[3, 4, 5, 6, 8], &reports);
+        assert_eq!(
+            issues,
+            vec![
+                ClosesIssue::WrongPackage {
+                    bug: 3,
+                    package: "bar".to_string()
+                },
+                ClosesIssue::AlreadyClosed {
+                    bug: 4,
+                    done: "Jane <jane@example.com>".to_string()
+                },
+                ClosesIssue::Archived(5),
+                ClosesIssue::Merged {
+                    bug: 6,
+                    with: vec![7]
+                },
+                ClosesIssue::NotFound(8),
+            ]
+        );
+        assert_eq!(issues[3].bug(), 6);
+        assert_eq!(issues[3].to_string(), "#6 is merged with #7");
+        assert_eq!(issues[0].to_string(), "#3 is filed against bar");
+    }
+}
@@ -0,0 +1,1780 @@
+//! Commands for the control@ interface of the BTS
+//!
+//! The BTS can be manipulated by sending commands by mail to control@ (or in
+//! pseudo-headers of other messages). This module provides typed representations
+//! of these commands, validates them and renders complete control messages. It
+//! can also parse control messages found in bug logs back into commands, and
+//! pair them with the transcripts the BTS sent in reply.
+//!
+//! See <https://www.debian.org/Bugs/server-control> for the full documentation
+//! of the control language.
+//!
+//! # Examples
+//!
+//! ```
+//! use debbugs::control::{Command, ControlMessage, TagAction};
+//! use debbugs::{Severity, Tag};
+//!
+//! let message = ControlMessage::new()
+//!     .command(Command::Severity { bug: 1000, severity: Severity::Serious })
+//!     .command(Command::Tags { bug: 1000, action: TagAction::Add, tags: vec![Tag::Patch] });
+//! message.validate().unwrap();
+//! assert_eq!(message.body(), "severity 1000 serious\ntags 1000 + patch\nthanks\n");
+//! ```
+
+use crate::mail::Mail;
+use crate::{BugId, BugLog, BugReport, Error, Severity, Tag};
+use debversion::Version;
+use lazy_regex::{regex_captures, regex_is_match};
+use std::collections::HashMap;
+
+/// How a list-valued property (tags, affects, usertags) is modified
+#[derive(Debug, PartialEq, Eq, Default, Clone, Copy)]
+pub enum TagAction {
+    /// Add to the existing values (`+`, default)
+    #[default]
+    Add,
+    /// Remove from the existing values (`-`)
+    Remove,
+    /// Replace the existing values (`=`)
+    Set,
+}
+
+impl std::fmt::Display for TagAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TagAction::Add => f.write_str("+"),
+            TagAction::Remove => f.write_str("-"),
+            TagAction::Set => f.write_str("="),
+        }
+    }
+}
+
+/// A version, optionally qualified with the source package it belongs to
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SourceVersion {
+    /// The source package, e.g. "foo" in "foo/1.0-1"
+    pub source: Option<String>,
+    /// The version itself
+    pub version: Version,
+}
+
+impl From<Version> for SourceVersion {
+    fn from(version: Version) -> Self {
+        SourceVersion {
+            source: None,
+            version,
+        }
+    }
+}
+
+impl std::fmt::Display for SourceVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.source {
+            Some(source) => write!(f, "{}/{}", source, self.version),
+            None => write!(f, "{}", self.version),
+        }
+    }
+}
+
+/// How to change the submitter when reopening a bug
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ReopenSubmitter {
+    /// Make the sender of the control message the submitter (`!`)
+    Sender,
+    /// Set the submitter to the given address
+    Address(String),
+}
+
+/// One section of a usercategory, listed with `*`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CategorySection {
+    /// The title of the section
+    pub title: String,
+    /// Prefix applied to all selections in this section, e.g. "tag="
+    pub prefix: Option<String>,
+    /// The selections in this section, in display order
+    pub selections: Vec<CategorySelection>,
+}
+
+/// A selection within a usercategory section, listed with `+`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CategorySelection {
+    /// The title shown for bugs matching this selection
+    pub title: String,
+    /// Explicit ordering number for this selection
+    pub order: Option<u32>,
+    /// The selection expression, e.g. "needs-review"; `None` makes this the
+    /// default selection for bugs not matching any other selection
+    pub selection: Option<String>,
+}
+
+/// A command understood by control@
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Command {
+    /// Change the title of a bug
+    Retitle { bug: BugId, title: String },
+    /// Change the severity of a bug
+    Severity { bug: BugId, severity: Severity },
+    /// Change the tags of a bug
+    Tags {
+        bug: BugId,
+        action: TagAction,
+        tags: Vec<Tag>,
+    },
+    /// Merge bugs, which must have the same package and severity
+    Merge { bugs: Vec<BugId> },
+    /// Merge bugs, adjusting their state to match the first bug
+    ForceMerge { bugs: Vec<BugId> },
+    /// Remove a bug from its set of merged bugs
+    Unmerge { bug: BugId },
+    /// Reassign a bug to one or more other packages
+    Reassign {
+        bug: BugId,
+        packages: Vec<String>,
+        version: Option<SourceVersion>,
+    },
+    /// Change the packages affected by a bug
+    Affects {
+        bug: BugId,
+        action: TagAction,
+        packages: Vec<String>,
+    },
+    /// Mark a bug as blocked by other bugs
+    Block { bug: BugId, blockers: Vec<BugId> },
+    /// Remove blocking bugs from a bug
+    Unblock { bug: BugId, blockers: Vec<BugId> },
+    /// Record that a bug was found in a version
+    Found {
+        bug: BugId,
+        version: Option<SourceVersion>,
+    },
+    /// Remove a found version from a bug
+    NotFound { bug: BugId, version: SourceVersion },
+    /// Record that a bug was fixed in a version, without closing it
+    Fixed { bug: BugId, version: SourceVersion },
+    /// Remove a fixed version from a bug
+    NotFixed { bug: BugId, version: SourceVersion },
+    /// Set the owner of a bug
+    Owner { bug: BugId, owner: String },
+    /// Remove the owner of a bug
+    NoOwner { bug: BugId },
+    /// Record where a bug has been forwarded to
+    Forwarded { bug: BugId, address: String },
+    /// Forget where a bug has been forwarded to
+    NotForwarded { bug: BugId },
+    /// Close a bug (deprecated, mail nnn-done@ instead)
+    Close {
+        bug: BugId,
+        version: Option<SourceVersion>,
+    },
+    /// Reopen a closed bug, optionally changing the submitter
+    Reopen {
+        bug: BugId,
+        submitter: Option<ReopenSubmitter>,
+    },
+    /// Archive a bug that is eligible for archival
+    Archive { bug: BugId },
+    /// Unarchive a bug
+    Unarchive { bug: BugId },
+    /// Set the user for subsequent usertag and usercategory commands
+    User { user: String },
+    /// Change the usertags of a bug, for the current user
+    Usertags {
+        bug: BugId,
+        action: TagAction,
+        tags: Vec<String>,
+    },
+    /// Define (or with no sections, remove) a usercategory for the current user
+    Usercategory {
+        name: String,
+        hidden: bool,
+        sections: Vec<CategorySection>,
+    },
+}
+
+fn join<T: std::fmt::Display>(items: &[T], separator: &str) -> String {
+    items
+        .iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+impl std::fmt::Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Command::Retitle { bug, title } => write!(f, "retitle {} {}", bug, title),
+            Command::Severity { bug, severity } => write!(f, "severity {} {}", bug, severity),
+            Command::Tags { bug, action, tags } => {
+                write!(f, "tags {} {}", bug, action)?;
+                if !tags.is_empty() {
+                    write!(f, " {}", join(tags, " "))?;
+                }
+                Ok(())
+            }
+            Command::Merge { bugs } => write!(f, "merge {}", join(bugs, " ")),
+            Command::ForceMerge { bugs } => write!(f, "forcemerge {}", join(bugs, " ")),
+            Command::Unmerge { bug } => write!(f, "unmerge {}", bug),
+            Command::Reassign {
+                bug,
+                packages,
+                version,
+            } => {
+                write!(f, "reassign {} {}", bug, packages.join(","))?;
+                if let Some(version) = version {
+                    write!(f, " {}", version)?;
+                }
+                Ok(())
+            }
+            Command::Affects {
+                bug,
+                action,
+                packages,
+            } => {
+                write!(f, "affects {} {}", bug, action)?;
+                if !packages.is_empty() {
+                    write!(f, " {}", packages.join(" "))?;
+                }
+                Ok(())
+            }
+            Command::Block { bug, blockers } => {
+                write!(f, "block {} by {}", bug, join(blockers, " "))
+            }
+            Command::Unblock { bug, blockers } => {
+                write!(f, "unblock {} by {}", bug, join(blockers, " "))
+            }
+            Command::Found { bug, version } => {
+                write!(f, "found {}", bug)?;
+                if let Some(version) = version {
+                    write!(f, " {}", version)?;
+                }
+                Ok(())
+            }
+            Command::NotFound { bug, version } => write!(f, "notfound {} {}", bug, version),
+            Command::Fixed { bug, version } => write!(f, "fixed {} {}", bug, version),
+            Command::NotFixed { bug, version } => write!(f, "notfixed {} {}", bug, version),
+            Command::Owner { bug, owner } => write!(f, "owner {} {}", bug, owner),
+            Command::NoOwner { bug } => write!(f, "noowner {}", bug),
+            Command::Forwarded { bug, address } => write!(f, "forwarded {} {}", bug, address),
+            Command::NotForwarded { bug } => write!(f, "notforwarded {}", bug),
+            Command::Close { bug, version } => {
+                write!(f, "close {}", bug)?;
+                if let Some(version) = version {
+                    write!(f, " {}", version)?;
+                }
+                Ok(())
+            }
+            Command::Reopen { bug, submitter } => {
+                write!(f, "reopen {}", bug)?;
+                match submitter {
+                    Some(ReopenSubmitter::Sender) => write!(f, " !"),
+                    Some(ReopenSubmitter::Address(address)) => write!(f, " {}", address),
+                    None => Ok(()),
+                }
+            }
+            Command::Archive { bug } => write!(f, "archive {}", bug),
+            Command::Unarchive { bug } => write!(f, "unarchive {}", bug),
+            Command::User { user } => write!(f, "user {}", user),
+            Command::Usertags { bug, action, tags } => {
+                write!(f, "usertags {} {}", bug, action)?;
+                if !tags.is_empty() {
+                    write!(f, " {}", tags.join(" "))?;
+                }
+                Ok(())
+            }
+            Command::Usercategory {
+                name,
+                hidden,
+                sections,
+            } => {
+                write!(f, "usercategory {}", name)?;
+                if *hidden {
+                    write!(f, " [hidden]")?;
+                }
+                for section in sections {
+                    write!(f, "\n * {}", section.title)?;
+                    if let Some(prefix) = &section.prefix {
+                        write!(f, " [{}]", prefix)?;
+                    }
+                    for selection in section.selections.iter() {
+                        write!(f, "\n  + {}", selection.title)?;
+                        match (&selection.order, &selection.selection) {
+                            (Some(order), Some(s)) => write!(f, " [{}:{}]", order, s)?,
+                            (None, Some(s)) => write!(f, " [{}]", s)?,
+                            (Some(order), None) => write!(f, " [{}:]", order)?,
+                            (None, None) => {}
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn invalid(msg: String) -> Result<(), Error> {
+    Err(Error::ValidationError(msg))
+}
+
+pub(crate) fn validate_package(package: &str) -> Result<(), Error> {
+    let name = package.strip_prefix("src:").unwrap_or(package);
+    if !regex_is_match!(r"^[a-z0-9][a-z0-9+.-]+$", name) {
+        return invalid(format!("Invalid package name: {:?}", package));
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_text(what: &str, text: &str) -> Result<(), Error> {
+    if text.trim().is_empty() {
+        return invalid(format!("{} must not be empty", what));
+    }
+    if text.contains('\n') || text.contains('\r') {
+        return invalid(format!("{} must not contain newlines", what));
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_word(what: &str, word: &str) -> Result<(), Error> {
+    validate_text(what, word)?;
+    if word.split_whitespace().count() != 1 {
+        return invalid(format!("{} must not contain whitespace: {:?}", what, word));
+    }
+    Ok(())
+}
+
+impl Command {
+    /// Returns the bugs this command operates on
+    pub fn bugs(&self) -> Vec<BugId> {
+        match self {
+            Command::Merge { bugs } | Command::ForceMerge { bugs } => bugs.clone(),
+            Command::Block { bug, blockers } | Command::Unblock { bug, blockers } => {
+                std::iter::once(*bug)
+                    .chain(blockers.iter().copied())
+                    .collect()
+            }
+            Command::Retitle { bug, .. }
+            | Command::Severity { bug, .. }
+            | Command::Tags { bug, .. }
+            | Command::Unmerge { bug }
+            | Command::Reassign { bug, .. }
+            | Command::Affects { bug, .. }
+            | Command::Found { bug, .. }
+            | Command::NotFound { bug, .. }
+            | Command::Fixed { bug, .. }
+            | Command::NotFixed { bug, .. }
+            | Command::Owner { bug, .. }
+            | Command::NoOwner { bug }
+            | Command::Forwarded { bug, .. }
+            | Command::NotForwarded { bug }
+            | Command::Close { bug, .. }
+            | Command::Reopen { bug, .. }
+            | Command::Archive { bug }
+            | Command::Unarchive { bug }
+            | Command::Usertags { bug, .. } => vec![*bug],
+            Command::User { .. } | Command::Usercategory { .. } => vec![],
+        }
+    }
+
+    /// Checks that the command is well-formed
+    ///
+    /// This only checks the command itself; use [`ControlMessage::validate`] to
+    /// also check commands that depend on earlier commands.
+    pub fn validate(&self) -> Result<(), Error> {
+        match self {
+            Command::Retitle { title, .. } => validate_text("Title", title),
+            Command::Tags { action, tags, .. } => {
+                if tags.is_empty() && *action != TagAction::Set {
+                    return invalid("tags requires at least one tag".to_string());
+                }
+                Ok(())
+            }
+            Command::Merge { bugs } | Command::ForceMerge { bugs } => {
+                let mut unique = bugs.clone();
+                unique.sort();
+                unique.dedup();
+                if unique.len() < 2 {
+                    return invalid(format!(
+                        "{} requires at least two distinct bugs",
+                        if matches!(self, Command::Merge { .. }) {
+                            "merge"
+                        } else {
+                            "forcemerge"
+                        }
+                    ));
+                }
+                Ok(())
+            }
+            Command::Reassign { packages, .. } => {
+                if packages.is_empty() {
+                    return invalid("reassign requires at least one package".to_string());
+                }
+                packages.iter().try_for_each(|p| validate_package(p))
+            }
+            Command::Affects {
+                action, packages, ..
+            } => {
+                if packages.is_empty() && *action != TagAction::Set {
+                    return invalid("affects requires at least one package".to_string());
+                }
+                packages.iter().try_for_each(|p| validate_package(p))
+            }
+            Command::Unblock { blockers, .. } if blockers.is_empty() => {
+                invalid("unblock requires at least one blocking bug".to_string())
+            }
+            Command::Block { bug, blockers } | Command::Unblock { bug, blockers } => {
+                if blockers.is_empty() {
+                    return invalid("block requires at least one blocking bug".to_string());
+                }
+                if blockers.contains(bug) {
+                    return invalid(format!("Bug {} can not block itself", bug));
+                }
+                Ok(())
+            }
+            Command::Owner { owner, .. } => validate_word("Owner", owner),
+            Command::Forwarded { address, .. } => validate_text("Forwarded address", address),
+            Command::Reopen {
+                submitter: Some(ReopenSubmitter::Address(address)),
+                ..
+            } => validate_word("Submitter", address),
+            Command::User { user } => validate_word("User", user),
+            Command::Usertags { action, tags, .. } => {
+                if tags.is_empty() && *action != TagAction::Set {
+                    return invalid("usertags requires at least one tag".to_string());
+                }
+                tags.iter().try_for_each(|t| validate_word("Usertag", t))
+            }
+            Command::Usercategory { name, sections, .. } => {
+                validate_word("Usercategory name", name)?;
+                for section in sections {
+                    validate_text("Usercategory section title", &section.title)?;
+                    for selection in section.selections.iter() {
+                        validate_text("Usercategory selection title", &selection.title)?;
+                    }
+                }
+                Ok(())
+            }
+            Command::Severity { .. }
+            | Command::Unmerge { .. }
+            | Command::Found { .. }
+            | Command::NotFound { .. }
+            | Command::Fixed { .. }
+            | Command::NotFixed { .. }
+            | Command::NoOwner { .. }
+            | Command::NotForwarded { .. }
+            | Command::Close { .. }
+            | Command::Reopen { .. }
+            | Command::Archive { .. }
+            | Command::Unarchive { .. } => Ok(()),
+        }
+    }
+}
+
+/// A message to control@, consisting of a list of commands
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct ControlMessage {
+    /// The commands, in the order they will be processed
+    pub commands: Vec<Command>,
+}
+
+/// The state of a bug that is relevant for merging
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct MergeState {
+    package: Option<String>,
+    severity: Option<String>,
+    done: bool,
+}
+
+impl ControlMessage {
+    /// Creates an empty control message
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a command to the message
+    pub fn command(mut self, command: Command) -> Self {
+        self.commands.push(command);
+        self
+    }
+
+    /// Checks that all commands are well-formed
+    ///
+    /// In addition to [`Command::validate`], this checks that usertags and
+    /// usercategory commands are preceded by a user command.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.commands.is_empty() {
+            return invalid("Control message has no commands".to_string());
+        }
+        let mut user = false;
+        for command in self.commands.iter() {
+            command.validate()?;
+            match command {
+                Command::User { .. } => user = true,
+                Command::Usertags { .. } | Command::Usercategory { .. } if !user => {
+                    return invalid(format!("{:?} requires a preceding user command", command));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the commands against the current state of the bugs they affect
+    ///
+    /// All bugs referenced by the commands must be present in `reports`. Bugs to
+    /// be merged must be assigned to the same package, and (except for
+    /// forcemerge) have the same severity and be in the same open or done state.
+    /// Changes made by earlier commands in the same message (reassign, severity,
+    /// close and reopen) are taken into account.
+    pub fn validate_against(&self, reports: &HashMap<BugId, BugReport>) -> Result<(), Error> {
+        self.validate()?;
+        let mut states: HashMap<BugId, MergeState> = HashMap::new();
+        for command in self.commands.iter() {
+            for bug in command.bugs() {
+                if states.contains_key(&bug) {
+                    continue;
+                }
+                let report = reports
+                    .get(&bug)
+                    .ok_or_else(|| Error::ValidationError(format!("Unknown bug {}", bug)))?;
+                states.insert(
+                    bug,
+                    MergeState {
+                        package: report.package.clone(),
+                        severity: report.severity.clone(),
+                        done: report
+                            .done
+                            .as_deref()
+                            .map(|d| !d.is_empty())
+                            .unwrap_or(false),
+                    },
+                );
+            }
+            match command {
+                Command::Reassign { bug, packages, .. } => {
+                    states.get_mut(bug).unwrap().package = Some(packages.join(","));
+                }
+                Command::Severity { bug, severity } => {
+                    states.get_mut(bug).unwrap().severity = Some(severity.to_string());
+                }
+                Command::Close { bug, .. } => states.get_mut(bug).unwrap().done = true,
+                Command::Reopen { bug, .. } => states.get_mut(bug).unwrap().done = false,
+                Command::Merge { bugs } | Command::ForceMerge { bugs } => {
+                    let force = matches!(command, Command::ForceMerge { .. });
+                    let first = &states[&bugs[0]];
+                    for other in bugs[1..].iter() {
+                        let state = &states[other];
+                        if state.package != first.package {
+                            return invalid(format!(
+                                "Bugs {} and {} are assigned to different packages ({:?} and {:?})",
+                                bugs[0], other, first.package, state.package
+                            ));
+                        }
+                        if force {
+                            continue;
+                        }
+                        if state.severity != first.severity {
+                            return invalid(format!(
+                                "Bugs {} and {} have different severities ({:?} and {:?})",
+                                bugs[0], other, first.severity, state.severity
+                            ));
+                        }
+                        if state.done != first.done {
+                            return invalid(format!(
+                                "Bugs {} and {} are not both open or both done",
+                                bugs[0], other
+                            ));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates the commands and builds a message sending them to `to`
+    ///
+    /// `to` is usually the control@ address of the BTS, see
+    /// [`crate::mail::BtsAddresses::control`].
+    pub fn mail(&self, from: &str, to: &str, subject: &str) -> Result<Mail, Error> {
+        self.validate()?;
+        let mut mail = Mail::new(from, to, subject);
+        mail.body = self.body();
+        Ok(mail)
+    }
+
+    /// Renders the body of the control message, terminated by `thanks`
+    pub fn body(&self) -> String {
+        let mut body = String::new();
+        for command in self.commands.iter() {
+            body.push_str(&command.to_string());
+            body.push('\n');
+        }
+        body.push_str("thanks\n");
+        body
+    }
+}
+
+impl std::str::FromStr for SourceVersion {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (source, version) = match s.split_once('/') {
+            Some((source, version)) => (Some(source.to_string()), version),
+            None => (None, s),
+        };
+        let version = version
+            .parse()
+            .map_err(|_| Error::ParseError(format!("Invalid version: {:?}", s)))?;
+        Ok(SourceVersion { source, version })
+    }
+}
+
+/// A command parsed from the body of a control message
+#[derive(Debug)]
+pub struct ParsedCommand {
+    /// The line the command was parsed from (several lines for usercategory)
+    pub line: String,
+    /// The user in effect for this command, as set by the last `user` command
+    pub user: Option<String>,
+    /// The command, or the reason it could not be parsed
+    pub command: Result<Command, Error>,
+}
+
+fn parse_error<T>(msg: String) -> Result<T, Error> {
+    Err(Error::ParseError(msg))
+}
+
+/// Parses a bug number, optionally prefixed with `#`
+///
+/// `-1` refers to the bug created by the message the commands are part of.
+fn parse_bug(s: &str, new_bug: Option<BugId>) -> Result<BugId, Error> {
+    let s = s.strip_prefix('#').unwrap_or(s);
+    if s == "-1" {
+        return new_bug.ok_or_else(|| {
+            Error::ParseError("-1 can only be used for a newly created bug".to_string())
+        });
+    }
+    match s.parse::<BugId>() {
+        Ok(bug) if bug > 0 => Ok(bug),
+        _ => parse_error(format!("Invalid bug number: {:?}", s)),
+    }
+}
+
+/// Splits a list of values separated by whitespace or commas
+fn split_list(s: &str) -> Vec<&str> {
+    s.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+fn parse_bugs(s: &str, new_bug: Option<BugId>) -> Result<Vec<BugId>, Error> {
+    split_list(s)
+        .into_iter()
+        .map(|b| parse_bug(b, new_bug))
+        .collect()
+}
+
+/// Splits the leading bug number off the arguments of a command
+fn split_bug(args: &str, new_bug: Option<BugId>) -> Result<(BugId, &str), Error> {
+    let (bug, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    Ok((parse_bug(bug, new_bug)?, rest.trim()))
+}
+
+/// Parses the arguments of a command that takes nothing but a bug number
+fn single_bug(verb: &str, args: &str, new_bug: Option<BugId>) -> Result<BugId, Error> {
+    let (bug, rest) = split_bug(args, new_bug)?;
+    if !rest.is_empty() {
+        return parse_error(format!("Unexpected arguments to {}: {:?}", verb, rest));
+    }
+    Ok(bug)
+}
+
+fn required<'a>(verb: &str, what: &str, value: &'a str) -> Result<&'a str, Error> {
+    if value.is_empty() {
+        return parse_error(format!("{} requires {}", verb, what));
+    }
+    Ok(value)
+}
+
+/// Splits an optional `+`, `-` or `=` off the start of a list of values
+fn split_action(s: &str) -> (TagAction, &str) {
+    if let Some(rest) = s.strip_prefix('+') {
+        (TagAction::Add, rest.trim_start())
+    } else if let Some(rest) = s.strip_prefix('-') {
+        (TagAction::Remove, rest.trim_start())
+    } else if let Some(rest) = s.strip_prefix('=') {
+        (TagAction::Set, rest.trim_start())
+    } else {
+        (TagAction::Add, s)
+    }
+}
+
+fn optional_version(args: &str) -> Result<Option<SourceVersion>, Error> {
+    if args.is_empty() {
+        Ok(None)
+    } else {
+        args.parse().map(Some)
+    }
+}
+
+/// Parses a single-line command
+fn parse_command(line: &str, new_bug: Option<BugId>) -> Result<Command, Error> {
+    let (verb, args) = line
+        .split_once(char::is_whitespace)
+        .map(|(verb, args)| (verb, args.trim()))
+        .unwrap_or((line, ""));
+    let verb = verb.to_ascii_lowercase();
+    let verb = verb.as_str();
+    match verb {
+        "retitle" => {
+            let (bug, title) = split_bug(args, new_bug)?;
+            let title = required(verb, "a title", title)?;
+            Ok(Command::Retitle {
+                bug,
+                title: title.to_string(),
+            })
+        }
+        "severity" => {
+            let (bug, severity) = split_bug(args, new_bug)?;
+            let severity = severity
+                .to_ascii_lowercase()
+                .parse()
+                .map_err(|_| Error::ParseError(format!("Unknown severity: {:?}", severity)))?;
+            Ok(Command::Severity { bug, severity })
+        }
+        "tag" | "tags" => {
+            let (bug, args) = split_bug(args, new_bug)?;
+            let (action, args) = split_action(args);
+            let tags = split_list(args)
+                .into_iter()
+                .map(|t| {
+                    t.parse()
+                        .map_err(|_| Error::ParseError(format!("Unknown tag: {:?}", t)))
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(Command::Tags { bug, action, tags })
+        }
+        "merge" => Ok(Command::Merge {
+            bugs: parse_bugs(args, new_bug)?,
+        }),
+        "forcemerge" => Ok(Command::ForceMerge {
+            bugs: parse_bugs(args, new_bug)?,
+        }),
+        "unmerge" => Ok(Command::Unmerge {
+            bug: single_bug(verb, args, new_bug)?,
+        }),
+        "reassign" => {
+            let (bug, args) = split_bug(args, new_bug)?;
+            let mut words = required(verb, "a package", args)?.split_whitespace();
+            let packages = words
+                .next()
+                .unwrap_or_default()
+                .split(',')
+                .filter(|p| !p.is_empty())
+                .map(|p| p.to_string())
+                .collect();
+            let version = optional_version(words.next().unwrap_or_default())?;
+            Ok(Command::Reassign {
+                bug,
+                packages,
+                version,
+            })
+        }
+        "affects" => {
+            let (bug, args) = split_bug(args, new_bug)?;
+            let (action, args) = split_action(args);
+            Ok(Command::Affects {
+                bug,
+                action,
+                packages: split_list(args)
+                    .into_iter()
+                    .map(|p| p.to_string())
+                    .collect(),
+            })
+        }
+        "block" | "unblock" => {
+            let (bug, args) = split_bug(args, new_bug)?;
+            let blockers = match args.split_once(char::is_whitespace) {
+                Some(("by", blockers)) | Some(("with", blockers)) => parse_bugs(blockers, new_bug)?,
+                _ => return parse_error(format!("{} requires 'by' and bug numbers", verb)),
+            };
+            if verb == "block" {
+                Ok(Command::Block { bug, blockers })
+            } else {
+                Ok(Command::Unblock { bug, blockers })
+            }
+        }
+        "found" | "close" => {
+            let (bug, args) = split_bug(args, new_bug)?;
+            let version = optional_version(args)?;
+            if verb == "found" {
+                Ok(Command::Found { bug, version })
+            } else {
+                Ok(Command::Close { bug, version })
+            }
+        }
+        "notfound" | "fixed" | "notfixed" => {
+            let (bug, args) = split_bug(args, new_bug)?;
+            let version = required(verb, "a version", args)?.parse()?;
+            Ok(match verb {
+                "notfound" => Command::NotFound { bug, version },
+                "fixed" => Command::Fixed { bug, version },
+                _ => Command::NotFixed { bug, version },
+            })
+        }
+        "owner" => {
+            let (bug, owner) = split_bug(args, new_bug)?;
+            Ok(Command::Owner {
+                bug,
+                owner: required(verb, "an owner", owner)?.to_string(),
+            })
+        }
+        "noowner" => Ok(Command::NoOwner {
+            bug: single_bug(verb, args, new_bug)?,
+        }),
+        "forwarded" => {
+            let (bug, address) = split_bug(args, new_bug)?;
+            Ok(Command::Forwarded {
+                bug,
+                address: required(verb, "an address", address)?.to_string(),
+            })
+        }
+        "notforwarded" => Ok(Command::NotForwarded {
+            bug: single_bug(verb, args, new_bug)?,
+        }),
+        "reopen" => {
+            let (bug, submitter) = split_bug(args, new_bug)?;
+            let submitter = match submitter {
+                "" | "=" => None,
+                "!" => Some(ReopenSubmitter::Sender),
+                address => Some(ReopenSubmitter::Address(address.to_string())),
+            };
+            Ok(Command::Reopen { bug, submitter })
+        }
+        "archive" => Ok(Command::Archive {
+            bug: single_bug(verb, args, new_bug)?,
+        }),
+        "unarchive" => Ok(Command::Unarchive {
+            bug: single_bug(verb, args, new_bug)?,
+        }),
+        "user" => Ok(Command::User {
+            user: required(verb, "a user", args)?.to_string(),
+        }),
+        "usertag" | "usertags" => {
+            let (bug, args) = split_bug(args, new_bug)?;
+            let (action, args) = split_action(args);
+            Ok(Command::Usertags {
+                bug,
+                action,
+                tags: split_list(args)
+                    .into_iter()
+                    .map(|t| t.to_string())
+                    .collect(),
+            })
+        }
+        _ => parse_error(format!("Unknown command: {:?}", verb)),
+    }
+}
+
+/// Splits a trailing `[...]` off a usercategory line
+fn split_brackets(s: &str) -> (&str, Option<&str>) {
+    match regex_captures!(r"^(.*?)\s*\[([^\]]*)\]$", s) {
+        Some((_, title, inner)) => (title, Some(inner)),
+        None => (s, None),
+    }
+}
+
+/// Parses a usercategory command, with its section and selection lines
+fn parse_usercategory(args: &str, lines: &[&str]) -> Result<Command, Error> {
+    let (name, hidden) = match split_brackets(args) {
+        (name, Some("hidden")) => (name, true),
+        (name, None) => (name, false),
+        (_, Some(other)) => {
+            return parse_error(format!("Unknown usercategory option: {:?}", other));
+        }
+    };
+    let name = required("usercategory", "a name", name)?;
+    let mut sections: Vec<CategorySection> = Vec::new();
+    for line in lines {
+        if let Some(section) = line.strip_prefix('*') {
+            let (title, prefix) = split_brackets(section.trim());
+            sections.push(CategorySection {
+                title: title.to_string(),
+                prefix: prefix.map(|p| p.to_string()),
+                selections: vec![],
+            });
+        } else if let Some(selection) = line.strip_prefix('+') {
+            let section = match sections.last_mut() {
+                Some(section) => section,
+                None => return parse_error(format!("Selection outside of a section: {:?}", line)),
+            };
+            let (title, inner) = split_brackets(selection.trim());
+            let (order, selection) = match inner.map(|i| i.split_once(':')) {
+                Some(Some((order, selection))) => match order.parse() {
+                    Ok(order) => (Some(order), selection),
+                    Err(_) => return parse_error(format!("Invalid selection order: {:?}", order)),
+                },
+                Some(None) => (None, inner.unwrap_or_default()),
+                None => (None, ""),
+            };
+            section.selections.push(CategorySelection {
+                title: title.to_string(),
+                order,
+                selection: Some(selection)
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.to_string()),
+            });
+        }
+    }
+    Ok(Command::Usercategory {
+        name: name.to_string(),
+        hidden,
+        sections,
+    })
+}
+
+/// Returns whether a line ends the list of commands
+fn is_terminator(line: &str) -> bool {
+    regex_is_match!(
+        r"^(?:stop|quit|--|thank(?:s|\s*you)?|kthxbye)\.*\s*$"i,
+        line
+    )
+}
+
+/// Parses the commands in the body of a control message
+///
+/// Empty lines and lines starting with `#` are skipped, and processing stops at
+/// a line consisting of `thanks` (or `quit`, `stop`, or a `--` signature
+/// separator), like the BTS does. `new_bug` is the bug that `-1` refers to, for messages that create a
+/// new bug.
+///
+/// Lines that can not be parsed are returned with an error rather than
+/// aborting, as the BTS also continues after a malformed command.
+pub fn parse_control(body: &str, new_bug: Option<BugId>) -> Vec<ParsedCommand> {
+    let mut ret = Vec::new();
+    let mut user: Option<String> = None;
+    let mut lines = body.lines().map(str::trim).peekable();
+    while let Some(line) = lines.next() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if is_terminator(line) {
+            break;
+        }
+        let (verb, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let (text, command) = if verb.eq_ignore_ascii_case("usercategory") {
+            let mut continuation = Vec::new();
+            while let Some(next) = lines.next_if(|l| l.starts_with('*') || l.starts_with('+')) {
+                continuation.push(next);
+            }
+            let text = std::iter::once(line)
+                .chain(continuation.iter().copied())
+                .collect::<Vec<_>>()
+                .join("\n");
+            (text, parse_usercategory(args.trim(), &continuation))
+        } else {
+            (line.to_string(), parse_command(line, new_bug))
+        };
+        if let Ok(Command::User { user: new_user }) = &command {
+            user = Some(new_user.clone());
+        }
+        ret.push(ParsedCommand {
+            line: text,
+            user: user.clone(),
+            command,
+        });
+    }
+    ret
+}
+
+/// Parses the `Control:` pseudo-headers at the start of a message body
+///
+/// Messages to submit@ or nnn@ can contain control commands in `Control:`
+/// pseudo-headers; `-1` in these refers to `bug`, the bug the message was
+/// filed as or sent to.
+pub fn parse_control_pseudo_headers(body: &str, bug: BugId) -> Vec<ParsedCommand> {
+    let commands = body
+        .lines()
+        .skip_while(|l| l.trim().is_empty())
+        .take_while(|l| !l.trim().is_empty())
+        .filter_map(|l| regex_captures!(r"^Control:\s*(.*)$"i, l))
+        .map(|(_, command)| command)
+        .collect::<Vec<_>>()
+        .join("\n");
+    parse_control(&commands, Some(bug))
+}
+
+/// The response to a single command in a "Processed:" transcript
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TranscriptEntry {
+    /// The command as echoed by the BTS, without the leading `>`
+    pub line: String,
+    /// The response of the BTS to the command
+    pub response: String,
+}
+
+impl TranscriptEntry {
+    /// Returns whether the BTS reported an error for this command
+    ///
+    /// The BTS does not mark errors in a machine-readable way, so this looks
+    /// for the phrases it uses when a command fails.
+    pub fn is_error(&self) -> bool {
+        regex_is_match!(
+            r"(?m)^(?:Failed to |Unknown command|Unable to |No valid user|Bug number \S+ not found)",
+            &self.response
+        )
+    }
+}
+
+/// Parses the transcript the BTS sends in reply to a control message
+///
+/// The BTS echoes each command prefixed with `>`, followed by its response.
+pub fn parse_transcript(body: &str) -> Vec<TranscriptEntry> {
+    let mut entries: Vec<TranscriptEntry> = Vec::new();
+    for line in body.lines() {
+        if let Some(command) = line.strip_prefix('>') {
+            let command = command.trim();
+            // Continuation lines of usercategory commands are echoed as well
+            if let Some(last) = entries.last_mut() {
+                if last.response.is_empty()
+                    && (command.starts_with('*') || command.starts_with('+'))
+                {
+                    last.line.push('\n');
+                    last.line.push_str(command);
+                    continue;
+                }
+            }
+            entries.push(TranscriptEntry {
+                line: command.to_string(),
+                response: String::new(),
+            });
+        } else if let Some(last) = entries.last_mut() {
+            if !last.response.is_empty() || !line.trim().is_empty() {
+                last.response.push_str(line);
+                last.response.push('\n');
+            }
+        }
+    }
+    for entry in entries.iter_mut() {
+        entry.response.truncate(entry.response.trim_end().len());
+    }
+    entries
+}
+
+/// A parsed command together with the response of the BTS to it
+#[derive(Debug)]
+pub struct CommandOutcome {
+    /// The command
+    pub command: ParsedCommand,
+    /// The transcript entry for the command, if it was found in the transcript
+    pub entry: Option<TranscriptEntry>,
+}
+
+impl CommandOutcome {
+    /// Returns whether the command succeeded, or `None` if that is unknown
+    pub fn succeeded(&self) -> Option<bool> {
+        self.entry.as_ref().map(|e| !e.is_error())
+    }
+}
+
+/// Pairs parsed commands with the entries of the transcript for their message
+///
+/// Commands are matched to transcript entries in order, comparing the command
+/// lines with whitespace normalised.
+pub fn pair_with_transcript(
+    commands: Vec<ParsedCommand>,
+    transcript: &[TranscriptEntry],
+) -> Vec<CommandOutcome> {
+    fn normalize(s: &str) -> String {
+        s.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+    let mut next = 0;
+    commands
+        .into_iter()
+        .map(|command| {
+            let line = normalize(&command.line);
+            let entry = transcript[next..]
+                .iter()
+                .position(|e| normalize(&e.line) == line)
+                .map(|i| {
+                    next += i + 1;
+                    transcript[next - 1].clone()
+                });
+            CommandOutcome { command, entry }
+        })
+        .collect()
+}
+
+/// Finds the "Processed:" transcript the BTS sent in reply to a control message
+pub fn find_transcript<'a>(message: &BugLog, logs: &'a [BugLog]) -> Option<&'a BugLog> {
+    let message_id = message.raw_header("Message-ID")?;
+    let message_id = message_id.trim();
+    if message_id.is_empty() {
+        return None;
+    }
+    logs.iter().find(|log| {
+        log.raw_header("Subject")
+            .map(|s| regex_is_match!(r"^Processed(?: \(with \d+ errors?\))?:", &s))
+            .unwrap_or(false)
+            && log
+                .raw_header("In-Reply-To")
+                .map(|r| r.contains(message_id))
+                .unwrap_or(false)
+    })
+}
+
+/// Parses a control message from a bug log and determines which commands succeeded
+///
+/// The transcript is looked up in `logs` with [`find_transcript`]; if it can
+/// not be found, the outcome of every command is unknown.
+pub fn control_outcomes(message: &BugLog, logs: &[BugLog]) -> Vec<CommandOutcome> {
+    let commands = parse_control(&message.body, None);
+    let transcript = find_transcript(message, logs)
+        .map(|t| parse_transcript(&t.body))
+        .unwrap_or_default();
+    pair_with_transcript(commands, &transcript)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(s: &str) -> SourceVersion {
+        SourceVersion::from(s.parse::<Version>().unwrap())
+    }
+
+    #[test]
+    fn test_render_commands() {
+        let cases = vec![
+            (
+                Command::Retitle {
+                    bug: 1,
+                    title: "foo: crashes".to_string(),
+                },
+                "retitle 1 foo: crashes",
+            ),
+            (
+                Command::Severity {
+                    bug: 1,
+                    severity: Severity::Grave,
+                },
+                "severity 1 grave",
+            ),
+            (
+                Command::Tags {
+                    bug: 1,
+                    action: TagAction::Remove,
+                    tags: vec![Tag::Moreinfo, Tag::Ignore("trixie".to_string())],
+                },
+                "tags 1 - moreinfo trixie-ignore",
+            ),
+            (
+                Command::Tags {
+                    bug: 1,
+                    action: TagAction::Set,
+                    tags: vec![],
+                },
+                "tags 1 =",
+            ),
+            (
+                Command::Merge {
+                    bugs: vec![1, 2, 3],
+                },
+                "merge 1 2 3",
+            ),
+            (Command::ForceMerge { bugs: vec![1, 2] }, "forcemerge 1 2"),
+            (Command::Unmerge { bug: 1 }, "unmerge 1"),
+            (
+                Command::Reassign {
+                    bug: 1,
+                    packages: vec!["foo".to_string(), "src:bar".to_string()],
+                    version: Some(SourceVersion {
+                        source: Some("foo".to_string()),
+                        version: "1.0-1".parse().unwrap(),
+                    }),
+                },
+                "reassign 1 foo,src:bar foo/1.0-1",
+            ),
+            (
+                Command::Affects {
+                    bug: 1,
+                    action: TagAction::Add,
+                    packages: vec!["baz".to_string()],
+                },
+                "affects 1 + baz",
+            ),
+            (
+                Command::Block {
+                    bug: 1,
+                    blockers: vec![2, 3],
+                },
+                "block 1 by 2 3",
+            ),
+            (
+                Command::Unblock {
+                    bug: 1,
+                    blockers: vec![2],
+                },
+                "unblock 1 by 2",
+            ),
+            (
+                Command::Found {
+                    bug: 1,
+                    version: Some(version("1.0-1")),
+                },
+                "found 1 1.0-1",
+            ),
+            (
+                Command::Found {
+                    bug: 1,
+                    version: None,
+                },
+                "found 1",
+            ),
+            (
+                Command::NotFound {
+                    bug: 1,
+                    version: version("1.0-1"),
+                },
+                "notfound 1 1.0-1",
+            ),
+            (
+                Command::Fixed {
+                    bug: 1,
+                    version: version("1:2.0-1"),
+                },
+                "fixed 1 1:2.0-1",
+            ),
+            (
+                Command::NotFixed {
+                    bug: 1,
+                    version: version("2.0-1"),
+                },
+                "notfixed 1 2.0-1",
+            ),
+            (
+                Command::Owner {
+                    bug: 1,
+                    owner: "!".to_string(),
+                },
+                "owner 1 !",
+            ),
+            (Command::NoOwner { bug: 1 }, "noowner 1"),
+            (
+                Command::Forwarded {
+                    bug: 1,
+                    address: "https://example.com/issues/1".to_string(),
+                },
+                "forwarded 1 https://example.com/issues/1",
+            ),
+            (Command::NotForwarded { bug: 1 }, "notforwarded 1"),
+            (
+                Command::Close {
+                    bug: 1,
+                    version: Some(version("2.0-1")),
+                },
+                "close 1 2.0-1",
+            ),
+            (
+                Command::Reopen {
+                    bug: 1,
+                    submitter: Some(ReopenSubmitter::Sender),
+                },
+                "reopen 1 !",
+            ),
+            (
+                Command::Reopen {
+                    bug: 1,
+                    submitter: None,
+                },
+                "reopen 1",
+            ),
+            (Command::Archive { bug: 1 }, "archive 1"),
+            (Command::Unarchive { bug: 1 }, "unarchive 1"),
+            (
+                Command::User {
+                    user: "debian-qa@lists.debian.org".to_string(),
+                },
+                "user debian-qa@lists.debian.org",
+            ),
+            (
+                Command::Usertags {
+                    bug: 1,
+                    action: TagAction::Add,
+                    tags: vec!["needs-review".to_string()],
+                },
+                "usertags 1 + needs-review",
+            ),
+        ];
+        for (command, expected) in cases {
+            assert_eq!(command.to_string(), expected);
+            command.validate().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_render_usercategory() {
+        let command = Command::Usercategory {
+            name: "reviews".to_string(),
+            hidden: true,
+            sections: vec![CategorySection {
+                title: "Review state".to_string(),
+                prefix: Some("tag=".to_string()),
+                selections: vec![
+                    CategorySelection {
+                        title: "Needs review".to_string(),
+                        order: Some(1),
+                        selection: Some("needs-review".to_string()),
+                    },
+                    CategorySelection {
+                        title: "Other".to_string(),
+                        order: None,
+                        selection: None,
+                    },
+                ],
+            }],
+        };
+        assert_eq!(
+            command.to_string(),
+            "usercategory reviews [hidden]\n * Review state [tag=]\n  + Needs review [1:needs-review]\n  + Other"
+        );
+    }
+
+    #[test]
+    fn test_validate_command() {
+        assert!(Command::Merge { bugs: vec![1] }.validate().is_err());
+        assert!(Command::Merge { bugs: vec![1, 1] }.validate().is_err());
+        assert!(Command::Retitle {
+            bug: 1,
+            title: "two\nlines".to_string()
+        }
+        .validate()
+        .is_err());
+        assert!(Command::Retitle {
+            bug: 1,
+            title: " ".to_string()
+        }
+        .validate()
+        .is_err());
+        assert!(Command::Tags {
+            bug: 1,
+            action: TagAction::Add,
+            tags: vec![]
+        }
+        .validate()
+        .is_err());
+        assert!(Command::Reassign {
+            bug: 1,
+            packages: vec!["Not A Package".to_string()],
+            version: None
+        }
+        .validate()
+        .is_err());
+        assert!(Command::Block {
+            bug: 1,
+            blockers: vec![1]
+        }
+        .validate()
+        .is_err());
+        assert_eq!(
+            Command::Unblock {
+                bug: 1,
+                blockers: vec![]
+            }
+            .validate()
+            .unwrap_err()
+            .to_string(),
+            "Validation Error: unblock requires at least one blocking bug"
+        );
+        assert!(Command::Owner {
+            bug: 1,
+            owner: "two words".to_string()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_message_body() {
+        let message = ControlMessage::new()
+            .command(Command::User {
+                user: "me@example.com".to_string(),
+            })
+            .command(Command::Usertags {
+                bug: 1,
+                action: TagAction::Add,
+                tags: vec!["foo".to_string()],
+            });
+        message.validate().unwrap();
+        assert_eq!(
+            message.body(),
+            "user me@example.com\nusertags 1 + foo\nthanks\n"
+        );
+    }
+
+    #[test]
+    fn test_message_mail() {
+        let message = ControlMessage::new().command(Command::NoOwner { bug: 1 });
+        let mail = message
+            .mail(
+                "jane@example.com",
+                &crate::mail::BtsAddresses::default().control(),
+                "Releasing #1",
+            )
+            .unwrap();
+        assert_eq!(mail.to, vec!["control@bugs.debian.org"]);
+        assert_eq!(mail.body, "noowner 1\nthanks\n");
+        assert!(ControlMessage::new()
+            .mail("jane@example.com", "control@bugs.debian.org", "nothing")
+            .is_err());
+    }
+
+    #[test]
+    fn test_message_validate() {
+        assert!(ControlMessage::new().validate().is_err());
+        let message = ControlMessage::new().command(Command::Usertags {
+            bug: 1,
+            action: TagAction::Add,
+            tags: vec!["foo".to_string()],
+        });
+        assert!(matches!(message.validate(), Err(Error::ValidationError(_))));
+    }
+
+    fn report(package: &str, severity: &str, done: Option<&str>) -> BugReport {
+        let mut xml = format!(
+            "<package>{}</package><severity>{}</severity>",
+            package, severity
+        );
+        if let Some(done) = done {
+            xml.push_str(&format!("<done>{}</done>", done));
+        }
+        BugReport::from_fields(&xml)
+    }
+
+    #[test]
+    fn test_validate_against_merge() {
+        let reports: HashMap<BugId, BugReport> = [
+            (1, report("foo", "normal", None)),
+            (2, report("foo", "normal", None)),
+            (3, report("foo", "serious", None)),
+            (4, report("bar", "normal", None)),
+            (5, report("foo", "normal", Some("a@example.com"))),
+        ]
+        .into_iter()
+        .collect();
+
+        let merge = |bugs: Vec<BugId>| ControlMessage::new().command(Command::Merge { bugs });
+        merge(vec![1, 2]).validate_against(&reports).unwrap();
+        assert!(merge(vec![1, 3]).validate_against(&reports).is_err());
+        assert!(merge(vec![1, 4]).validate_against(&reports).is_err());
+        assert!(merge(vec![1, 5]).validate_against(&reports).is_err());
+        assert!(merge(vec![1, 99]).validate_against(&reports).is_err());
+
+        ControlMessage::new()
+            .command(Command::ForceMerge { bugs: vec![1, 3] })
+            .validate_against(&reports)
+            .unwrap();
+        assert!(ControlMessage::new()
+            .command(Command::ForceMerge { bugs: vec![1, 4] })
+            .validate_against(&reports)
+            .is_err());
+
+        // Earlier commands in the same message are taken into account
+        ControlMessage::new()
+            .command(Command::Severity {
+                bug: 3,
+                severity: Severity::Normal,
+            })
+            .command(Command::Reassign {
+                bug: 4,
+                packages: vec!["foo".to_string()],
+                version: None,
+            })
+            .command(Command::Reopen {
+                bug: 5,
+                submitter: None,
+            })
+            .command(Command::Merge {
+                bugs: vec![1, 3, 4, 5],
+            })
+            .validate_against(&reports)
+            .unwrap();
+    }
+
+    fn commands(parsed: &[ParsedCommand]) -> Vec<&Command> {
+        parsed.iter().map(|p| p.command.as_ref().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_parse_control_terminators() {
+        for terminator in [
+            "thanks",
+            "Thank you.",
+            "thank",
+            "quit",
+            "stop",
+            "kthxbye",
+            "--",
+        ] {
+            let parsed = parse_control(&format!("close 1000\n{}\nclose 1001\n", terminator), None);
+            assert_eq!(parsed.len(), 1, "{:?}", terminator);
+        }
+
+        // Lines that merely start like a terminator do not end the commands
+        let parsed = parse_control(
+            "stopping 1000\nquitting\nthankfully 1000\n--force\nclose 1000\n",
+            None,
+        );
+        assert_eq!(parsed.len(), 5);
+        assert!(parsed[..4].iter().all(|p| p.command.is_err()));
+        assert_eq!(
+            parsed[4].command.as_ref().unwrap(),
+            &Command::Close {
+                bug: 1000,
+                version: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_control() {
+        let body = "# triage\n\
+                    severity #1000 Serious\n\
+                    \n\
+                    tags 1000 +patch, moreinfo\n\
+                    tag 1000 = \n\
+                    reassign 1000 src:foo,bar foo/1.0-1\n\
+                    block 1000 by 1001, 1002\n\
+                    found 1000\n\
+                    notfixed 1000 2.0-1\n\
+                    reopen 1000 =\n\
+                    retitle 1000 foo: crashes  on start\n\
+                    thanks\n\
+                    close 1000\n";
+        let parsed = parse_control(body, None);
+        assert_eq!(
+            commands(&parsed),
+            vec![
+                &Command::Severity {
+                    bug: 1000,
+                    severity: Severity::Serious
+                },
+                &Command::Tags {
+                    bug: 1000,
+                    action: TagAction::Add,
+                    tags: vec![Tag::Patch, Tag::Moreinfo]
+                },
+                &Command::Tags {
+                    bug: 1000,
+                    action: TagAction::Set,
+                    tags: vec![]
+                },
+                &Command::Reassign {
+                    bug: 1000,
+                    packages: vec!["src:foo".to_string(), "bar".to_string()],
+                    version: Some("foo/1.0-1".parse().unwrap())
+                },
+                &Command::Block {
+                    bug: 1000,
+                    blockers: vec![1001, 1002]
+                },
+                &Command::Found {
+                    bug: 1000,
+                    version: None
+                },
+                &Command::NotFixed {
+                    bug: 1000,
+                    version: version("2.0-1")
+                },
+                &Command::Reopen {
+                    bug: 1000,
+                    submitter: None
+                },
+                &Command::Retitle {
+                    bug: 1000,
+                    title: "foo: crashes  on start".to_string()
+                },
+            ]
+        );
+        assert_eq!(parsed[0].line, "severity #1000 Serious");
+    }
+
+    #[test]
+    fn test_parse_control_roundtrip() {
+        let message = ControlMessage::new()
+            .command(Command::Merge {
+                bugs: vec![1, 2, 3],
+            })
+            .command(Command::Affects {
+                bug: 1,
+                action: TagAction::Remove,
+                packages: vec!["foo".to_string()],
+            })
+            .command(Command::Fixed {
+                bug: 1,
+                version: version("1:2.0-1"),
+            })
+            .command(Command::Owner {
+                bug: 1,
+                owner: "!".to_string(),
+            })
+            .command(Command::Forwarded {
+                bug: 1,
+                address: "https://example.com/1".to_string(),
+            })
+            .command(Command::Reopen {
+                bug: 1,
+                submitter: Some(ReopenSubmitter::Sender),
+            })
+            .command(Command::Unarchive { bug: 1 });
+        let parsed = parse_control(&message.body(), None);
+        assert_eq!(
+            parsed
+                .into_iter()
+                .map(|p| p.command.unwrap())
+                .collect::<Vec<_>>(),
+            message.commands
+        );
+    }
+
+    #[test]
+    fn test_parse_control_errors() {
+        let parsed = parse_control(
+            "frobnicate 1000\nseverity 1000 annoying\nunmerge 1000 1001\ntags -1 patch\nnoowner 1000\n-- \nJane",
+            None,
+        );
+        assert_eq!(parsed.len(), 5);
+        assert!(matches!(parsed[0].command, Err(Error::ParseError(_))));
+        assert!(parsed[1].command.is_err());
+        assert!(parsed[2].command.is_err());
+        assert!(parsed[3].command.is_err());
+        assert_eq!(
+            parsed[4].command.as_ref().unwrap(),
+            &Command::NoOwner { bug: 1000 }
+        );
+    }
+
+    #[test]
+    fn test_parse_control_user() {
+        let body = "usertags 1 + foo\n\
+                    user me@example.com\n\
+                    usertag 1 bar\n\
+                    usercategory reviews [hidden]\n \
+                    * Review state [tag=]\n  \
+                    + Needs review [1:needs-review]\n  \
+                    + Other\n\
+                    user other@example.com\n\
+                    usertags 2 - baz\n";
+        let parsed = parse_control(body, None);
+        assert_eq!(
+            parsed.iter().map(|p| p.user.as_deref()).collect::<Vec<_>>(),
+            vec![
+                None,
+                Some("me@example.com"),
+                Some("me@example.com"),
+                Some("me@example.com"),
+                Some("other@example.com"),
+                Some("other@example.com"),
+            ]
+        );
+        assert_eq!(
+            parsed[3].command.as_ref().unwrap().to_string(),
+            "usercategory reviews [hidden]\n * Review state [tag=]\n  + Needs review [1:needs-review]\n  + Other"
+        );
+        assert_eq!(
+            parsed[3].line,
+            "usercategory reviews [hidden]\n* Review state [tag=]\n+ Needs review [1:needs-review]\n+ Other"
+        );
+        assert_eq!(
+            parsed[5].command.as_ref().unwrap(),
+            &Command::Usertags {
+                bug: 2,
+                action: TagAction::Remove,
+                tags: vec!["baz".to_string()]
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_control_pseudo_headers() {
+        let body = "\nPackage: foo\nVersion: 1.0-1\nControl: tags -1 patch\ncontrol: block -1 by 999\n\nControl: close -1\n";
+        let parsed = parse_control_pseudo_headers(body, 1000);
+        assert_eq!(
+            commands(&parsed),
+            vec![
+                &Command::Tags {
+                    bug: 1000,
+                    action: TagAction::Add,
+                    tags: vec![Tag::Patch]
+                },
+                &Command::Block {
+                    bug: 1000,
+                    blockers: vec![999]
+                },
+            ]
+        );
+    }
+
+    const TRANSCRIPT: &str = "Processing commands for control@bugs.debian.org:
+
+> severity 1000 serious
+Bug #1000 [foo] foo: crashes
+Severity set to 'serious' from 'normal'
+> tags  1000 + patch
+Bug #1000 [foo] foo: crashes
+Added tag(s) patch.
+> merge 1000 1001
+Failed to merge 1000: the following bugs have different severities
+> thanks
+Stopping processing here.
+
+Please contact me if you need assistance.
+";
+
+    #[test]
+    fn test_parse_transcript() {
+        let entries = parse_transcript(TRANSCRIPT);
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].line, "severity 1000 serious");
+        assert_eq!(
+            entries[0].response,
+            "Bug #1000 [foo] foo: crashes\nSeverity set to 'serious' from 'normal'"
+        );
+        assert!(!entries[0].is_error());
+        assert!(entries[2].is_error());
+    }
+
+    #[test]
+    fn test_control_outcomes() {
+        let message = BugLog {
+            header: "Message-ID: <cmd@example.com>\nTo: control@bugs.debian.org".to_string(),
+            msgnum: 5,
+            body: "severity 1000 serious\ntags 1000 + patch\nfound 1000 1.0\nmerge 1000 1001\nthanks\n"
+                .to_string(),
+        };
+        let transcript = BugLog {
+            header: "Subject: Processed (with 1 error): fix\nIn-Reply-To: <cmd@example.com>"
+                .to_string(),
+            msgnum: 10,
+            body: TRANSCRIPT.to_string(),
+        };
+        let unrelated = BugLog {
+            header: "Subject: Processed: other\nIn-Reply-To: <other@example.com>".to_string(),
+            msgnum: 7,
+            body: "> severity 1000 serious\nFailed to set severity\n".to_string(),
+        };
+        let logs = vec![message.clone(), unrelated, transcript];
+        assert_eq!(find_transcript(&message, &logs).unwrap().msgnum, 10);
+
+        let outcomes = control_outcomes(&message, &logs);
+        assert_eq!(
+            outcomes.iter().map(|o| o.succeeded()).collect::<Vec<_>>(),
+            vec![Some(true), Some(true), None, Some(false)]
+        );
+
+        let outcomes = control_outcomes(&message, &logs[..2]);
+        assert!(outcomes.iter().all(|o| o.succeeded().is_none()));
+    }
+}
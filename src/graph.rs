@@ -0,0 +1,397 @@
+//! Graphs of blocking relations between bugs
+//!
+//! Bugs can block other bugs, e.g. the bugs of a transition block its
+//! tracking bug. [`BlockGraph`] collects these relations from the `blocks` and
+//! `blockedby` fields of bug reports. The clients provide `crawl_blocks` to
+//! follow them from a set of starting bugs.
+//!
+//! Edges point from the blocking bug to the blocked one, so a topological
+//! order lists bugs in the order they have to be fixed.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use debbugs::blocking::Debbugs;
+//!
+//! let client = Debbugs::default();
+//! let graph = client.crawl_blocks(&[1000], 3).unwrap();
+//! std::fs::write("blocks.dot", graph.to_dot()).unwrap();
+//! ```
+
+use crate::{BugId, BugReport};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Parses a list of bug numbers as found in `blocks` and `blockedby`
+fn parse_bug_list(list: Option<&str>) -> Vec<BugId> {
+    list.unwrap_or_default()
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|b| b.trim_start_matches('#').parse().ok())
+        .collect()
+}
+
+/// Escapes a string for use in a JSON document
+fn json_string(s: &str) -> String {
+    let mut ret = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            '\r' => ret.push_str("\\r"),
+            '\t' => ret.push_str("\\t"),
+            c if (c as u32) < 0x20 => ret.push_str(&format!("\\u{:04x}", c as u32)),
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
+/// Escapes a string for use in a quoted DOT identifier
+fn dot_string(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+/// A graph of bugs and the bugs that block them
+#[derive(Debug, Default, Clone)]
+pub struct BlockGraph {
+    nodes: BTreeSet<BugId>,
+    edges: BTreeSet<(BugId, BugId)>,
+    reports: BTreeMap<BugId, BugReport>,
+}
+
+impl BlockGraph {
+    /// Creates an empty graph
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a graph from bug reports
+    pub fn from_reports(reports: &HashMap<BugId, BugReport>) -> Self {
+        let mut graph = Self::new();
+        for (bug, report) in reports {
+            graph.add_report(*bug, report.clone());
+        }
+        graph
+    }
+
+    /// Adds a bug and the relations its report lists
+    pub fn add_report(&mut self, bug: BugId, report: BugReport) {
+        for blocked in parse_bug_list(report.blocks.as_deref()) {
+            self.add_edge(bug, blocked);
+        }
+        for blocker in parse_bug_list(report.blockedby.as_deref()) {
+            self.add_edge(blocker, bug);
+        }
+        self.nodes.insert(bug);
+        self.reports.insert(bug, report);
+    }
+
+    /// Records that `blocker` blocks `blocked`
+    pub fn add_edge(&mut self, blocker: BugId, blocked: BugId) {
+        self.nodes.insert(blocker);
+        self.nodes.insert(blocked);
+        self.edges.insert((blocker, blocked));
+    }
+
+    /// Returns all bugs in the graph, in ascending order
+    pub fn bugs(&self) -> impl Iterator<Item = BugId> + '_ {
+        self.nodes.iter().copied()
+    }
+
+    /// Returns all relations as (blocker, blocked) pairs
+    pub fn edges(&self) -> impl Iterator<Item = (BugId, BugId)> + '_ {
+        self.edges.iter().copied()
+    }
+
+    /// Returns the report of a bug, if it has been added
+    pub fn report(&self, bug: BugId) -> Option<&BugReport> {
+        self.reports.get(&bug)
+    }
+
+    /// Returns the bugs that are referenced, but whose reports have not been added
+    pub fn unresolved(&self) -> Vec<BugId> {
+        self.nodes
+            .iter()
+            .copied()
+            .filter(|bug| !self.reports.contains_key(bug))
+            .collect()
+    }
+
+    /// Returns the bugs that block `bug`
+    pub fn blockers(&self, bug: BugId) -> Vec<BugId> {
+        self.edges
+            .iter()
+            .filter(|(_, blocked)| *blocked == bug)
+            .map(|(blocker, _)| *blocker)
+            .collect()
+    }
+
+    /// Returns the bugs that `bug` blocks
+    pub fn blocked(&self, bug: BugId) -> Vec<BugId> {
+        self.edges
+            .range((bug, BugId::MIN)..=(bug, BugId::MAX))
+            .map(|(_, blocked)| *blocked)
+            .collect()
+    }
+
+    /// Returns the groups of bugs that block each other, directly or indirectly
+    ///
+    /// Every cycle is returned as the sorted set of bugs it involves.
+    pub fn cycles(&self) -> Vec<Vec<BugId>> {
+        // Tarjan's algorithm for strongly connected components
+        struct State<'a> {
+            graph: &'a BlockGraph,
+            index: HashMap<BugId, usize>,
+            lowlink: HashMap<BugId, usize>,
+            stack: Vec<BugId>,
+            on_stack: BTreeSet<BugId>,
+            components: Vec<Vec<BugId>>,
+        }
+
+        fn connect(state: &mut State, bug: BugId) {
+            let index = state.index.len();
+            state.index.insert(bug, index);
+            state.lowlink.insert(bug, index);
+            state.stack.push(bug);
+            state.on_stack.insert(bug);
+            for next in state.graph.blocked(bug) {
+                if !state.index.contains_key(&next) {
+                    connect(state, next);
+                    let low = state.lowlink[&bug].min(state.lowlink[&next]);
+                    state.lowlink.insert(bug, low);
+                } else if state.on_stack.contains(&next) {
+                    let low = state.lowlink[&bug].min(state.index[&next]);
+                    state.lowlink.insert(bug, low);
+                }
+            }
+            if state.lowlink[&bug] == state.index[&bug] {
+                let mut component = vec![];
+                while let Some(member) = state.stack.pop() {
+                    state.on_stack.remove(&member);
+                    component.push(member);
+                    if member == bug {
+                        break;
+                    }
+                }
+                component.sort();
+                state.components.push(component);
+            }
+        }
+
+        let mut state = State {
+            graph: self,
+            index: HashMap::new(),
+            lowlink: HashMap::new(),
+            stack: vec![],
+            on_stack: BTreeSet::new(),
+            components: vec![],
+        };
+        for bug in self.nodes.iter() {
+            if !state.index.contains_key(bug) {
+                connect(&mut state, *bug);
+            }
+        }
+        let mut cycles: Vec<Vec<BugId>> = state
+            .components
+            .into_iter()
+            .filter(|c| c.len() > 1 || self.edges.contains(&(c[0], c[0])))
+            .collect();
+        cycles.sort();
+        cycles
+    }
+
+    /// Returns the bugs ordered so that every bug comes after the bugs blocking it
+    ///
+    /// Returns `None` if the graph has cycles, see [`BlockGraph::cycles`].
+    pub fn topological_order(&self) -> Option<Vec<BugId>> {
+        let mut indegree: BTreeMap<BugId, usize> = self.nodes.iter().map(|b| (*b, 0)).collect();
+        for (_, blocked) in self.edges.iter() {
+            *indegree.get_mut(blocked).unwrap() += 1;
+        }
+        let mut ready: BTreeSet<BugId> = indegree
+            .iter()
+            .filter(|(_, degree)| **degree == 0)
+            .map(|(bug, _)| *bug)
+            .collect();
+        let mut order = vec![];
+        while let Some(bug) = ready.pop_first() {
+            order.push(bug);
+            for blocked in self.blocked(bug) {
+                let degree = indegree.get_mut(&blocked).unwrap();
+                *degree -= 1;
+                if *degree == 0 {
+                    ready.insert(blocked);
+                }
+            }
+        }
+        if order.len() == self.nodes.len() {
+            Some(order)
+        } else {
+            None
+        }
+    }
+
+    fn label(&self, bug: BugId) -> String {
+        match self.reports.get(&bug) {
+            Some(report) => match (&report.package, &report.subject) {
+                (Some(package), Some(subject)) => format!("#{} {}: {}", bug, package, subject),
+                (None, Some(subject)) => format!("#{}: {}", bug, subject),
+                _ => format!("#{}", bug),
+            },
+            None => format!("#{}", bug),
+        }
+    }
+
+    fn is_done(&self, bug: BugId) -> bool {
+        self.reports
+            .get(&bug)
+            .and_then(|r| r.done.as_deref())
+            .is_some_and(|d| !d.is_empty())
+    }
+
+    /// Renders the graph in the DOT language of Graphviz
+    ///
+    /// Closed bugs are drawn dashed, and bugs whose reports have not been
+    /// added dotted.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph blocks {\n    node [shape=box];\n");
+        for bug in self.nodes.iter() {
+            let style = if !self.reports.contains_key(bug) {
+                ", style=dotted"
+            } else if self.is_done(*bug) {
+                ", style=dashed"
+            } else {
+                ""
+            };
+            dot.push_str(&format!(
+                "    {} [label={}{}];\n",
+                bug,
+                dot_string(&self.label(*bug)),
+                style
+            ));
+        }
+        for (blocker, blocked) in self.edges.iter() {
+            dot.push_str(&format!("    {} -> {};\n", blocker, blocked));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders the graph as a JSON document
+    ///
+    /// The document has a list of `nodes`, with the bug number, package,
+    /// subject and whether the bug is done, and a list of `edges` from the
+    /// blocking to the blocked bug.
+    pub fn to_json(&self) -> String {
+        let optional = |s: Option<&str>| s.map(json_string).unwrap_or_else(|| "null".to_string());
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|bug| {
+                let report = self.reports.get(bug);
+                format!(
+                    "{{\"id\":{},\"package\":{},\"subject\":{},\"done\":{}}}",
+                    bug,
+                    optional(report.and_then(|r| r.package.as_deref())),
+                    optional(report.and_then(|r| r.subject.as_deref())),
+                    self.is_done(*bug)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let edges = self
+            .edges
+            .iter()
+            .map(|(blocker, blocked)| format!("{{\"from\":{},\"to\":{}}}", blocker, blocked))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("{{\"nodes\":[{}],\"edges\":[{}]}}", nodes, edges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transition() -> BlockGraph {
+        // 1 is the tracking bug, blocked by 2 and 3; 4 blocks 3
+        BlockGraph::from_reports(&HashMap::from([
+            (
+                1,
+                BugReport::from_fields("<package>release.debian.org</package><subject>transition: foo</subject><blockedby>2 3</blockedby>"),
+            ),
+            (2, BugReport::from_fields("<package>bar</package><subject>FTBFS \"with\" foo</subject><blocks>1</blocks>")),
+            (3, BugReport::from_fields("<blocks>1</blocks><blockedby>4</blockedby><done>jane@example.com</done>")),
+        ]))
+    }
+
+    #[test]
+    fn test_relations() {
+        let graph = transition();
+        assert_eq!(graph.bugs().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert_eq!(
+            graph.edges().collect::<Vec<_>>(),
+            vec![(2, 1), (3, 1), (4, 3)]
+        );
+        assert_eq!(graph.blockers(1), vec![2, 3]);
+        assert_eq!(graph.blocked(4), vec![3]);
+        assert_eq!(graph.unresolved(), vec![4]);
+        assert!(graph.report(2).is_some());
+    }
+
+    #[test]
+    fn test_topological_order() {
+        let graph = transition();
+        assert_eq!(graph.topological_order(), Some(vec![2, 4, 3, 1]));
+        assert!(graph.cycles().is_empty());
+    }
+
+    #[test]
+    fn test_cycles() {
+        let mut graph = transition();
+        graph.add_edge(1, 4);
+        graph.add_edge(5, 5);
+        assert_eq!(graph.cycles(), vec![vec![1, 3, 4], vec![5]]);
+        assert_eq!(graph.topological_order(), None);
+    }
+
+    #[test]
+    fn test_to_dot() {
+        assert_eq!(
+            transition().to_dot(),
+            "digraph blocks {\n    node [shape=box];\n\
+             \x20   1 [label=\"#1 release.debian.org: transition: foo\"];\n\
+             \x20   2 [label=\"#2 bar: FTBFS \\\"with\\\" foo\"];\n\
+             \x20   3 [label=\"#3\", style=dashed];\n\
+             \x20   4 [label=\"#4\", style=dotted];\n\
+             \x20   2 -> 1;\n\
+             \x20   3 -> 1;\n\
+             \x20   4 -> 3;\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut graph = BlockGraph::new();
+        graph.add_report(
+            1,
+            BugReport::from_fields(
+                "<package>foo</package><subject>a \"b\"\n</subject><blocks>2</blocks>",
+            ),
+        );
+        assert_eq!(
+            graph.to_json(),
+            "{\"nodes\":[\
+             {\"id\":1,\"package\":\"foo\",\"subject\":\"a \\\"b\\\"\\n\",\"done\":false},\
+             {\"id\":2,\"package\":null,\"subject\":null,\"done\":false}],\
+             \"edges\":[{\"from\":1,\"to\":2}]}"
+        );
+    }
+}
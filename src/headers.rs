@@ -0,0 +1,270 @@
+//! Typed access to common bug log headers
+//!
+//! [`BugLog::parsed_headers`] decodes RFC 2047 encoded words and parses
+//! addresses and dates in one pass over [`BugLog::header`], so callers do not
+//! have to re-parse it themselves. Malformed headers are treated as absent.
+
+use crate::BugLog;
+use chrono::{DateTime, FixedOffset};
+use mailparse::{MailAddr, MailHeader, MailHeaderMap};
+
+/// An email address, optionally with a display name
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Address {
+    /// The display name, e.g. "Jane Doe"
+    pub name: Option<String>,
+    /// The address itself, e.g. "jane@example.com"
+    pub address: String,
+}
+
+impl std::fmt::Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{} <{}>", name, self.address),
+            None => f.write_str(&self.address),
+        }
+    }
+}
+
+fn parse_addresses(header: &MailHeader) -> Vec<Address> {
+    let addrs = match mailparse::addrparse_header(header) {
+        Ok(addrs) => addrs,
+        Err(_) => return vec![],
+    };
+    addrs
+        .iter()
+        .flat_map(|addr| match addr {
+            MailAddr::Single(info) => vec![info.clone()],
+            MailAddr::Group(group) => group.addrs.clone(),
+        })
+        .map(|info| Address {
+            name: info.display_name.filter(|n| !n.is_empty()),
+            address: info.addr,
+        })
+        .collect()
+}
+
+/// Parses a date as found in a `Date` header
+///
+/// Dates are parsed strictly according to RFC 2822 first; if that fails, the more
+/// lenient parser from mailparse is used and the result is returned in UTC.
+pub(crate) fn parse_date(value: &str) -> Option<DateTime<FixedOffset>> {
+    if let Ok(date) = DateTime::parse_from_rfc2822(value.trim()) {
+        return Some(date);
+    }
+    // mailparse fills in defaults for missing fields, so require at least a year and a time
+    if !lazy_regex::regex_is_match!(r"\b\d{4}\b.*\b\d{1,2}:\d{2}", value) {
+        return None;
+    }
+    let timestamp = mailparse::dateparse(value).ok()?;
+    DateTime::from_timestamp(timestamp, 0).map(|date| date.fixed_offset())
+}
+
+/// The common headers of a bug log message, decoded in a single pass
+///
+/// Returned by [`BugLog::parsed_headers`].
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct MessageHeaders {
+    /// The sender of the message, from the `From` header
+    pub sender: Option<Address>,
+    /// The date of the message, from the `Date` header
+    pub date: Option<DateTime<FixedOffset>>,
+    /// The subject of the message, with encoded words decoded
+    pub subject: Option<String>,
+    /// The Message-ID of the message, without angle brackets
+    pub message_id: Option<String>,
+    /// The addresses in the `X-Debbugs-Cc` header
+    ///
+    /// The BTS forwards the message to these addresses in addition to its usual recipients.
+    pub debbugs_cc: Vec<Address>,
+    /// Whether the sender asked not to receive an acknowledgement
+    ///
+    /// This corresponds to the presence of the `X-Debbugs-No-Ack` header.
+    pub debbugs_no_ack: bool,
+}
+
+fn addresses(headers: &[MailHeader], name: &str) -> Vec<Address> {
+    headers
+        .get_all_headers(name)
+        .into_iter()
+        .flat_map(parse_addresses)
+        .collect()
+}
+
+fn message_id(value: &str) -> Option<String> {
+    let value = value.trim();
+    let value = value
+        .strip_prefix('<')
+        .and_then(|v| v.strip_suffix('>'))
+        .unwrap_or(value);
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+impl MessageHeaders {
+    fn from_headers(headers: &[MailHeader]) -> Self {
+        MessageHeaders {
+            sender: addresses(headers, "From").into_iter().next(),
+            date: headers.get_first_value("Date").and_then(|v| parse_date(&v)),
+            subject: headers.get_first_value("Subject"),
+            message_id: headers
+                .get_first_value("Message-ID")
+                .and_then(|v| message_id(&v)),
+            debbugs_cc: addresses(headers, "X-Debbugs-Cc"),
+            debbugs_no_ack: headers.get_first_header("X-Debbugs-No-Ack").is_some(),
+        }
+    }
+}
+
+impl BugLog {
+    /// Parses the common headers of the message
+    ///
+    /// The header block is parsed once; if it can not be parsed at all, all
+    /// headers are treated as absent.
+    pub fn parsed_headers(&self) -> MessageHeaders {
+        match self.headers() {
+            Ok(headers) => MessageHeaders::from_headers(&headers),
+            Err(_) => MessageHeaders::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, Timelike};
+
+    fn log(header: &str) -> MessageHeaders {
+        BugLog {
+            header: header.to_string(),
+            msgnum: 5,
+            body: "".to_string(),
+        }
+        .parsed_headers()
+    }
+
+    #[test]
+    fn test_sender() {
+        let sender = log("From: Jane Doe <jane@example.com>").sender.unwrap();
+        assert_eq!(sender.name.as_deref(), Some("Jane Doe"));
+        assert_eq!(sender.address, "jane@example.com");
+        assert_eq!(sender.to_string(), "Jane Doe <jane@example.com>");
+
+        let sender = log("From: jane@example.com").sender.unwrap();
+        assert_eq!(sender.name, None);
+        assert_eq!(sender.to_string(), "jane@example.com");
+    }
+
+    #[test]
+    fn test_sender_encoded() {
+        let sender = log("From: =?UTF-8?Q?Jos=C3=A9_P=C3=A9rez?= <jose@example.com>")
+            .sender
+            .unwrap();
+        assert_eq!(sender.name.as_deref(), Some("José Pérez"));
+        assert_eq!(sender.address, "jose@example.com");
+    }
+
+    #[test]
+    fn test_sender_missing_or_malformed() {
+        assert_eq!(log("Subject: no sender").sender, None);
+        assert_eq!(log("From: <unterminated@example.com").sender, None);
+    }
+
+    #[test]
+    fn test_date() {
+        let date = log("Date: Tue, 2 Jan 2024 10:00:00 +0100").date.unwrap();
+        assert_eq!(date.year(), 2024);
+        assert_eq!(date.hour(), 10);
+        assert_eq!(date.offset().local_minus_utc(), 3600);
+    }
+
+    #[test]
+    fn test_date_lenient() {
+        // Obsolete time zone names and extra comments
+        let date = log("Date: 2 Jan 2024 10:00:00 GMT (Coordinated Universal Time)")
+            .date
+            .unwrap();
+        assert_eq!(date.day(), 2);
+        assert_eq!(date.hour(), 10);
+    }
+
+    #[test]
+    fn test_date_malformed() {
+        assert_eq!(log("Date: sometime last week").date, None);
+        assert_eq!(log("Subject: no date").date, None);
+    }
+
+    #[test]
+    fn test_subject() {
+        assert_eq!(
+            log("Subject: foo: crashes on startup").subject.as_deref(),
+            Some("foo: crashes on startup")
+        );
+        assert_eq!(
+            log("Subject: =?ISO-8859-1?Q?caf=E9?= crashes\n =?UTF-8?B?4pyT?=")
+                .subject
+                .as_deref(),
+            Some("café crashes ✓")
+        );
+        assert_eq!(log("From: a@example.com").subject, None);
+    }
+
+    #[test]
+    fn test_message_id() {
+        assert_eq!(
+            log("Message-ID: <abc@example.com>").message_id.as_deref(),
+            Some("abc@example.com")
+        );
+        assert_eq!(
+            log("Message-Id: abc@example.com").message_id.as_deref(),
+            Some("abc@example.com")
+        );
+        assert_eq!(log("Message-ID: <>").message_id, None);
+        assert_eq!(log("Subject: none").message_id, None);
+    }
+
+    #[test]
+    fn test_debbugs_cc() {
+        let cc = log("X-Debbugs-Cc: a@example.com, Bob <bob@example.com>").debbugs_cc;
+        assert_eq!(
+            cc,
+            vec![
+                Address {
+                    name: None,
+                    address: "a@example.com".to_string()
+                },
+                Address {
+                    name: Some("Bob".to_string()),
+                    address: "bob@example.com".to_string()
+                }
+            ]
+        );
+        assert!(log("Subject: none").debbugs_cc.is_empty());
+    }
+
+    #[test]
+    fn test_parsed_headers() {
+        let headers = log("From: Jane Doe <jane@example.com>\n\
+             Date: Tue, 2 Jan 2024 10:00:00 +0100\n\
+             Subject: foo: crashes\n\
+             Message-ID: <abc@example.com>\n\
+             X-Debbugs-Cc: bob@example.com\n\
+             X-Debbugs-No-Ack: yes");
+        assert_eq!(headers.sender.unwrap().address, "jane@example.com");
+        assert_eq!(headers.date.unwrap().year(), 2024);
+        assert_eq!(headers.subject.as_deref(), Some("foo: crashes"));
+        assert_eq!(headers.message_id.as_deref(), Some("abc@example.com"));
+        assert_eq!(headers.debbugs_cc.len(), 1);
+        assert!(headers.debbugs_no_ack);
+        assert_eq!(log(""), MessageHeaders::default());
+    }
+
+    #[test]
+    fn test_debbugs_no_ack() {
+        assert!(log("X-Debbugs-No-Ack: yes").debbugs_no_ack);
+        assert!(!log("Subject: none").debbugs_no_ack);
+    }
+}
@@ -0,0 +1,260 @@
+//! Profiles of debbugs deployments
+//!
+//! Debbugs is used by several projects besides Debian. The deployments differ in
+//! where the SOAP interface and web pages live, which domain they receive mail
+//! at, and which features they have enabled. An [`Instance`] bundles this
+//! information, so clients and mail addresses can be set up consistently.
+//!
+//! Clients created with `from_instance` refuse requests for features the
+//! instance does not have with [`Error::Unsupported`], rather than sending
+//! them and failing in less obvious ways.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use debbugs::instance::Instance;
+//!
+//! let gnu = Instance::gnu();
+//! let client = debbugs::blocking::Debbugs::from_instance(&gnu);
+//! // Fails early, as the GNU tracker does not track versions
+//! assert!(client.get_versions("emacs", &[], &[]).is_err());
+//! println!("{}", gnu.bug_url(12345));
+//! println!("{}", gnu.addresses().control());
+//! ```
+
+use crate::mail::BtsAddresses;
+use crate::mbox::MboxKind;
+use crate::{BugId, Error};
+
+/// Features that are not available on every debbugs deployment
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Capabilities {
+    /// Usertags can be set and queried with `get_usertag`
+    pub usertags: bool,
+    /// Found and fixed versions are tracked against source package
+    /// versions, and `get_versions` returns meaningful data
+    pub versions: bool,
+    /// Bug logs can be downloaded as mboxes from `bugreport.cgi`
+    pub mbox: bool,
+}
+
+impl Default for Capabilities {
+    /// All features enabled, as on bugs.debian.org
+    fn default() -> Self {
+        Capabilities {
+            usertags: true,
+            versions: true,
+            mbox: true,
+        }
+    }
+}
+
+/// A debbugs deployment
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Instance {
+    /// A short name for the instance, e.g. "debian"
+    pub name: String,
+    /// The URL of the SOAP endpoint
+    pub soap_url: String,
+    /// The base URL of the web interface, with a trailing slash
+    pub web_url: String,
+    /// The base URL of the CGI scripts such as `bugreport.cgi`, with a
+    /// trailing slash
+    pub cgi_url: String,
+    /// The domain the BTS receives mail at
+    pub mail_domain: String,
+    /// The features available on this instance
+    pub capabilities: Capabilities,
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Instance::debian()
+    }
+}
+
+impl Instance {
+    /// Creates a profile for a custom deployment, assuming all features are available
+    ///
+    /// The CGI scripts are assumed to live next to the SOAP endpoint, as they
+    /// do on a standard debbugs installation.
+    pub fn new(name: &str, soap_url: &str, web_url: &str, mail_domain: &str) -> Self {
+        let mut web_url = web_url.to_string();
+        if !web_url.ends_with('/') {
+            web_url.push('/');
+        }
+        let cgi_url = match soap_url.rsplit_once('/') {
+            Some((dir, _script)) => format!("{}/", dir),
+            None => web_url.clone(),
+        };
+        Instance {
+            name: name.to_string(),
+            soap_url: soap_url.to_string(),
+            web_url,
+            cgi_url,
+            mail_domain: mail_domain.to_string(),
+            capabilities: Capabilities::default(),
+        }
+    }
+
+    /// Creates a profile for a deployment of which only the SOAP endpoint is known
+    ///
+    /// The web interface and mail domain are taken to be on the host of the
+    /// SOAP endpoint, and all features are assumed to be available.
+    pub fn from_soap_url(soap_url: &str) -> Self {
+        let (scheme, rest) = soap_url.split_once("://").unwrap_or(("https", soap_url));
+        let host = rest.split('/').next().unwrap_or(rest);
+        Instance::new(
+            "custom",
+            soap_url,
+            &format!("{}://{}/", scheme, host),
+            host.split(':').next().unwrap_or(host),
+        )
+    }
+
+    /// The Debian bug tracking system at bugs.debian.org
+    pub fn debian() -> Self {
+        Instance::new(
+            "debian",
+            crate::DEFAULT_URL,
+            "https://bugs.debian.org/",
+            "bugs.debian.org",
+        )
+    }
+
+    /// The GNU bug tracker at debbugs.gnu.org
+    ///
+    /// This is also the tracker behind issues.guix.gnu.org.
+    pub fn gnu() -> Self {
+        Instance {
+            capabilities: Capabilities {
+                usertags: true,
+                versions: false,
+                mbox: true,
+            },
+            ..Instance::new(
+                "gnu",
+                "https://debbugs.gnu.org/cgi/soap.cgi",
+                "https://debbugs.gnu.org/",
+                "debbugs.gnu.org",
+            )
+        }
+    }
+
+    /// Returns the built-in profiles
+    pub fn builtin() -> Vec<Instance> {
+        vec![Instance::debian(), Instance::gnu()]
+    }
+
+    /// Looks up a built-in profile by name
+    pub fn by_name(name: &str) -> Option<Instance> {
+        Instance::builtin().into_iter().find(|i| i.name == name)
+    }
+
+    /// Returns the URL of the web page for a bug
+    pub fn bug_url(&self, bug: BugId) -> String {
+        format!("{}{}", self.web_url, bug)
+    }
+
+    /// Returns the URL of an mbox of the messages of a bug
+    pub fn mbox_url(&self, bug: BugId, kind: MboxKind) -> String {
+        crate::mbox::mbox_url(&self.cgi_url, bug, kind)
+    }
+
+    /// Returns the error for a request of a feature this instance does not have
+    pub(crate) fn unsupported(&self, feature: &str) -> Error {
+        Error::Unsupported(format!("{} is not supported by {}", feature, self.name))
+    }
+
+    /// Returns the mail addresses of this instance
+    pub fn addresses(&self) -> BtsAddresses {
+        BtsAddresses::new(&self.mail_domain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debian() {
+        let debian = Instance::default();
+        assert_eq!(debian.name, "debian");
+        assert_eq!(debian.soap_url, crate::DEFAULT_URL);
+        assert_eq!(debian.bug_url(1000), "https://bugs.debian.org/1000");
+        assert_eq!(debian.addresses(), BtsAddresses::default());
+        assert!(debian.capabilities.versions);
+        assert_eq!(debian.cgi_url, "https://bugs.debian.org/cgi-bin/");
+        assert_eq!(
+            debian.mbox_url(1000, MboxKind::Full),
+            "https://bugs.debian.org/cgi-bin/bugreport.cgi?bug=1000;mbox=yes"
+        );
+        assert_eq!(
+            debian.mbox_url(1000, MboxKind::Maintainer),
+            "https://bugs.debian.org/cgi-bin/bugreport.cgi?bug=1000;mboxmaint=yes"
+        );
+    }
+
+    #[test]
+    fn test_gnu() {
+        let gnu = Instance::gnu();
+        assert_eq!(gnu.soap_url, "https://debbugs.gnu.org/cgi/soap.cgi");
+        assert_eq!(gnu.bug_url(12345), "https://debbugs.gnu.org/12345");
+        assert_eq!(gnu.addresses().done(12345), "12345-done@debbugs.gnu.org");
+        assert!(!gnu.capabilities.versions);
+        assert_eq!(
+            gnu.mbox_url(42, MboxKind::Status),
+            "https://debbugs.gnu.org/cgi/bugreport.cgi?bug=42;mboxstatus=yes"
+        );
+        assert!(matches!(
+            gnu.unsupported("get_versions"),
+            Error::Unsupported(_)
+        ));
+    }
+
+    #[test]
+    fn test_by_name() {
+        assert_eq!(Instance::by_name("gnu"), Some(Instance::gnu()));
+        assert_eq!(Instance::by_name("debian"), Some(Instance::debian()));
+        assert_eq!(Instance::by_name("launchpad"), None);
+    }
+
+    #[test]
+    fn test_custom() {
+        let instance = Instance::new(
+            "example",
+            "https://bugs.example.com/cgi-bin/soap.cgi",
+            "https://bugs.example.com",
+            "bugs.example.com",
+        );
+        assert_eq!(instance.web_url, "https://bugs.example.com/");
+        assert_eq!(instance.bug_url(1), "https://bugs.example.com/1");
+        assert_eq!(instance.cgi_url, "https://bugs.example.com/cgi-bin/");
+        assert_eq!(instance.capabilities, Capabilities::default());
+    }
+
+    #[test]
+    fn test_from_soap_url() {
+        let instance = Instance::from_soap_url("http://localhost:8080/cgi-bin/soap.cgi");
+        assert_eq!(instance.web_url, "http://localhost:8080/");
+        assert_eq!(instance.cgi_url, "http://localhost:8080/cgi-bin/");
+        assert_eq!(instance.mail_domain, "localhost");
+        assert_eq!(
+            Instance::from_soap_url(crate::DEFAULT_URL).cgi_url,
+            Instance::debian().cgi_url
+        );
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_client_capabilities() {
+        let client = crate::blocking::Debbugs::from_instance(&Instance::gnu());
+        assert_eq!(client.instance(), &Instance::gnu());
+        assert!(matches!(
+            client.get_versions("emacs", &[], &[]),
+            Err(Error::Unsupported(_))
+        ));
+        let client = crate::blocking::Debbugs::new("https://bugs.example.com/soap.cgi");
+        assert_eq!(client.instance().cgi_url, "https://bugs.example.com/");
+    }
+}
@@ -7,7 +7,8 @@
 //!
 //! - **blocking** (default): Enables the synchronous `debbugs::blocking::Debbugs` client
 //! - **tokio** (default): Enables the asynchronous `debbugs::Debbugs` client
-//! - **mailparse** (default): Enables parsing of email headers in bug logs
+//! - **mailparse** (default): Enables parsing of email headers and MIME bodies in bug logs
+//! - **smtp**: Enables sending mail to the BTS over SMTP, see the `send` module
 //!
 //! # Examples
 //!
@@ -41,9 +42,39 @@
 mod soap;
 pub use soap::{BugLog, BugReport};
 
+pub mod classify;
+pub mod closes;
+pub mod control;
+pub mod graph;
+#[cfg(feature = "mailparse")]
+pub mod headers;
+pub mod instance;
+pub mod logfile;
+pub mod mail;
+pub mod maildir;
+pub mod mbox;
+pub mod merged;
+#[cfg(feature = "mailparse")]
+pub mod mime;
+#[cfg(feature = "mailparse")]
+pub mod patches;
+pub mod rc;
+pub mod send;
+pub mod submit;
+pub mod suites;
+#[cfg(feature = "mailparse")]
+pub mod thread;
+pub mod usertags;
+pub mod versions;
+pub mod wnpp;
+
 pub const DEFAULT_URL: &str = "https://bugs.debian.org/cgi-bin/soap.cgi";
 pub const DEFAULT_USER_AGENT: &str = concat!("debbugs-rs/", env!("CARGO_PKG_VERSION"));
 
+/// The number of bugs to request in a single `get_status` call when retrieving
+/// many bugs at once
+pub(crate) const STATUS_BATCH_SIZE: usize = 100;
+
 /// Errors that can occur when interacting with the Debbugs API
 #[derive(Debug)]
 pub enum Error {
@@ -71,6 +102,35 @@ pub enum Error {
     /// an error in processing the request (e.g., invalid parameters,
     /// server-side errors, or authentication issues).
     Fault(soap::Fault),
+
+    /// Errors parsing data obtained outside of the SOAP interface
+    ///
+    /// This occurs when bug log files or other raw debbugs data
+    /// do not have the expected format.
+    ParseError(String),
+
+    /// I/O errors
+    ///
+    /// This occurs when reading or writing local files fails.
+    IoError(std::io::Error),
+
+    /// Validation errors
+    ///
+    /// This occurs when a control command or message would be rejected
+    /// by the BTS, e.g. because of missing arguments.
+    ValidationError(String),
+
+    /// Mail delivery errors
+    ///
+    /// This occurs when a message could not be handed over for delivery,
+    /// e.g. because sendmail failed or the SMTP server rejected it.
+    MailError(String),
+
+    /// Unsupported requests
+    ///
+    /// This occurs when a request needs a feature that the debbugs
+    /// instance does not have, e.g. version tracking on debbugs.gnu.org.
+    Unsupported(String),
 }
 
 impl From<reqwest::Error> for Error {
@@ -79,6 +139,12 @@ impl From<reqwest::Error> for Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::IoError(err)
+    }
+}
+
 /// The status of a bug report
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum BugStatus {
@@ -126,6 +192,8 @@ pub enum Pending {
     Done,
     /// Bug has been forwarded
     Forwarded,
+    /// Bug is not present in the versions being looked at
+    Absent,
 }
 
 impl std::str::FromStr for Pending {
@@ -138,6 +206,7 @@ impl std::str::FromStr for Pending {
             "fixed" => Ok(Pending::Fixed),
             "done" => Ok(Pending::Done),
             "forwarded" => Ok(Pending::Forwarded),
+            "absent" => Ok(Pending::Absent),
             _ => Err(Error::SoapError(format!("Unknown pending: {}", s))),
         }
     }
@@ -151,6 +220,7 @@ impl std::fmt::Display for Pending {
             Pending::Done => f.write_str("done"),
             Pending::Forwarded => f.write_str("forwarded"),
             Pending::Fixed => f.write_str("fixed"),
+            Pending::Absent => f.write_str("absent"),
         }
     }
 }
@@ -190,6 +260,208 @@ impl std::fmt::Display for Archived {
     }
 }
 
+/// The severity of a bug report
+///
+/// Severities are ordered from least (`Wishlist`) to most severe (`Critical`).
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Clone, Copy)]
+pub enum Severity {
+    /// A feature request or a bug that is very difficult to fix
+    Wishlist,
+    /// A problem which doesn't affect the package's usefulness
+    Minor,
+    /// The default severity
+    #[default]
+    Normal,
+    /// A bug which has a major effect on the usability of a package
+    Important,
+    /// A severe violation of Debian policy
+    Serious,
+    /// The package is unusable or mostly so, or causes data loss
+    Grave,
+    /// The package breaks unrelated software or the whole system
+    Critical,
+}
+
+impl Severity {
+    /// All severities, from least to most severe
+    pub const ALL: [Severity; 7] = [
+        Severity::Wishlist,
+        Severity::Minor,
+        Severity::Normal,
+        Severity::Important,
+        Severity::Serious,
+        Severity::Grave,
+        Severity::Critical,
+    ];
+
+    /// Returns whether bugs of this severity are release-critical
+    pub fn is_release_critical(&self) -> bool {
+        *self >= Severity::Serious
+    }
+}
+
+impl std::str::FromStr for Severity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wishlist" => Ok(Severity::Wishlist),
+            "minor" => Ok(Severity::Minor),
+            "normal" => Ok(Severity::Normal),
+            "important" => Ok(Severity::Important),
+            "serious" => Ok(Severity::Serious),
+            "grave" => Ok(Severity::Grave),
+            "critical" => Ok(Severity::Critical),
+            _ => Err(Error::SoapError(format!("Unknown severity: {}", s))),
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Severity::Wishlist => f.write_str("wishlist"),
+            Severity::Minor => f.write_str("minor"),
+            Severity::Normal => f.write_str("normal"),
+            Severity::Important => f.write_str("important"),
+            Severity::Serious => f.write_str("serious"),
+            Severity::Grave => f.write_str("grave"),
+            Severity::Critical => f.write_str("critical"),
+        }
+    }
+}
+
+/// Code names of Debian releases, which can be used as release tags
+pub const RELEASE_CODENAMES: &[&str] = &[
+    "potato", "woody", "sarge", "etch", "lenny", "squeeze", "wheezy", "jessie", "stretch",
+    "buster", "bullseye", "bookworm", "trixie", "forky", "duke",
+];
+
+/// A tag that can be set on a bug report
+///
+/// See <https://www.debian.org/Bugs/Developer#tags> for the meaning of each tag.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Tag {
+    /// A patch to fix the bug is included in the bug log
+    Patch,
+    /// The bug won't be fixed
+    Wontfix,
+    /// More information is needed from the submitter
+    Moreinfo,
+    /// The bug can't be reproduced
+    Unreproducible,
+    /// The maintainer requests help with the bug
+    Help,
+    /// The bug is a security issue
+    Security,
+    /// The bug applies to the upstream part of the package
+    Upstream,
+    /// A fix has been prepared and will be uploaded soon
+    Pending,
+    /// The bug has been confirmed by the maintainer
+    Confirmed,
+    /// The bug is fixed or worked around, but there are issues left
+    Fixed,
+    /// The bug has been fixed by the upstream maintainer
+    FixedUpstream,
+    /// The bug has been fixed in the package in the experimental distribution
+    FixedInExperimental,
+    /// The bug is relevant to the development of debian-installer
+    DI,
+    /// The bug affects support for IPv6
+    Ipv6,
+    /// The bug affects support for large files
+    Lfs,
+    /// The bug is relevant to the localisation of the package
+    L10n,
+    /// The bug is suitable for new contributors
+    Newcomer,
+    /// The bug is relevant to the accessibility of the package
+    A11y,
+    /// The package fails to build from source
+    Ftbfs,
+    /// The bug applies only to unstable
+    Sid,
+    /// The bug applies only to experimental
+    Experimental,
+    /// The bug applies only to the given release, e.g. "trixie"
+    Release(String),
+    /// The bug should be ignored for the purposes of the given release
+    Ignore(String),
+}
+
+impl std::str::FromStr for Tag {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "patch" => Ok(Tag::Patch),
+            "wontfix" => Ok(Tag::Wontfix),
+            "moreinfo" => Ok(Tag::Moreinfo),
+            "unreproducible" => Ok(Tag::Unreproducible),
+            "help" => Ok(Tag::Help),
+            "security" => Ok(Tag::Security),
+            "upstream" => Ok(Tag::Upstream),
+            "pending" => Ok(Tag::Pending),
+            "confirmed" => Ok(Tag::Confirmed),
+            "fixed" => Ok(Tag::Fixed),
+            "fixed-upstream" => Ok(Tag::FixedUpstream),
+            "fixed-in-experimental" => Ok(Tag::FixedInExperimental),
+            "d-i" => Ok(Tag::DI),
+            "ipv6" => Ok(Tag::Ipv6),
+            "lfs" => Ok(Tag::Lfs),
+            "l10n" => Ok(Tag::L10n),
+            "newcomer" => Ok(Tag::Newcomer),
+            "a11y" => Ok(Tag::A11y),
+            "ftbfs" => Ok(Tag::Ftbfs),
+            "sid" => Ok(Tag::Sid),
+            "experimental" => Ok(Tag::Experimental),
+            _ => {
+                if RELEASE_CODENAMES.contains(&s) {
+                    Ok(Tag::Release(s.to_string()))
+                } else if let Some(release) = s
+                    .strip_suffix("-ignore")
+                    .filter(|r| RELEASE_CODENAMES.contains(r))
+                {
+                    Ok(Tag::Ignore(release.to_string()))
+                } else {
+                    Err(Error::SoapError(format!("Unknown tag: {}", s)))
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Tag {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Tag::Patch => f.write_str("patch"),
+            Tag::Wontfix => f.write_str("wontfix"),
+            Tag::Moreinfo => f.write_str("moreinfo"),
+            Tag::Unreproducible => f.write_str("unreproducible"),
+            Tag::Help => f.write_str("help"),
+            Tag::Security => f.write_str("security"),
+            Tag::Upstream => f.write_str("upstream"),
+            Tag::Pending => f.write_str("pending"),
+            Tag::Confirmed => f.write_str("confirmed"),
+            Tag::Fixed => f.write_str("fixed"),
+            Tag::FixedUpstream => f.write_str("fixed-upstream"),
+            Tag::FixedInExperimental => f.write_str("fixed-in-experimental"),
+            Tag::DI => f.write_str("d-i"),
+            Tag::Ipv6 => f.write_str("ipv6"),
+            Tag::Lfs => f.write_str("lfs"),
+            Tag::L10n => f.write_str("l10n"),
+            Tag::Newcomer => f.write_str("newcomer"),
+            Tag::A11y => f.write_str("a11y"),
+            Tag::Ftbfs => f.write_str("ftbfs"),
+            Tag::Sid => f.write_str("sid"),
+            Tag::Experimental => f.write_str("experimental"),
+            Tag::Release(release) => f.write_str(release),
+            Tag::Ignore(release) => write!(f, "{}-ignore", release),
+        }
+    }
+}
+
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match &self {
@@ -197,6 +469,11 @@ impl std::fmt::Display for Error {
             Error::XmlError(err) => write!(f, "XML Error: {}", err),
             Error::ReqwestError(err) => write!(f, "Reqwest Error: {}", err),
             Error::Fault(err) => write!(f, "Fault: {}", err),
+            Error::ParseError(err) => write!(f, "Parse Error: {}", err),
+            Error::IoError(err) => write!(f, "I/O Error: {}", err),
+            Error::ValidationError(err) => write!(f, "Validation Error: {}", err),
+            Error::MailError(err) => write!(f, "Mail Error: {}", err),
+            Error::Unsupported(err) => write!(f, "Unsupported: {}", err),
         }
     }
 }
@@ -346,6 +623,61 @@ mod tests {
         assert_eq!(Archived::default(), Archived::NotArchived);
     }
 
+    #[test]
+    fn test_severity_from_str() {
+        assert_eq!(Severity::from_str("serious").unwrap(), Severity::Serious);
+        assert_eq!(Severity::from_str("wishlist").unwrap(), Severity::Wishlist);
+        assert!(Severity::from_str("Serious").is_err());
+        assert!(Severity::from_str("fixed").is_err());
+    }
+
+    #[test]
+    fn test_severity_roundtrip() {
+        for severity in Severity::ALL {
+            assert_eq!(Severity::from_str(&severity.to_string()).unwrap(), severity);
+        }
+    }
+
+    #[test]
+    fn test_severity_ordering() {
+        assert!(Severity::Critical > Severity::Grave);
+        assert!(Severity::Normal > Severity::Minor);
+        assert!(Severity::Serious.is_release_critical());
+        assert!(!Severity::Important.is_release_critical());
+        assert_eq!(Severity::default(), Severity::Normal);
+    }
+
+    #[test]
+    fn test_tag_from_str() {
+        assert_eq!(Tag::from_str("patch").unwrap(), Tag::Patch);
+        assert_eq!(Tag::from_str("d-i").unwrap(), Tag::DI);
+        assert_eq!(
+            Tag::from_str("trixie").unwrap(),
+            Tag::Release("trixie".to_string())
+        );
+        assert_eq!(
+            Tag::from_str("bookworm-ignore").unwrap(),
+            Tag::Ignore("bookworm".to_string())
+        );
+        assert!(Tag::from_str("unknown").is_err());
+        assert!(Tag::from_str("unknown-ignore").is_err());
+        assert!(Tag::from_str("sid-ignore").is_err());
+    }
+
+    #[test]
+    fn test_tag_roundtrip() {
+        for s in [
+            "patch",
+            "fixed-upstream",
+            "fixed-in-experimental",
+            "sid",
+            "trixie",
+            "trixie-ignore",
+        ] {
+            assert_eq!(Tag::from_str(s).unwrap().to_string(), s);
+        }
+    }
+
     #[test]
     fn test_error_display() {
         let soap_err = Error::SoapError("test error".to_string());
@@ -365,6 +697,27 @@ mod tests {
         };
         let fault_err = Error::Fault(fault);
         assert_eq!(fault_err.to_string(), "Fault: { faultcode: Client, faultstring: Invalid request, faultactor: None, detail: Some(\"Missing required parameter\") }");
+
+        let parse_err = Error::ParseError("unexpected marker".to_string());
+        assert_eq!(parse_err.to_string(), "Parse Error: unexpected marker");
+
+        let io_err = Error::from(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no such file",
+        ));
+        assert_eq!(io_err.to_string(), "I/O Error: no such file");
+
+        let validation_err = Error::ValidationError("merge needs two bugs".to_string());
+        assert_eq!(
+            validation_err.to_string(),
+            "Validation Error: merge needs two bugs"
+        );
+
+        let mail_err = Error::MailError("sendmail exited with status 1".to_string());
+        assert_eq!(
+            mail_err.to_string(),
+            "Mail Error: sendmail exited with status 1"
+        );
     }
 
     #[test]
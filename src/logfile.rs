@@ -0,0 +1,407 @@
+//! Parser for the raw debbugs `.log` file format
+//!
+//! Debbugs stores the history of a bug in a `.log` file, made up of records that
+//! are delimited by lines containing a single control character. The SOAP
+//! `get_bug_log` call flattens these records into plain messages; this module
+//! keeps the record types and recipient lists around.
+//!
+//! The format is described in the `Debbugs::Log` Perl module. Every record starts
+//! with a marker line and ends with a line containing `\x03`:
+//!
+//! - `\x01` starts an *autocheck* record (an old-style message)
+//! - `\x02` starts a *recips* record; the next line contains the recipients,
+//!   separated by `\x04`, followed by a `\x05` line and the message itself
+//! - `\x06` starts an *html* record, containing a HTML snippet
+//! - `\x07` starts an *incoming-recv* record, a message received by the BTS
+//!
+//! Lines inside records that start with one of the control characters are
+//! escaped by prefixing them with `\x18`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! let data = std::fs::read("/var/lib/debbugs/spool/db-h/00/1000.log").unwrap();
+//! let records = debbugs::logfile::parse_log(&data).unwrap();
+//! for log in debbugs::logfile::bug_log(&records) {
+//!     println!("#{}: {}", log.msgnum, log.header.lines().next().unwrap_or(""));
+//! }
+//! ```
+
+use crate::{BugId, BugLog, Error};
+
+const AUTOCHECK: u8 = 0x01;
+const RECIPS: u8 = 0x02;
+const KILL_END: u8 = 0x03;
+const RECIPS_SEPARATOR: u8 = 0x04;
+const GO: u8 = 0x05;
+const HTML: u8 = 0x06;
+const INCOMING_RECV: u8 = 0x07;
+const ESCAPE: u8 = 0x18;
+
+/// The type of a record in a debbugs log file
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RecordKind {
+    /// An old-style message, predating the other record types
+    Autocheck,
+    /// A message sent out by the BTS, together with its recipients
+    Recips,
+    /// A HTML snippet describing an action of the BTS
+    Html,
+    /// A message received by the BTS
+    IncomingRecv,
+}
+
+impl std::fmt::Display for RecordKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RecordKind::Autocheck => f.write_str("autocheck"),
+            RecordKind::Recips => f.write_str("recips"),
+            RecordKind::Html => f.write_str("html"),
+            RecordKind::IncomingRecv => f.write_str("incoming-recv"),
+        }
+    }
+}
+
+/// The recipients of a message sent out by the BTS
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Recipients {
+    /// The recipients were taken from the message headers (`sendmail -t`)
+    FromHeaders,
+    /// An explicit list of recipient addresses
+    List(Vec<String>),
+}
+
+/// A single record from a debbugs log file
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Record {
+    /// The type of the record
+    pub kind: RecordKind,
+    /// The recipients, only set for [`RecordKind::Recips`] records
+    pub recipients: Option<Recipients>,
+    /// The unescaped contents of the record: a complete message, or HTML
+    pub text: String,
+}
+
+impl Record {
+    /// Returns whether this record contains an email message
+    pub fn is_message(&self) -> bool {
+        self.kind != RecordKind::Html
+    }
+
+    /// Converts this record into a [`BugLog`] with the given message number
+    ///
+    /// Returns `None` for HTML records, which do not contain a message.
+    pub fn to_bug_log(&self, msgnum: BugId) -> Option<BugLog> {
+        if !self.is_message() {
+            return None;
+        }
+        Some(crate::mbox::message_to_bug_log(
+            self.text.as_bytes(),
+            msgnum,
+        ))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum State {
+    Between,
+    Recipients,
+    Go,
+    Body(RecordKind),
+}
+
+/// Parses the contents of a debbugs `.log` file into its records
+///
+/// The input is accepted as bytes, since log files frequently contain messages
+/// in a variety of character sets; invalid UTF-8 is replaced lossily.
+pub fn parse_log(input: &[u8]) -> Result<Vec<Record>, Error> {
+    let mut records = Vec::new();
+    let mut state = State::Between;
+    let mut recipients = None;
+    let mut text: Vec<u8> = Vec::new();
+
+    let mut lines: Vec<&[u8]> = input.split(|b| *b == b'\n').collect();
+    // A trailing newline produces an empty last element
+    if lines.last().map(|l| l.is_empty()).unwrap_or(false) {
+        lines.pop();
+    }
+
+    for (lineno, line) in lines.into_iter().enumerate() {
+        let marker = if line.len() == 1 && line[0] <= INCOMING_RECV {
+            Some(line[0])
+        } else {
+            None
+        };
+        match (&state, marker) {
+            (State::Between, Some(AUTOCHECK)) => state = State::Body(RecordKind::Autocheck),
+            (State::Between, Some(RECIPS)) => state = State::Recipients,
+            (State::Between, Some(HTML)) => state = State::Body(RecordKind::Html),
+            (State::Between, Some(INCOMING_RECV)) => state = State::Body(RecordKind::IncomingRecv),
+            (State::Between, _) => {
+                return Err(Error::ParseError(format!(
+                    "line {}: expected record marker, got {:?}",
+                    lineno + 1,
+                    String::from_utf8_lossy(line)
+                )));
+            }
+            (State::Recipients, None) => {
+                recipients = Some(if line == b"-t" {
+                    Recipients::FromHeaders
+                } else {
+                    Recipients::List(
+                        line.split(|b| *b == RECIPS_SEPARATOR)
+                            .filter(|r| !r.is_empty())
+                            .map(|r| String::from_utf8_lossy(r).into_owned())
+                            .collect(),
+                    )
+                });
+                state = State::Go;
+            }
+            (State::Go, Some(GO)) => state = State::Body(RecordKind::Recips),
+            (State::Body(kind), Some(KILL_END)) => {
+                records.push(Record {
+                    kind: *kind,
+                    recipients: if *kind == RecordKind::Recips {
+                        recipients.take()
+                    } else {
+                        None
+                    },
+                    text: String::from_utf8_lossy(&text).into_owned(),
+                });
+                text.clear();
+                state = State::Between;
+            }
+            (State::Body(_), None) => {
+                let line = line.strip_prefix(&[ESCAPE]).unwrap_or(line);
+                text.extend_from_slice(line);
+                text.push(b'\n');
+            }
+            (state, marker) => {
+                return Err(Error::ParseError(format!(
+                    "line {}: unexpected {} in state {:?}",
+                    lineno + 1,
+                    match marker {
+                        Some(m) => format!("marker \\x{:02x}", m),
+                        None => format!("line {:?}", String::from_utf8_lossy(line)),
+                    },
+                    state
+                )));
+            }
+        }
+    }
+
+    if state != State::Between {
+        return Err(Error::ParseError(format!(
+            "unterminated record at end of log (state {:?})",
+            state
+        )));
+    }
+
+    Ok(records)
+}
+
+/// Reads and parses a debbugs `.log` file from a reader
+pub fn read_log<R: std::io::Read>(mut reader: R) -> Result<Vec<Record>, Error> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    parse_log(&data)
+}
+
+/// A message from a debbugs log file, with the metadata the SOAP interface drops
+#[derive(Debug, Clone)]
+pub struct LogMessage {
+    /// The message, numbered by its position in the log
+    pub log: BugLog,
+    /// The type of the record the message was stored in
+    pub kind: RecordKind,
+    /// The recipients of the message, if it was sent out by the BTS
+    pub recipients: Option<Recipients>,
+}
+
+/// Returns all messages in the log, including those sent out by the BTS
+///
+/// Messages are numbered by their (1-based) record position in the log, which
+/// matches the numbering used by `get_bug_log` and `bugreport.cgi`.
+pub fn messages(records: &[Record]) -> Vec<LogMessage> {
+    records
+        .iter()
+        .enumerate()
+        .filter_map(|(i, record)| {
+            record.to_bug_log(i as BugId + 1).map(|log| LogMessage {
+                log,
+                kind: record.kind,
+                recipients: record.recipients.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Returns the Message-ID of a message, without angle brackets, for comparing
+/// messages
+pub(crate) fn message_id(log: &BugLog) -> Option<String> {
+    let msgid = log.raw_header("Message-ID")?;
+    Some(
+        msgid
+            .trim_start_matches('<')
+            .trim_end_matches('>')
+            .to_string(),
+    )
+}
+
+/// Returns the messages in the log the same way the SOAP `get_bug_log` call does
+///
+/// Only messages received by the BTS are included, duplicate messages (by
+/// Message-ID) are skipped, as are acknowledgements sent by the BTS itself.
+pub fn bug_log(records: &[Record]) -> Vec<BugLog> {
+    let mut seen = std::collections::HashSet::new();
+    let mut ret = Vec::new();
+    for (i, record) in records.iter().enumerate() {
+        if record.kind != RecordKind::IncomingRecv {
+            continue;
+        }
+        let log = match record.to_bug_log(i as BugId + 1) {
+            Some(log) => log,
+            None => continue,
+        };
+        if let Some(msgid) = message_id(&log) {
+            if lazy_regex::regex_is_match!(r"^handler\..+\.ack(?:info)?@", &msgid) {
+                continue;
+            }
+            if !seen.insert(msgid) {
+                continue;
+            }
+        }
+        ret.push(log);
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[u8] = b"\x07\n\
+Received: (at submit) by bugs.debian.org; 1 Jan 2024 00:00:00 +0000\n\
+From: Alice <alice@example.com>\n\
+Subject: foo: crashes on startup\n\
+Message-ID: <report@example.com>\n\
+\n\
+Package: foo\n\
+Version: 1.0-1\n\
+\x18\x03 escaped line\n\
+\x03\n\
+\x06\n\
+<strong>Acknowledgement sent</strong> to <code>Alice</code>\n\
+\x03\n\
+\x02\n\
+alice@example.com\x04foo@packages.debian.org\n\
+\x05\n\
+From: owner@bugs.debian.org\n\
+Subject: Bug#1000: Acknowledgement\n\
+Message-ID: <handler.1000.B.123.ack@bugs.debian.org>\n\
+\n\
+Thank you for filing a new Bug report with Debian.\n\
+\x03\n\
+\x02\n\
+-t\n\
+\x05\n\
+To: bob@example.com\n\
+\n\
+Forwarded\n\
+\x03\n\
+\x07\n\
+Received: (at 1000) by bugs.debian.org; 2 Jan 2024 00:00:00 +0000\n\
+From: Bob <bob@example.com>\n\
+Message-ID: <report@example.com>\n\
+\n\
+Duplicate delivery\n\
+\x03\n";
+
+    #[test]
+    fn test_parse_log() {
+        let records = parse_log(SAMPLE).unwrap();
+        assert_eq!(records.len(), 5);
+        assert_eq!(records[0].kind, RecordKind::IncomingRecv);
+        assert!(records[0].text.contains("\x03 escaped line\n"));
+        assert_eq!(records[0].recipients, None);
+        assert_eq!(records[1].kind, RecordKind::Html);
+        assert!(!records[1].is_message());
+        assert_eq!(records[2].kind, RecordKind::Recips);
+        assert_eq!(
+            records[2].recipients,
+            Some(Recipients::List(vec![
+                "alice@example.com".to_string(),
+                "foo@packages.debian.org".to_string()
+            ]))
+        );
+        assert_eq!(records[3].recipients, Some(Recipients::FromHeaders));
+    }
+
+    #[test]
+    fn test_record_to_bug_log() {
+        let records = parse_log(SAMPLE).unwrap();
+        let log = records[0].to_bug_log(1).unwrap();
+        assert_eq!(log.msgnum, 1);
+        assert!(log.header.starts_with("Received: (at submit)"));
+        assert!(log.header.ends_with("Message-ID: <report@example.com>"));
+        assert!(log.body.starts_with("Package: foo\n"));
+        assert!(records[1].to_bug_log(2).is_none());
+    }
+
+    #[test]
+    fn test_messages() {
+        let records = parse_log(SAMPLE).unwrap();
+        let messages = messages(&records);
+        assert_eq!(messages.len(), 4);
+        assert_eq!(
+            messages.iter().map(|m| m.log.msgnum).collect::<Vec<_>>(),
+            vec![1, 3, 4, 5]
+        );
+        assert_eq!(messages[1].kind, RecordKind::Recips);
+    }
+
+    #[test]
+    fn test_bug_log() {
+        let records = parse_log(SAMPLE).unwrap();
+        let logs = bug_log(&records);
+        // The acknowledgement and the duplicate are skipped
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].msgnum, 1);
+    }
+
+    #[test]
+    fn test_bug_log_folded_message_id() {
+        let records = parse_log(
+            b"\x07\nFrom: a@example.com\nMessage-ID:\n <one@example.com>\n\nfirst\n\x03\n\
+              \x07\nFrom: a@example.com\nMessage-ID: <one@example.com>\n\nagain\n\x03\n",
+        )
+        .unwrap();
+        let logs = bug_log(&records);
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].body, "first\n");
+    }
+
+    #[test]
+    fn test_parse_log_empty() {
+        assert!(parse_log(b"").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_log_invalid() {
+        assert!(matches!(parse_log(b"garbage\n"), Err(Error::ParseError(_))));
+        assert!(matches!(
+            parse_log(b"\x07\nFrom: a@example.com\n"),
+            Err(Error::ParseError(_))
+        ));
+        assert!(matches!(
+            parse_log(b"\x02\na@example.com\n\x03\n"),
+            Err(Error::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_log() {
+        let records = read_log(SAMPLE).unwrap();
+        assert_eq!(records.len(), 5);
+    }
+}
@@ -0,0 +1,670 @@
+//! Composition of outgoing mail
+//!
+//! The BTS is driven by mail: bugs are filed by mailing submit@, and changed by
+//! mailing control@ or nnn@. [`Mail`] is a minimal RFC 5322 message builder used
+//! for these messages; it renders UTF-8 bodies, encodes non-ASCII headers as
+//! RFC 2047 encoded words and adds attachments as base64-encoded MIME parts.
+
+use crate::{BugId, BugLog};
+use chrono::{DateTime, FixedOffset};
+use lazy_regex::{regex, regex_is_match};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static MESSAGE_IDS: AtomicUsize = AtomicUsize::new(0);
+
+/// A file attached to an outgoing message
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Attachment {
+    /// The file name the attachment is offered as
+    pub filename: String,
+    /// The MIME type, e.g. "text/x-diff"
+    pub mime_type: String,
+    /// The contents of the attachment
+    pub data: Vec<u8>,
+}
+
+/// An outgoing mail message
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Mail {
+    /// The sender, e.g. "Jane Doe <jane@example.com>"
+    pub from: String,
+    /// The primary recipients
+    pub to: Vec<String>,
+    /// Additional recipients
+    pub cc: Vec<String>,
+    /// The subject, which may contain non-ASCII characters
+    pub subject: String,
+    /// The date of the message
+    pub date: DateTime<FixedOffset>,
+    /// The Message-ID of the message, without angle brackets
+    pub message_id: String,
+    /// Additional headers, e.g. `In-Reply-To`
+    pub headers: Vec<(String, String)>,
+    /// The plain text body
+    pub body: String,
+    /// Files attached to the message
+    pub attachments: Vec<Attachment>,
+}
+
+/// The mail addresses of a debbugs instance
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BtsAddresses {
+    /// The domain the BTS receives mail at, e.g. "bugs.debian.org"
+    pub domain: String,
+}
+
+impl Default for BtsAddresses {
+    fn default() -> Self {
+        BtsAddresses::new("bugs.debian.org")
+    }
+}
+
+impl BtsAddresses {
+    /// Creates the addresses for a BTS receiving mail at `domain`
+    pub fn new(domain: &str) -> Self {
+        BtsAddresses {
+            domain: domain.to_string(),
+        }
+    }
+
+    /// The address new bugs are submitted to
+    pub fn submit(&self) -> String {
+        format!("submit@{}", self.domain)
+    }
+
+    /// The address control messages are sent to
+    pub fn control(&self) -> String {
+        format!("control@{}", self.domain)
+    }
+
+    /// The address for follow-ups to a bug (nnn@)
+    pub fn bug(&self, bug: BugId) -> String {
+        format!("{}@{}", bug, self.domain)
+    }
+
+    /// The address for closing a bug (nnn-done@)
+    pub fn done(&self, bug: BugId) -> String {
+        format!("{}-done@{}", bug, self.domain)
+    }
+
+    /// The address for messages to the submitter of a bug only (nnn-submitter@)
+    pub fn submitter(&self, bug: BugId) -> String {
+        format!("{}-submitter@{}", bug, self.domain)
+    }
+
+    /// The address for follow-ups that are filed without being forwarded to
+    /// the maintainer (nnn-quiet@)
+    pub fn quiet(&self, bug: BugId) -> String {
+        format!("{}-quiet@{}", bug, self.domain)
+    }
+
+    /// The address for follow-ups that are only forwarded to the maintainer
+    /// (nnn-maintonly@)
+    pub fn maintonly(&self, bug: BugId) -> String {
+        format!("{}-maintonly@{}", bug, self.domain)
+    }
+
+    /// The deprecated alias of [`BtsAddresses::done`] (nnn-close@)
+    pub fn close(&self, bug: BugId) -> String {
+        format!("{}-close@{}", bug, self.domain)
+    }
+
+    /// Builds a reply to a message in the log of a bug, sent to nnn@
+    ///
+    /// The subject is taken from the original message, and the reply is
+    /// threaded with `In-Reply-To` and `References` headers. Change
+    /// [`Mail::to`] to reply to one of the other addresses of the bug instead.
+    pub fn reply(&self, bug: BugId, log: &BugLog, from: &str) -> Mail {
+        let mut mail = Mail::new(from, &self.bug(bug), &reply_subject(bug, log));
+        mail.set_in_reply_to(log);
+        mail
+    }
+}
+
+fn reply_subject(bug: BugId, log: &BugLog) -> String {
+    #[cfg(feature = "mailparse")]
+    let subject = log.parsed_headers().subject;
+    // Encoded words can not be decoded without mailparse
+    #[cfg(not(feature = "mailparse"))]
+    let subject = log.raw_header("Subject").filter(|s| !s.contains("=?"));
+    match subject.map(|s| s.trim().to_string()) {
+        Some(s) if regex_is_match!(r"^re:"i, &s) => s,
+        Some(s) if !s.is_empty() => format!("Re: {}", s),
+        _ => format!("Re: Bug#{}", bug),
+    }
+}
+
+/// Extracts the message ids from a header value, without angle brackets
+pub(crate) fn message_ids(value: &str) -> Vec<String> {
+    regex!(r"<([^<>\s]+)>")
+        .captures_iter(value)
+        .map(|c| c[1].to_string())
+        .collect()
+}
+
+/// Extracts the bare address from an address like "Jane Doe <jane@example.com>"
+pub(crate) fn bare_address(address: &str) -> &str {
+    match (address.rfind('<'), address.rfind('>')) {
+        (Some(start), Some(end)) if start < end => address[start + 1..end].trim(),
+        _ => address.trim(),
+    }
+}
+
+fn domain(address: &str) -> &str {
+    bare_address(address)
+        .rsplit_once('@')
+        .map(|(_, domain)| domain)
+        .filter(|d| !d.is_empty())
+        .unwrap_or("localhost")
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes data as base64, without line breaks
+pub(crate) fn base64(data: &[u8]) -> String {
+    let mut ret = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | ((*b as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                ret.push(BASE64_ALPHABET[(n >> (18 - 6 * i)) as usize & 0x3f] as char);
+            } else {
+                ret.push('=');
+            }
+        }
+    }
+    ret
+}
+
+/// Encodes a header value as RFC 2047 encoded words, if it is not plain ASCII
+fn encode_words(value: &str) -> String {
+    if value.is_ascii() && !value.contains("=?") {
+        return value.to_string();
+    }
+    // Keep encoded words short enough for the header to fit in 78 character lines
+    let mut words = Vec::new();
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        if i + c.len_utf8() - start > 39 {
+            words.push(&value[start..i]);
+            start = i;
+        }
+    }
+    words.push(&value[start..]);
+    words
+        .into_iter()
+        .map(|w| format!("=?UTF-8?B?{}?=", base64(w.as_bytes())))
+        .collect::<Vec<_>>()
+        .join("\n ")
+}
+
+/// Encodes the display name of an address, if it is not plain ASCII
+fn encode_address(address: &str) -> String {
+    match address.rfind('<') {
+        Some(start) if !address[..start].is_ascii() => {
+            let name = address[..start].trim().trim_matches('"');
+            format!("{} {}", encode_words(name), &address[start..])
+        }
+        _ => address.to_string(),
+    }
+}
+
+/// Collapses line breaks in a header value, so it can not inject other headers
+fn sanitize(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn quote_parameter(name: &str, value: &str) -> String {
+    if value.is_ascii() {
+        format!(
+            "{}=\"{}\"",
+            name,
+            value.replace('\\', "\\\\").replace('"', "\\\"")
+        )
+    } else {
+        // RFC 2231 extended parameter
+        let encoded: String = value
+            .bytes()
+            .map(|b| {
+                if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                    (b as char).to_string()
+                } else {
+                    format!("%{:02X}", b)
+                }
+            })
+            .collect();
+        format!("{}*=UTF-8''{}", name, encoded)
+    }
+}
+
+fn text_part_headers(body: &str) -> String {
+    format!(
+        "Content-Type: text/plain; charset=utf-8\nContent-Transfer-Encoding: {}\n",
+        if body.is_ascii() { "7bit" } else { "8bit" }
+    )
+}
+
+impl Mail {
+    /// Creates a message with the current date and a newly generated Message-ID
+    pub fn new(from: &str, to: &str, subject: &str) -> Self {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        let message_id = format!(
+            "{}.{}.{}.{}@{}",
+            now.as_secs(),
+            now.subsec_micros(),
+            std::process::id(),
+            MESSAGE_IDS.fetch_add(1, Ordering::Relaxed),
+            domain(from)
+        );
+        Mail {
+            from: from.to_string(),
+            to: vec![to.to_string()],
+            cc: vec![],
+            subject: subject.to_string(),
+            date: DateTime::from_timestamp(now.as_secs() as i64, 0)
+                .unwrap_or_default()
+                .fixed_offset(),
+            message_id,
+            headers: vec![],
+            body: String::new(),
+            attachments: vec![],
+        }
+    }
+
+    /// Makes this message a reply to `log`
+    ///
+    /// Sets the `In-Reply-To` header to the Message-ID of `log`, and the
+    /// `References` header to its references followed by its Message-ID. Does
+    /// nothing if `log` has no Message-ID.
+    pub fn set_in_reply_to(&mut self, log: &BugLog) {
+        let message_id = match log
+            .raw_header("Message-ID")
+            .and_then(|v| message_ids(&v).into_iter().next())
+        {
+            Some(message_id) => message_id,
+            None => return,
+        };
+        // Without References, the parent's In-Reply-To is the best guess (RFC 5322, 3.6.4)
+        let mut references = log
+            .raw_header("References")
+            .or_else(|| log.raw_header("In-Reply-To"))
+            .map(|v| message_ids(&v))
+            .unwrap_or_default();
+        references.push(message_id.clone());
+        self.headers.retain(|(name, _)| {
+            !name.eq_ignore_ascii_case("In-Reply-To") && !name.eq_ignore_ascii_case("References")
+        });
+        self.headers
+            .push(("In-Reply-To".to_string(), format!("<{}>", message_id)));
+        self.headers.push((
+            "References".to_string(),
+            references
+                .iter()
+                .map(|r| format!("<{}>", r))
+                .collect::<Vec<_>>()
+                .join(" "),
+        ));
+    }
+
+    /// Returns the bare addresses of all recipients, for use as the envelope recipients
+    pub fn recipients(&self) -> Vec<String> {
+        self.to
+            .iter()
+            .chain(self.cc.iter())
+            .map(|a| bare_address(a).to_string())
+            .collect()
+    }
+
+    /// Returns the bare address of the sender, for use as the envelope sender
+    pub fn sender(&self) -> String {
+        bare_address(&self.from).to_string()
+    }
+
+    /// Renders the message in RFC 5322 format, with LF line endings
+    pub fn to_rfc5322(&self) -> String {
+        let mut message = String::new();
+        let address_list = |addresses: &[String]| {
+            addresses
+                .iter()
+                .map(|a| encode_address(&sanitize(a)))
+                .collect::<Vec<_>>()
+                .join(",\n ")
+        };
+        message.push_str(&format!(
+            "From: {}\n",
+            encode_address(&sanitize(&self.from))
+        ));
+        message.push_str(&format!("To: {}\n", address_list(&self.to)));
+        if !self.cc.is_empty() {
+            message.push_str(&format!("Cc: {}\n", address_list(&self.cc)));
+        }
+        message.push_str(&format!(
+            "Subject: {}\n",
+            encode_words(&sanitize(&self.subject))
+        ));
+        message.push_str(&format!("Date: {}\n", self.date.to_rfc2822()));
+        message.push_str(&format!("Message-ID: <{}>\n", self.message_id));
+        for (name, value) in self.headers.iter() {
+            message.push_str(&format!(
+                "{}: {}\n",
+                sanitize(name),
+                encode_words(&sanitize(value))
+            ));
+        }
+        message.push_str("MIME-Version: 1.0\n");
+
+        let mut body = self.body.clone();
+        if !body.ends_with('\n') {
+            body.push('\n');
+        }
+
+        if self.attachments.is_empty() {
+            message.push_str(&text_part_headers(&body));
+            message.push('\n');
+            message.push_str(&body);
+            return message;
+        }
+
+        // RFC 2046 limits boundaries to 70 characters, so hash the Message-ID
+        // rather than embedding it
+        let mut hasher = DefaultHasher::new();
+        self.message_id.hash(&mut hasher);
+        let prefix = format!("=_debbugs_{:016x}", hasher.finish());
+        let mut boundary = prefix.clone();
+        let mut counter = 0;
+        while body.contains(&boundary) {
+            counter += 1;
+            boundary = format!("{}_{}", prefix, counter);
+        }
+        message.push_str(&format!(
+            "Content-Type: multipart/mixed; boundary=\"{}\"\n\n",
+            boundary
+        ));
+        message.push_str("This is a multi-part message in MIME format.\n\n");
+        message.push_str(&format!("--{}\n", boundary));
+        message.push_str(&text_part_headers(&body));
+        message.push_str("Content-Disposition: inline\n\n");
+        message.push_str(&body);
+        for attachment in self.attachments.iter() {
+            message.push_str(&format!("--{}\n", boundary));
+            message.push_str(&format!(
+                "Content-Type: {}; {}\n",
+                sanitize(&attachment.mime_type),
+                quote_parameter("name", &attachment.filename)
+            ));
+            message.push_str(&format!(
+                "Content-Disposition: attachment; {}\n",
+                quote_parameter("filename", &attachment.filename)
+            ));
+            message.push_str("Content-Transfer-Encoding: base64\n\n");
+            let encoded = base64(&attachment.data);
+            for line in encoded.as_bytes().chunks(76) {
+                message.push_str(std::str::from_utf8(line).unwrap());
+                message.push('\n');
+            }
+        }
+        message.push_str(&format!("--{}--\n", boundary));
+        message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mail() -> Mail {
+        let mut mail = Mail::new(
+            "Jane Doe <jane@example.com>",
+            "submit@bugs.debian.org",
+            "foo: crashes",
+        );
+        mail.date = DateTime::parse_from_rfc2822("Tue, 2 Jan 2024 10:00:00 +0000").unwrap();
+        mail.message_id = "1234@example.com".to_string();
+        mail
+    }
+
+    #[test]
+    fn test_bts_addresses() {
+        let addresses = BtsAddresses::default();
+        assert_eq!(addresses.submit(), "submit@bugs.debian.org");
+        assert_eq!(addresses.control(), "control@bugs.debian.org");
+        assert_eq!(addresses.bug(1000), "1000@bugs.debian.org");
+        assert_eq!(addresses.done(1000), "1000-done@bugs.debian.org");
+        assert_eq!(addresses.submitter(1000), "1000-submitter@bugs.debian.org");
+        assert_eq!(addresses.quiet(1000), "1000-quiet@bugs.debian.org");
+        assert_eq!(addresses.maintonly(1000), "1000-maintonly@bugs.debian.org");
+        assert_eq!(addresses.close(1000), "1000-close@bugs.debian.org");
+        assert_eq!(
+            BtsAddresses::new("debbugs.gnu.org").submit(),
+            "submit@debbugs.gnu.org"
+        );
+    }
+
+    fn log(header: &str) -> BugLog {
+        BugLog {
+            header: header.to_string(),
+            msgnum: 5,
+            body: "".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_reply() {
+        let addresses = BtsAddresses::default();
+        let original = log("Subject: foo: crashes\nMessage-ID: <c@example.com>\nReferences: <a@example.com>\n <b@example.com>\nIn-Reply-To: <b@example.com>");
+        let reply = addresses.reply(1000, &original, "jane@example.com");
+        assert_eq!(reply.to, vec!["1000@bugs.debian.org"]);
+        assert_eq!(reply.subject, "Re: foo: crashes");
+        assert_eq!(
+            reply.headers,
+            vec![
+                ("In-Reply-To".to_string(), "<c@example.com>".to_string()),
+                (
+                    "References".to_string(),
+                    "<a@example.com> <b@example.com> <c@example.com>".to_string()
+                ),
+            ]
+        );
+        assert!(reply
+            .to_rfc5322()
+            .contains("In-Reply-To: <c@example.com>\nReferences: <a@example.com> <b@example.com> <c@example.com>\n"));
+    }
+
+    #[test]
+    fn test_reply_without_references() {
+        let addresses = BtsAddresses::default();
+        let reply = addresses.reply(
+            1000,
+            &log("Subject: RE: foo\nMessage-ID: <c@example.com>\nIn-Reply-To: <b@example.com>"),
+            "jane@example.com",
+        );
+        assert_eq!(reply.subject, "RE: foo");
+        assert_eq!(
+            reply.headers[1],
+            (
+                "References".to_string(),
+                "<b@example.com> <c@example.com>".to_string()
+            )
+        );
+
+        let reply = addresses.reply(1000, &log("From: a@example.com"), "jane@example.com");
+        assert_eq!(reply.subject, "Re: Bug#1000");
+        assert!(reply.headers.is_empty());
+    }
+
+    #[test]
+    fn test_set_in_reply_to_replaces() {
+        let mut mail = mail();
+        mail.set_in_reply_to(&log("Message-ID: <a@example.com>"));
+        mail.set_in_reply_to(&log("Message-ID: <b@example.com>"));
+        assert_eq!(
+            mail.headers,
+            vec![
+                ("In-Reply-To".to_string(), "<b@example.com>".to_string()),
+                ("References".to_string(), "<b@example.com>".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_message_ids() {
+        assert_eq!(
+            message_ids("<a@example.com> <b@example.com>"),
+            vec!["a@example.com", "b@example.com"]
+        );
+        assert_eq!(message_ids("garbage"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xff, 0xfe, 0x00]), "//4A");
+    }
+
+    #[test]
+    fn test_bare_address() {
+        assert_eq!(bare_address("Jane <jane@example.com>"), "jane@example.com");
+        assert_eq!(bare_address(" jane@example.com "), "jane@example.com");
+    }
+
+    #[test]
+    fn test_new() {
+        let mail = Mail::new("jane@example.com", "submit@bugs.debian.org", "subject");
+        assert!(mail.message_id.ends_with("@example.com"));
+        assert_ne!(
+            mail.message_id,
+            Mail::new("jane@example.com", "x@example.com", "x").message_id
+        );
+        assert_eq!(mail.recipients(), vec!["submit@bugs.debian.org"]);
+        assert_eq!(mail.sender(), "jane@example.com");
+    }
+
+    #[test]
+    fn test_render_plain() {
+        let mut mail = mail();
+        mail.cc.push("Bob <bob@example.com>".to_string());
+        mail.headers
+            .push(("In-Reply-To".to_string(), "<abc@example.com>".to_string()));
+        mail.body = "Hello".to_string();
+        assert_eq!(
+            mail.to_rfc5322(),
+            "From: Jane Doe <jane@example.com>\n\
+             To: submit@bugs.debian.org\n\
+             Cc: Bob <bob@example.com>\n\
+             Subject: foo: crashes\n\
+             Date: Tue, 2 Jan 2024 10:00:00 +0000\n\
+             Message-ID: <1234@example.com>\n\
+             In-Reply-To: <abc@example.com>\n\
+             MIME-Version: 1.0\n\
+             Content-Type: text/plain; charset=utf-8\n\
+             Content-Transfer-Encoding: 7bit\n\
+             \n\
+             Hello\n"
+        );
+        assert_eq!(
+            mail.recipients(),
+            vec!["submit@bugs.debian.org", "bob@example.com"]
+        );
+    }
+
+    #[test]
+    fn test_render_non_ascii() {
+        let mut mail = mail();
+        mail.from = "José Pérez <jose@example.com>".to_string();
+        mail.subject = "café\ncrashes".to_string();
+        mail.body = "Ça plante\n".to_string();
+        let rendered = mail.to_rfc5322();
+        assert!(rendered.contains("From: =?UTF-8?B?Sm9zw6kgUMOpcmV6?= <jose@example.com>\n"));
+        assert!(rendered.contains("Subject: =?UTF-8?B?Y2Fmw6kgY3Jhc2hlcw==?=\n"));
+        assert!(rendered.contains("Content-Transfer-Encoding: 8bit\n\nÇa plante\n"));
+    }
+
+    #[test]
+    fn test_render_long_subject() {
+        let mut mail = mail();
+        mail.subject = "é".repeat(40);
+        let rendered = mail.to_rfc5322();
+        let subject: Vec<&str> = rendered
+            .lines()
+            .skip_while(|l| !l.starts_with("Subject:"))
+            .take_while(|l| l.starts_with("Subject:") || l.starts_with(' '))
+            .collect();
+        assert_eq!(subject.len(), 3);
+        assert!(subject.iter().all(|l| l.len() <= 78));
+    }
+
+    #[test]
+    fn test_render_attachments() {
+        let mut mail = mail();
+        mail.body = "See attached.".to_string();
+        mail.attachments.push(Attachment {
+            filename: "fix.patch".to_string(),
+            mime_type: "text/x-diff".to_string(),
+            data: b"--- a\n+++ b\n".to_vec(),
+        });
+        mail.attachments.push(Attachment {
+            filename: "journal ü.log".to_string(),
+            mime_type: "application/octet-stream".to_string(),
+            data: vec![0; 100],
+        });
+        let rendered = mail.to_rfc5322();
+        let (_, boundary) = lazy_regex::regex_captures!(
+            r#"Content-Type: multipart/mixed; boundary="([^"]+)""#,
+            &rendered
+        )
+        .unwrap();
+        assert_eq!(rendered.matches(&format!("--{}\n", boundary)).count(), 3);
+        assert!(rendered.ends_with(&format!("--{}--\n", boundary)));
+        assert!(rendered.contains("Content-Disposition: inline\n\nSee attached.\n"));
+        assert!(rendered.contains(
+            "Content-Type: text/x-diff; name=\"fix.patch\"\n\
+             Content-Disposition: attachment; filename=\"fix.patch\"\n\
+             Content-Transfer-Encoding: base64\n\
+             \n\
+             LS0tIGEKKysrIGIK\n"
+        ));
+        assert!(rendered.contains("filename*=UTF-8''journal%20%C3%BC.log"));
+        assert!(rendered.lines().all(|l| l.len() <= 78));
+    }
+
+    #[test]
+    fn test_boundary_length() {
+        let mut mail = mail();
+        mail.message_id = format!("<{}@example.com>", "x".repeat(200));
+        mail.attachments.push(Attachment {
+            filename: "a.txt".to_string(),
+            mime_type: "text/plain".to_string(),
+            data: b"a".to_vec(),
+        });
+        let rendered = mail.to_rfc5322();
+        let (_, boundary) = lazy_regex::regex_captures!(
+            r#"Content-Type: multipart/mixed; boundary="([^"]+)""#,
+            &rendered
+        )
+        .unwrap();
+        assert!(boundary.len() <= 70);
+
+        // A body that happens to contain the boundary gets a different one
+        mail.body = format!("--{}\n", boundary);
+        let rendered = mail.to_rfc5322();
+        let (_, other) = lazy_regex::regex_captures!(
+            r#"Content-Type: multipart/mixed; boundary="([^"]+)""#,
+            &rendered
+        )
+        .unwrap();
+        assert_ne!(other, boundary);
+        assert!(other.len() <= 70);
+    }
+}
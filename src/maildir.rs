@@ -0,0 +1,108 @@
+//! Export of bug logs into a Maildir
+//!
+//! Messages are written the same way as by [`crate::mbox::write_mbox`]: with a
+//! synthetic `X-Debbugs-Msgnum` header and without duplicate messages.
+
+use crate::mbox::{dedup_by_message_id, export_message};
+use crate::{BugLog, Error};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static DELIVERIES: AtomicUsize = AtomicUsize::new(0);
+
+/// Creates the `tmp`, `new` and `cur` subdirectories of a Maildir if necessary
+pub fn create_maildir(path: &Path) -> Result<(), Error> {
+    for subdir in ["tmp", "new", "cur"] {
+        std::fs::create_dir_all(path.join(subdir))?;
+    }
+    Ok(())
+}
+
+/// Generates a unique file name for a new message, following the Maildir conventions
+fn unique_name() -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let hostname = std::env::var("HOSTNAME")
+        .ok()
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+        .replace('/', "\\057")
+        .replace(':', "\\072");
+    format!(
+        "{}.M{}P{}Q{}.{}",
+        now.as_secs(),
+        now.subsec_micros(),
+        std::process::id(),
+        DELIVERIES.fetch_add(1, Ordering::Relaxed),
+        hostname
+    )
+}
+
+/// Writes bug log entries into a Maildir
+///
+/// The Maildir is created if it does not exist yet. Messages are delivered into
+/// `new`, by first writing them to `tmp` and then moving them into place.
+///
+/// Returns the paths of the delivered messages.
+///
+/// # Examples
+///
+/// ```no_run
+/// let client = debbugs::blocking::Debbugs::default();
+/// let logs = client.get_bug_log(1000).unwrap();
+/// debbugs::maildir::write_maildir(&logs, std::path::Path::new("Maildir/bug-1000")).unwrap();
+/// ```
+pub fn write_maildir(logs: &[BugLog], path: &Path) -> Result<Vec<PathBuf>, Error> {
+    create_maildir(path)?;
+    let mut ret = Vec::new();
+    for log in dedup_by_message_id(logs) {
+        let name = unique_name();
+        let tmp = path.join("tmp").join(&name);
+        let new = path.join("new").join(&name);
+        std::fs::write(&tmp, export_message(log))?;
+        std::fs::rename(&tmp, &new)?;
+        ret.push(new);
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_maildir() {
+        let td = std::env::temp_dir().join(format!("debbugs-maildir-{}", unique_name()));
+        let logs = vec![
+            BugLog {
+                header: "From: a@example.com\nMessage-ID: <1@example.com>".to_string(),
+                msgnum: 5,
+                body: "From the start\n".to_string(),
+            },
+            BugLog {
+                header: "From: a@example.com\nMessage-ID: <1@example.com>".to_string(),
+                msgnum: 7,
+                body: "duplicate\n".to_string(),
+            },
+            BugLog {
+                header: "From: b@example.com".to_string(),
+                msgnum: 10,
+                body: "reply\n".to_string(),
+            },
+        ];
+        let paths = write_maildir(&logs, &td).unwrap();
+        assert_eq!(paths.len(), 2);
+        assert!(td.join("cur").is_dir());
+        assert_eq!(std::fs::read_dir(td.join("tmp")).unwrap().count(), 0);
+        assert_eq!(
+            std::fs::read_to_string(&paths[0]).unwrap(),
+            "From: a@example.com\nMessage-ID: <1@example.com>\nX-Debbugs-Msgnum: 5\n\nFrom the start\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&paths[1]).unwrap(),
+            "From: b@example.com\nX-Debbugs-Msgnum: 10\n\nreply\n"
+        );
+        std::fs::remove_dir_all(&td).unwrap();
+    }
+}
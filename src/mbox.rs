@@ -0,0 +1,490 @@
+//! Support for the mbox exports of bug logs
+//!
+//! `bugreport.cgi` can export the messages of a bug as an mbox. Unlike the SOAP
+//! `get_bug_log` call, these contain the full MIME structure of every message,
+//! including attachments.
+//!
+//! Bug logs can also be written out as an mbox, for reading in a mail client.
+
+use crate::{BugId, BugLog, Error};
+use std::io::Write;
+
+/// Header added to exported messages to record their message number
+pub const MSGNUM_HEADER: &str = "X-Debbugs-Msgnum";
+
+/// The kind of mbox to retrieve from `bugreport.cgi`
+#[derive(Debug, PartialEq, Eq, Default, Clone, Copy)]
+pub enum MboxKind {
+    /// All messages, as received by the BTS (`mbox=yes`, default)
+    #[default]
+    Full,
+    /// The messages as they were forwarded to the maintainer (`mboxmaint=yes`)
+    Maintainer,
+    /// All messages, preceded by a message describing the bug's status (`mboxstatus=yes`)
+    Status,
+}
+
+impl MboxKind {
+    fn query_parameter(&self) -> &'static str {
+        match self {
+            MboxKind::Full => "mbox",
+            MboxKind::Maintainer => "mboxmaint",
+            MboxKind::Status => "mboxstatus",
+        }
+    }
+}
+
+/// Returns the `bugreport.cgi` URL for an mbox, given the base URL of the CGI scripts
+pub(crate) fn mbox_url(cgi_url: &str, bug_id: BugId, kind: MboxKind) -> String {
+    format!(
+        "{}bugreport.cgi?bug={};{}=yes",
+        cgi_url,
+        bug_id,
+        kind.query_parameter()
+    )
+}
+
+/// Splits an mbox into its individual messages
+///
+/// Lines that were escaped by prefixing `>` to `From ` (mboxrd style, as used by
+/// debbugs) are unescaped. The `From ` separator lines themselves are dropped.
+pub fn split_mbox(input: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+    if input.is_empty() {
+        return Ok(vec![]);
+    }
+    if !input.starts_with(b"From ") {
+        return Err(Error::ParseError(
+            "mbox does not start with a From line".to_string(),
+        ));
+    }
+    let mut messages = Vec::new();
+    let mut current: Option<Vec<u8>> = None;
+    let mut previous_blank = true;
+    for line in input.split_inclusive(|b| *b == b'\n') {
+        if previous_blank && line.starts_with(b"From ") {
+            if let Some(message) = current.take() {
+                messages.push(finish_message(message));
+            }
+            current = Some(Vec::new());
+            previous_blank = false;
+            continue;
+        }
+        previous_blank = line == b"\n" || line == b"\r\n";
+        let message = current.get_or_insert_with(Vec::new);
+        let unescaped = if is_escaped_from(line) {
+            &line[1..]
+        } else {
+            line
+        };
+        message.extend_from_slice(unescaped);
+    }
+    if let Some(message) = current.take() {
+        messages.push(finish_message(message));
+    }
+    Ok(messages)
+}
+
+/// Returns whether the line matches `^>+From `
+fn is_escaped_from(line: &[u8]) -> bool {
+    match line.iter().position(|b| *b != b'>') {
+        Some(ix) => ix > 0 && line[ix..].starts_with(b"From "),
+        None => false,
+    }
+}
+
+/// Removes the blank line separating a message from the next `From ` line
+fn finish_message(mut message: Vec<u8>) -> Vec<u8> {
+    if message.ends_with(b"\n\n") {
+        message.pop();
+    } else if message.ends_with(b"\r\n\r\n") {
+        message.truncate(message.len() - 2);
+    }
+    message
+}
+
+/// Converts a raw message to text, keeping parts that are not valid UTF-8
+///
+/// Parts sent in another charset than UTF-8 with an 8bit or binary transfer
+/// encoding are converted, so that [`BugLog::parsed`] can still decode them:
+/// text parts are transcoded to UTF-8 from their declared charset, and other
+/// parts are re-encoded as base64.
+#[cfg(feature = "mailparse")]
+fn message_text(message: &[u8]) -> String {
+    if let Ok(text) = std::str::from_utf8(message) {
+        return text.to_string();
+    }
+    let mail = match mailparse::parse_mail(message) {
+        Ok(mail) => mail,
+        Err(_) => return String::from_utf8_lossy(message).into_owned(),
+    };
+    let mut out = Vec::with_capacity(message.len());
+    let mut pos = 0;
+    reencode_parts(&mail, message, &mut out, &mut pos);
+    out.extend_from_slice(&message[pos..]);
+    // Anything left that is not UTF-8, e.g. raw 8bit headers, is replaced
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Without mailparse, bytes that are not valid UTF-8 are replaced
+#[cfg(not(feature = "mailparse"))]
+fn message_text(message: &[u8]) -> String {
+    String::from_utf8_lossy(message).into_owned()
+}
+
+/// Copies `message` up to the end of `part` to `out`, converting the leaf parts
+/// that are not valid UTF-8; `pos` is the position in `message` copied up to
+#[cfg(feature = "mailparse")]
+fn reencode_parts(
+    part: &mailparse::ParsedMail,
+    message: &[u8],
+    out: &mut Vec<u8>,
+    pos: &mut usize,
+) {
+    use mailparse::body::Body;
+
+    if !part.subparts.is_empty() {
+        for subpart in part.subparts.iter() {
+            reencode_parts(subpart, message, out, pos);
+        }
+        return;
+    }
+    if std::str::from_utf8(part.raw_bytes).is_ok()
+        || !matches!(
+            part.get_body_encoded(),
+            Body::SevenBit(_) | Body::EightBit(_) | Body::Binary(_)
+        )
+    {
+        return;
+    }
+    let replacement = match reencode_part(part) {
+        Some(replacement) => replacement,
+        None => return,
+    };
+    // Parts are slices of the message they were parsed from
+    let start = part.raw_bytes.as_ptr() as usize - message.as_ptr() as usize;
+    out.extend_from_slice(&message[*pos..start]);
+    out.extend_from_slice(replacement.as_bytes());
+    *pos = start + part.raw_bytes.len();
+}
+
+/// Renders a part with its body transcoded to UTF-8, or encoded as base64
+#[cfg(feature = "mailparse")]
+fn reencode_part(part: &mailparse::ParsedMail) -> Option<String> {
+    let is_text = part.ctype.mimetype.starts_with("text/");
+    let mut ret = String::new();
+    for header in part.headers.iter() {
+        let key = header.get_key();
+        if key.eq_ignore_ascii_case("Content-Transfer-Encoding")
+            || (is_text && key.eq_ignore_ascii_case("Content-Type"))
+        {
+            continue;
+        }
+        ret.push_str(&format!(
+            "{}: {}\n",
+            key,
+            String::from_utf8_lossy(header.get_value_raw())
+        ));
+    }
+    if is_text {
+        let body = part.get_body().ok()?;
+        ret.push_str(&format!(
+            "Content-Type: {}; charset=utf-8",
+            part.ctype.mimetype
+        ));
+        for (name, value) in part.ctype.params.iter() {
+            if name != "charset" {
+                ret.push_str(&format!("; {}=\"{}\"", name, value));
+            }
+        }
+        ret.push_str("\nContent-Transfer-Encoding: 8bit\n\n");
+        ret.push_str(&body);
+    } else {
+        let data = part.get_body_raw().ok()?;
+        ret.push_str("Content-Transfer-Encoding: base64\n\n");
+        let encoded = crate::mail::base64(&data);
+        for line in encoded.as_bytes().chunks(76) {
+            ret.push_str(std::str::from_utf8(line).ok()?);
+            ret.push('\n');
+        }
+    }
+    Some(ret)
+}
+
+/// Converts a raw message into a [`BugLog`], splitting header and body
+pub(crate) fn message_to_bug_log(message: &[u8], msgnum: BugId) -> BugLog {
+    let text = message_text(message).replace("\r\n", "\n");
+    let (header, body) = match text.find("\n\n") {
+        Some(ix) => (&text[..ix], &text[ix + 2..]),
+        None => (text.trim_end_matches('\n'), ""),
+    };
+    BugLog {
+        header: header.to_string(),
+        msgnum,
+        body: body.to_string(),
+    }
+}
+
+/// Parses an mbox into [`BugLog`] entries
+///
+/// The mbox format does not carry the message numbers of the bug log, so messages
+/// are numbered sequentially from 1 in the order they appear in the mbox.
+///
+/// Parts of messages that are not valid UTF-8 are converted so they can be
+/// decoded with [`BugLog::parsed`]; without the `mailparse` feature, invalid
+/// bytes are replaced instead.
+pub fn parse_mbox(input: &[u8]) -> Result<Vec<BugLog>, Error> {
+    Ok(split_mbox(input)?
+        .iter()
+        .enumerate()
+        .map(|(i, message)| message_to_bug_log(message, i as BugId + 1))
+        .collect())
+}
+
+/// Returns the logs with duplicate messages (by Message-ID) removed
+///
+/// The first occurrence of a message is kept. Messages without a Message-ID
+/// are never considered duplicates.
+pub(crate) fn dedup_by_message_id(logs: &[BugLog]) -> Vec<&BugLog> {
+    let mut seen = std::collections::HashSet::new();
+    logs.iter()
+        .filter(|log| match crate::logfile::message_id(log) {
+            Some(msgid) => seen.insert(msgid),
+            None => true,
+        })
+        .collect()
+}
+
+/// Renders a log entry as a complete message, adding the message number header
+pub(crate) fn export_message(log: &BugLog) -> String {
+    let mut message = log.header.trim_end_matches('\n').to_string();
+    if log.raw_header(MSGNUM_HEADER).is_none() {
+        message.push_str(&format!("\n{}: {}", MSGNUM_HEADER, log.msgnum));
+    }
+    message.push_str("\n\n");
+    message.push_str(&log.body);
+    if !message.ends_with('\n') {
+        message.push('\n');
+    }
+    message
+}
+
+/// Builds the `From ` separator line for a message
+fn from_line(log: &BugLog) -> String {
+    let sender = log
+        .raw_header("From")
+        .and_then(|from| {
+            let addr = match (from.rfind('<'), from.rfind('>')) {
+                (Some(start), Some(end)) if start < end => &from[start + 1..end],
+                _ => from.split_whitespace().find(|w| w.contains('@'))?,
+            };
+            Some(addr.to_string())
+        })
+        .unwrap_or_else(|| "MAILER-DAEMON".to_string());
+    let date = log
+        .raw_header("Date")
+        .and_then(|date| chrono::DateTime::parse_from_rfc2822(&date).ok())
+        .map(|date| date.to_utc())
+        .unwrap_or_default();
+    format!("From {} {}", sender, date.format("%a %b %e %H:%M:%S %Y"))
+}
+
+/// Writes bug log entries as an mbox
+///
+/// Lines starting with `From ` (optionally preceded by `>` characters) are escaped
+/// mboxrd style, so the mbox can be read back without corrupting messages.
+/// Every message gets a synthetic `X-Debbugs-Msgnum` header with its message
+/// number, and duplicate messages (by Message-ID) are only written once.
+///
+/// # Examples
+///
+/// ```no_run
+/// let client = debbugs::blocking::Debbugs::default();
+/// let logs = client.get_bug_log(1000).unwrap();
+/// let f = std::fs::File::create("1000.mbox").unwrap();
+/// debbugs::mbox::write_mbox(&logs, f).unwrap();
+/// ```
+pub fn write_mbox<W: Write>(logs: &[BugLog], mut writer: W) -> Result<(), Error> {
+    for log in dedup_by_message_id(logs) {
+        writeln!(writer, "{}", from_line(log))?;
+        for line in export_message(log).split_inclusive('\n') {
+            if line.starts_with("From ") || is_escaped_from(line.as_bytes()) {
+                writer.write_all(b">")?;
+            }
+            writer.write_all(line.as_bytes())?;
+        }
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[u8] = b"From alice@example.com Mon Jan  1 00:00:00 2024\n\
+From: Alice <alice@example.com>\n\
+Subject: foo: crashes\n\
+Content-Type: multipart/mixed; boundary=\"xyz\"\n\
+\n\
+--xyz\n\
+Content-Type: text/plain\n\
+\n\
+>From the start it crashes.\n\
+>>From here too.\n\
+--xyz--\n\
+\n\
+From bob@example.com Tue Jan  2 00:00:00 2024\n\
+From: Bob <bob@example.com>\n\
+Subject: Re: foo: crashes\n\
+\n\
+Confirmed.\n";
+
+    #[test]
+    fn test_split_mbox() {
+        let messages = split_mbox(SAMPLE).unwrap();
+        assert_eq!(messages.len(), 2);
+        let first = String::from_utf8(messages[0].clone()).unwrap();
+        assert!(first.starts_with("From: Alice"));
+        assert!(first.contains("\nFrom the start it crashes.\n"));
+        assert!(first.contains("\n>From here too.\n"));
+        assert!(first.ends_with("--xyz--\n"));
+    }
+
+    #[test]
+    fn test_split_mbox_empty() {
+        assert!(split_mbox(b"").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_split_mbox_invalid() {
+        assert!(matches!(
+            split_mbox(b"<html>Error</html>"),
+            Err(Error::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_mbox() {
+        let logs = parse_mbox(SAMPLE).unwrap();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].msgnum, 1);
+        assert!(logs[0]
+            .header
+            .ends_with("Content-Type: multipart/mixed; boundary=\"xyz\""));
+        assert!(logs[0].body.starts_with("--xyz\n"));
+        assert_eq!(logs[1].msgnum, 2);
+        assert_eq!(logs[1].body, "Confirmed.\n");
+    }
+
+    #[cfg(feature = "mailparse")]
+    #[test]
+    fn test_parse_mbox_8bit() {
+        let mut mbox = b"From alice@example.com Mon Jan  1 00:00:00 2024\n\
+From: Alice <alice@example.com>\n\
+Subject: latin-1\n\
+MIME-Version: 1.0\n\
+Content-Type: multipart/mixed; boundary=\"b\"\n\
+\n\
+--b\n\
+Content-Type: text/plain; charset=iso-8859-1; format=flowed\n\
+Content-Transfer-Encoding: 8bit\n\
+\n\
+Caf\xe9 cr\xe8me\n\
+--b\n\
+Content-Type: application/octet-stream\n\
+Content-Disposition: attachment; filename=\"data.bin\"\n\
+Content-Transfer-Encoding: binary\n\
+\n\
+\xff\xfe\x00\x01\n\
+--b\n\
+Content-Type: text/plain; charset=utf-8\n\
+\n\
+Already UTF-8: \xc3\xa9\n\
+--b--\n\
+\n"
+        .to_vec();
+        mbox.extend_from_slice(
+            b"From bob@example.com Tue Jan  2 00:00:00 2024\n\
+From: Bob <bob@example.com>\n\
+Content-Type: text/plain; charset=iso-8859-1\n\
+\n\
+Gr\xfc\xdfe\n",
+        );
+        let logs = parse_mbox(&mbox).unwrap();
+        assert_eq!(logs.len(), 2);
+        assert!(!logs[0].body.contains('\u{fffd}'));
+
+        let message = logs[0].parsed().unwrap();
+        assert_eq!(
+            message.text.as_deref(),
+            Some("Café crème\nAlready UTF-8: é")
+        );
+        assert_eq!(message.attachments.len(), 1);
+        assert_eq!(message.attachments[0].filename.as_deref(), Some("data.bin"));
+        assert_eq!(message.attachments[0].data, b"\xff\xfe\x00\x01");
+        assert!(logs[0].body.contains("format=\"flowed\""));
+
+        assert_eq!(logs[1].body, "Grüße\n");
+        assert_eq!(logs[1].parsed().unwrap().text.as_deref(), Some("Grüße\n"));
+    }
+
+    fn log(msgnum: BugId, header: &str, body: &str) -> BugLog {
+        BugLog {
+            header: header.to_string(),
+            msgnum,
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_write_mbox() {
+        let logs = vec![
+            log(
+                5,
+                "From: Alice <alice@example.com>\nDate: Tue, 2 Jan 2024 10:00:00 +0100\nMessage-ID: <1@example.com>",
+                "From the start.\n>From quoted.\nplain\n",
+            ),
+            // The same message, without angle brackets
+            log(10, "From: bob@example.com\nMessage-ID: 1@example.com", "dup\n"),
+            log(12, "Subject: no sender", "no newline"),
+        ];
+        let mut out = Vec::new();
+        write_mbox(&logs, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(
+            out,
+            "From alice@example.com Tue Jan  2 09:00:00 2024\n\
+From: Alice <alice@example.com>\n\
+Date: Tue, 2 Jan 2024 10:00:00 +0100\n\
+Message-ID: <1@example.com>\n\
+X-Debbugs-Msgnum: 5\n\
+\n\
+>From the start.\n\
+>>From quoted.\n\
+plain\n\
+\n\
+From MAILER-DAEMON Thu Jan  1 00:00:00 1970\n\
+Subject: no sender\n\
+X-Debbugs-Msgnum: 12\n\
+\n\
+no newline\n\
+\n"
+        );
+    }
+
+    #[test]
+    fn test_write_mbox_roundtrip() {
+        let logs = vec![
+            log(1, "From: a@example.com", "From here\n\nFrom there\n"),
+            log(2, "From: b@example.com", "reply\n"),
+        ];
+        let mut out = Vec::new();
+        write_mbox(&logs, &mut out).unwrap();
+        let parsed = parse_mbox(&out).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].header, "From: a@example.com\nX-Debbugs-Msgnum: 1");
+        assert_eq!(parsed[0].body, "From here\n\nFrom there\n");
+        assert_eq!(parsed[1].body, "reply\n");
+    }
+}
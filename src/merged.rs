@@ -0,0 +1,214 @@
+//! Groups of merged bugs
+//!
+//! Merged bugs are reports of the same problem; the BTS keeps them in sync and
+//! lists the merge partners of each in `mergedwith`. [`MergedGroups`] groups
+//! them into clusters, so that a problem reported several times is counted
+//! and listed once. Every cluster is represented by its lowest bug number.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use debbugs::blocking::Debbugs;
+//!
+//! let client = Debbugs::default();
+//! let mut reports = client.get_status(&[1000, 1001, 1002]).unwrap();
+//! let groups = client.resolve_merged(&mut reports).unwrap();
+//! println!("{} distinct problems", groups.len());
+//! ```
+
+use crate::{BugId, BugReport};
+use std::collections::{BTreeMap, HashMap};
+
+/// Merged bugs, grouped into clusters
+#[derive(Debug, Default, Clone)]
+pub struct MergedGroups {
+    parents: HashMap<BugId, BugId>,
+}
+
+impl MergedGroups {
+    /// Creates an empty set of groups
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Groups the given bugs and their merge partners
+    pub fn from_reports(reports: &HashMap<BugId, BugReport>) -> Self {
+        let mut groups = Self::new();
+        for (bug, report) in reports {
+            groups.insert(*bug);
+            for partner in report.mergedwith.as_deref().unwrap_or_default() {
+                groups.merge(*bug, *partner);
+            }
+        }
+        groups
+    }
+
+    /// Adds a bug that is not merged with any others yet
+    pub fn insert(&mut self, bug: BugId) {
+        self.parents.entry(bug).or_insert(bug);
+    }
+
+    /// Records that two bugs are merged
+    pub fn merge(&mut self, a: BugId, b: BugId) {
+        let a = self.root(a);
+        let b = self.root(b);
+        // Keep the lowest bug number as the root, so it is the canonical one
+        if a < b {
+            self.parents.insert(b, a);
+        } else if b < a {
+            self.parents.insert(a, b);
+        }
+    }
+
+    fn root(&mut self, bug: BugId) -> BugId {
+        self.insert(bug);
+        let mut root = bug;
+        while self.parents[&root] != root {
+            root = self.parents[&root];
+        }
+        // Compress the path, so later lookups are fast
+        let mut current = bug;
+        while current != root {
+            let next = self.parents[&current];
+            self.parents.insert(current, root);
+            current = next;
+        }
+        root
+    }
+
+    /// Returns the bug that represents the cluster of `bug`
+    ///
+    /// This is the lowest bug number in the cluster, or `None` if the bug is
+    /// not known.
+    pub fn canonical(&self, bug: BugId) -> Option<BugId> {
+        let mut current = *self.parents.get(&bug)?;
+        while self.parents[&current] != current {
+            current = self.parents[&current];
+        }
+        Some(current)
+    }
+
+    /// Returns whether two bugs are in the same cluster
+    pub fn same_group(&self, a: BugId, b: BugId) -> bool {
+        self.canonical(a).is_some() && self.canonical(a) == self.canonical(b)
+    }
+
+    /// Returns the clusters, each sorted, ordered by their canonical bug
+    pub fn groups(&self) -> Vec<Vec<BugId>> {
+        let mut groups: BTreeMap<BugId, Vec<BugId>> = BTreeMap::new();
+        for bug in self.parents.keys() {
+            if let Some(canonical) = self.canonical(*bug) {
+                groups.entry(canonical).or_default().push(*bug);
+            }
+        }
+        groups
+            .into_values()
+            .map(|mut group| {
+                group.sort();
+                group
+            })
+            .collect()
+    }
+
+    /// Returns the bugs in the cluster of `bug`, sorted
+    pub fn group(&self, bug: BugId) -> Vec<BugId> {
+        let canonical = match self.canonical(bug) {
+            Some(canonical) => canonical,
+            None => return vec![],
+        };
+        let mut group: Vec<BugId> = self
+            .parents
+            .keys()
+            .copied()
+            .filter(|b| self.canonical(*b) == Some(canonical))
+            .collect();
+        group.sort();
+        group
+    }
+
+    /// Returns the number of clusters
+    pub fn len(&self) -> usize {
+        self.parents
+            .iter()
+            .filter(|(bug, parent)| bug == parent)
+            .count()
+    }
+
+    /// Returns whether there are no bugs
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    /// Returns the merge partners of `reports` that are not in `reports` themselves
+    pub fn missing(&self, reports: &HashMap<BugId, BugReport>) -> Vec<BugId> {
+        let mut missing: Vec<BugId> = self
+            .parents
+            .keys()
+            .copied()
+            .filter(|bug| !reports.contains_key(bug))
+            .collect();
+        missing.sort();
+        missing
+    }
+
+    /// Returns one report per cluster, that of the canonical bug if available
+    ///
+    /// The reports are ordered by bug number.
+    pub fn dedup<'a>(&self, reports: &'a HashMap<BugId, BugReport>) -> Vec<(BugId, &'a BugReport)> {
+        let mut ret: BTreeMap<BugId, (BugId, &BugReport)> = BTreeMap::new();
+        for (bug, report) in reports {
+            let canonical = self.canonical(*bug).unwrap_or(*bug);
+            let entry = ret.entry(canonical).or_insert((*bug, report));
+            if *bug < entry.0 {
+                *entry = (*bug, report);
+            }
+        }
+        ret.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(mergedwith: &str) -> BugReport {
+        BugReport::from_fields(&format!("<mergedwith>{}</mergedwith>", mergedwith))
+    }
+
+    #[test]
+    fn test_groups() {
+        let reports = HashMap::from([
+            (5, report("3 9")),
+            (3, report("5 9")),
+            (7, report("")),
+            (12, report("11")),
+        ]);
+        let groups = MergedGroups::from_reports(&reports);
+        assert_eq!(groups.groups(), vec![vec![3, 5, 9], vec![7], vec![11, 12]]);
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups.canonical(9), Some(3));
+        assert_eq!(groups.canonical(12), Some(11));
+        assert_eq!(groups.canonical(100), None);
+        assert!(groups.same_group(5, 9));
+        assert!(!groups.same_group(5, 7));
+        assert_eq!(groups.group(5), vec![3, 5, 9]);
+        assert_eq!(groups.missing(&reports), vec![9, 11]);
+
+        let dedup = groups.dedup(&reports);
+        assert_eq!(
+            dedup.iter().map(|(bug, _)| *bug).collect::<Vec<_>>(),
+            vec![3, 7, 12]
+        );
+    }
+
+    #[test]
+    fn test_merge_transitive() {
+        let mut groups = MergedGroups::new();
+        groups.merge(4, 3);
+        groups.merge(2, 1);
+        groups.merge(4, 2);
+        assert_eq!(groups.groups(), vec![vec![1, 2, 3, 4]]);
+        assert_eq!(groups.canonical(3), Some(1));
+        assert!(!groups.is_empty());
+    }
+}
@@ -0,0 +1,221 @@
+//! MIME-aware decoding of bug log messages
+//!
+//! Messages retrieved with [`crate::Debbugs::get_bug_mbox`] keep their full MIME
+//! structure; messages from `get_bug_log` have already been flattened by the
+//! server, in which case the body is used as-is.
+
+use crate::BugLog;
+use mailparse::{DispositionType, MailParseError, ParsedMail};
+
+/// An attachment of a message
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Attachment {
+    /// The file name of the attachment, if one was specified
+    pub filename: Option<String>,
+    /// The MIME type of the attachment (e.g. "text/x-diff")
+    pub mime_type: String,
+    /// The decoded contents of the attachment
+    pub data: Vec<u8>,
+}
+
+impl Attachment {
+    /// Returns the contents of the attachment as text, if it is valid UTF-8
+    pub fn text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.data).ok()
+    }
+}
+
+/// A decoded message from a bug log
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct ParsedMessage {
+    /// The plain text body, with all inline text parts concatenated
+    pub text: Option<String>,
+    /// The HTML body, if the message has one
+    pub html: Option<String>,
+    /// Attachments, in the order they appear in the message
+    pub attachments: Vec<Attachment>,
+}
+
+fn filename(part: &ParsedMail) -> Option<String> {
+    part.get_content_disposition()
+        .params
+        .get("filename")
+        .or_else(|| part.ctype.params.get("name"))
+        .cloned()
+}
+
+/// Decodes a text part, normalizing line endings
+fn text_body(part: &ParsedMail) -> Result<String, MailParseError> {
+    Ok(part.get_body()?.replace("\r\n", "\n"))
+}
+
+fn collect(part: &ParsedMail, ret: &mut ParsedMessage) -> Result<(), MailParseError> {
+    if part.ctype.mimetype.starts_with("multipart/") && !part.subparts.is_empty() {
+        for subpart in part.subparts.iter() {
+            collect(subpart, ret)?;
+        }
+        return Ok(());
+    }
+
+    let disposition = part.get_content_disposition();
+    let filename = filename(part);
+    let is_attachment =
+        disposition.disposition == DispositionType::Attachment || filename.is_some();
+
+    match part.ctype.mimetype.as_str() {
+        "text/plain" if !is_attachment => {
+            let body = text_body(part)?;
+            match ret.text.as_mut() {
+                Some(text) => {
+                    if !text.ends_with('\n') {
+                        text.push('\n');
+                    }
+                    text.push_str(&body);
+                }
+                None => ret.text = Some(body),
+            }
+        }
+        "text/html" if !is_attachment && ret.html.is_none() => {
+            ret.html = Some(text_body(part)?);
+        }
+        // A multipart message without parts, e.g. a body that was already flattened
+        mimetype if mimetype.starts_with("multipart/") => {
+            let body = part.get_body_raw()?;
+            ret.text = Some(String::from_utf8_lossy(&body).into_owned());
+        }
+        mimetype => {
+            ret.attachments.push(Attachment {
+                filename,
+                mime_type: mimetype.to_string(),
+                data: part.get_body_raw()?,
+            });
+        }
+    }
+    Ok(())
+}
+
+impl BugLog {
+    /// Decodes the full message, including its MIME structure
+    ///
+    /// Charsets and transfer encodings (quoted-printable, base64) are decoded.
+    /// Inline `text/plain` parts make up the text body; the first inline
+    /// `text/html` part is the HTML alternative and all other parts are
+    /// returned as attachments.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use debbugs::blocking::Debbugs;
+    /// use debbugs::mbox::MboxKind;
+    ///
+    /// let client = Debbugs::default();
+    /// for log in client.get_bug_mbox(1000, MboxKind::Full).unwrap() {
+    ///     let message = log.parsed().unwrap();
+    ///     for attachment in message.attachments {
+    ///         println!("{:?} ({})", attachment.filename, attachment.mime_type);
+    ///     }
+    /// }
+    /// ```
+    pub fn parsed(&self) -> Result<ParsedMessage, MailParseError> {
+        let raw = format!("{}\n\n{}", self.header.trim_end_matches('\n'), self.body);
+        let mail = mailparse::parse_mail(raw.as_bytes())?;
+        let mut ret = ParsedMessage::default();
+        collect(&mail, &mut ret)?;
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(header: &str, body: &str) -> BugLog {
+        BugLog {
+            header: header.to_string(),
+            msgnum: 5,
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parsed_plain() {
+        let message = log(
+            "From: a@example.com\nContent-Type: text/plain; charset=iso-8859-1\nContent-Transfer-Encoding: quoted-printable",
+            "Caf=E9 au lait\n",
+        )
+        .parsed()
+        .unwrap();
+        assert_eq!(message.text.as_deref(), Some("Café au lait\n"));
+        assert_eq!(message.html, None);
+        assert!(message.attachments.is_empty());
+    }
+
+    #[test]
+    fn test_parsed_no_content_type() {
+        let message = log("From: a@example.com", "Hello\n").parsed().unwrap();
+        assert_eq!(message.text.as_deref(), Some("Hello\n"));
+    }
+
+    #[test]
+    fn test_parsed_multipart() {
+        let message = log(
+            "From: a@example.com\nMIME-Version: 1.0\nContent-Type: multipart/mixed; boundary=\"outer\"",
+            "--outer\n\
+Content-Type: multipart/alternative; boundary=\"inner\"\n\
+\n\
+--inner\n\
+Content-Type: text/plain; charset=utf-8\n\
+\n\
+See attached.\n\
+--inner\n\
+Content-Type: text/html; charset=utf-8\n\
+\n\
+<p>See attached.</p>\n\
+--inner--\n\
+--outer\n\
+Content-Type: text/x-diff\n\
+Content-Disposition: attachment; filename=\"fix.patch\"\n\
+Content-Transfer-Encoding: base64\n\
+\n\
+LS0tIGEvZm9vCisrKyBiL2Zvbwo=\n\
+--outer\n\
+Content-Type: image/png; name=\"screenshot.png\"\n\
+Content-Transfer-Encoding: base64\n\
+\n\
+iVBORw==\n\
+--outer--\n",
+        )
+        .parsed()
+        .unwrap();
+        assert_eq!(message.text.as_deref(), Some("See attached."));
+        assert_eq!(message.html.as_deref(), Some("<p>See attached.</p>"));
+        assert_eq!(message.attachments.len(), 2);
+        assert_eq!(
+            message.attachments[0].filename.as_deref(),
+            Some("fix.patch")
+        );
+        assert_eq!(message.attachments[0].mime_type, "text/x-diff");
+        assert_eq!(
+            message.attachments[0].text(),
+            Some("--- a/foo\n+++ b/foo\n")
+        );
+        assert_eq!(
+            message.attachments[1].filename.as_deref(),
+            Some("screenshot.png")
+        );
+        assert_eq!(message.attachments[1].data, b"\x89PNG");
+    }
+
+    #[test]
+    fn test_parsed_flattened_multipart() {
+        // get_bug_log keeps the original headers, but returns a flattened body
+        let message = log(
+            "From: a@example.com\nContent-Type: multipart/mixed; boundary=\"gone\"",
+            "Just the text.\n",
+        )
+        .parsed()
+        .unwrap();
+        assert_eq!(message.text.as_deref(), Some("Just the text.\n"));
+        assert!(message.attachments.is_empty());
+    }
+}
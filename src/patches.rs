@@ -0,0 +1,347 @@
+//! Extraction of patches from bug logs
+//!
+//! Patches end up in bug logs in several ways: as attachments, inlined in the
+//! message body, or as mails generated by `git format-patch`. This module finds
+//! all of them and can write them out for use with `git am` or `patch`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use debbugs::blocking::Debbugs;
+//! use debbugs::mbox::MboxKind;
+//!
+//! let client = Debbugs::default();
+//! let logs = client.get_bug_mbox(1000, MboxKind::Full).unwrap();
+//! if let Some(patch) = debbugs::patches::find_patches(&logs).last() {
+//!     let path = patch.write_to(std::path::Path::new(".")).unwrap();
+//!     println!("Latest patch from {:?} written to {}", patch.author, path.display());
+//! }
+//! ```
+
+use crate::mime::Attachment;
+use crate::{BugId, BugLog, Error};
+use lazy_regex::{regex, regex_is_match};
+use mailparse::MailHeaderMap;
+use std::path::{Path, PathBuf};
+
+/// How a patch was included in a message
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PatchSource {
+    /// The patch was attached to the message
+    Attachment,
+    /// The patch was part of the message body
+    Inline,
+    /// The message (or attachment) was generated by `git format-patch`
+    FormatPatch,
+}
+
+/// A patch found in a bug log
+#[derive(Debug, Clone)]
+pub struct Patch {
+    /// Message number of the message containing the patch
+    pub msgnum: BugId,
+    /// Sender of the message containing the patch
+    pub author: Option<String>,
+    /// Date of the message containing the patch, as found in the `Date` header
+    pub date: Option<String>,
+    /// Subject of the message containing the patch
+    pub subject: Option<String>,
+    /// File name of the attachment the patch was found in
+    pub filename: Option<String>,
+    /// How the patch was included in the message
+    pub source: PatchSource,
+    /// The patch itself
+    ///
+    /// For [`PatchSource::FormatPatch`] this is a complete mail, suitable for
+    /// `git am`; otherwise it is a plain diff, suitable for `patch` or `git apply`.
+    pub content: String,
+}
+
+const PATCH_EXTENSIONS: &[&str] = &[".patch", ".diff", ".debdiff"];
+
+const PATCH_MIME_TYPES: &[&str] = &["text/x-diff", "text/x-patch", "text/x-debdiff"];
+
+/// Returns the offset at which a diff starts in the given text, if it contains one
+///
+/// Only diffs with at least one unified diff hunk are recognized.
+fn find_diff(text: &str) -> Option<usize> {
+    let start = regex!(r"(?m)^(?:diff --git |diff -|Index: |--- \S.*\n\+\+\+ )")
+        .find(text)?
+        .start();
+    if regex_is_match!(r"(?m)^@@ -\d+(?:,\d+)? \+\d+(?:,\d+)? @@", &text[start..]) {
+        Some(start)
+    } else {
+        None
+    }
+}
+
+/// Returns whether the text is a mail generated by `git format-patch`
+fn is_format_patch(text: &str) -> bool {
+    regex_is_match!(r"^From [0-9a-f]{40} ", text)
+        || (regex_is_match!(r"(?m)^Subject: \[PATCH", text) && text.contains("\n---\n"))
+}
+
+/// Strips a trailing mail signature from an inline diff
+fn strip_signature(diff: &str) -> &str {
+    match diff.find("\n-- \n") {
+        Some(ix) => &diff[..ix + 1],
+        None => diff,
+    }
+}
+
+fn patch_from_attachment(attachment: &Attachment) -> Option<(PatchSource, String)> {
+    let text = attachment.text()?;
+    let named_patch = attachment
+        .filename
+        .as_deref()
+        .map(|f| PATCH_EXTENSIONS.iter().any(|ext| f.ends_with(ext)))
+        .unwrap_or(false);
+    let typed_patch = PATCH_MIME_TYPES.contains(&attachment.mime_type.as_str());
+    if is_format_patch(text) {
+        Some((PatchSource::FormatPatch, text.to_string()))
+    } else if named_patch || typed_patch || find_diff(text).is_some() {
+        Some((PatchSource::Attachment, text.to_string()))
+    } else {
+        None
+    }
+}
+
+/// Returns the patches found in a single bug log message
+pub fn patches_in_message(log: &BugLog) -> Vec<Patch> {
+    let headers = log.headers().ok();
+    let header = |name: &str| headers.as_ref().and_then(|h| h.get_first_value(name));
+    let author = header("From");
+    let date = header("Date");
+    let subject = header("Subject");
+
+    let (text, attachments) = match log.parsed() {
+        Ok(parsed) => (parsed.text.unwrap_or_default(), parsed.attachments),
+        Err(_) => (log.body.clone(), vec![]),
+    };
+
+    let patch = |filename: Option<String>, source, content| Patch {
+        msgnum: log.msgnum,
+        author: author.clone(),
+        date: date.clone(),
+        subject: subject.clone(),
+        filename,
+        source,
+        content,
+    };
+
+    let mut ret = Vec::new();
+    if let Some(start) = find_diff(&text) {
+        let subject_is_patch = subject
+            .as_deref()
+            .map(|s| regex_is_match!(r"^\[PATCH", s))
+            .unwrap_or(false);
+        if subject_is_patch {
+            let mut content = String::new();
+            for name in ["From", "Date", "Subject"] {
+                if let Some(value) = log.raw_header(name) {
+                    content.push_str(&format!("{}: {}\n", name, value));
+                }
+            }
+            content.push('\n');
+            content.push_str(strip_signature(&text));
+            ret.push(patch(None, PatchSource::FormatPatch, content));
+        } else {
+            ret.push(patch(
+                None,
+                PatchSource::Inline,
+                strip_signature(&text[start..]).to_string(),
+            ));
+        }
+    }
+    for attachment in attachments.iter() {
+        if let Some((source, content)) = patch_from_attachment(attachment) {
+            ret.push(patch(attachment.filename.clone(), source, content));
+        }
+    }
+    ret
+}
+
+/// Returns all patches found in a bug log, in message order
+///
+/// The last entry is the most recently submitted patch. Note that messages
+/// from `get_bug_log` have their attachments stripped by the server; use
+/// [`crate::Debbugs::get_bug_mbox`] to find attached patches as well.
+pub fn find_patches(logs: &[BugLog]) -> Vec<Patch> {
+    logs.iter().flat_map(patches_in_message).collect()
+}
+
+impl Patch {
+    /// Returns a file name for this patch
+    ///
+    /// The file name includes the message number, so patches from different
+    /// messages sort in the order they were submitted.
+    pub fn file_name(&self) -> String {
+        let base = match (&self.filename, &self.subject) {
+            (Some(filename), _) => filename.clone(),
+            (None, Some(subject)) => {
+                let slug = regex!(r"[^A-Za-z0-9]+")
+                    .replace_all(subject.trim_start_matches("Re: "), "-")
+                    .trim_matches('-')
+                    .to_lowercase();
+                format!("{}.patch", slug)
+            }
+            (None, None) => "patch.patch".to_string(),
+        };
+        format!(
+            "{:04}-{}",
+            self.msgnum,
+            base.replace(['/', '\\'], "_").trim_start_matches('.')
+        )
+    }
+
+    /// Writes the patch into the given directory, returning the path of the new file
+    pub fn write_to(&self, directory: &Path) -> Result<PathBuf, Error> {
+        let path = directory.join(self.file_name());
+        std::fs::write(&path, &self.content)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "diff --git a/foo.c b/foo.c\n\
+--- a/foo.c\n\
++++ b/foo.c\n\
+@@ -1,1 +1,1 @@\n\
+-int x;\n\
++int y;\n";
+
+    fn log(msgnum: BugId, header: &str, body: &str) -> BugLog {
+        BugLog {
+            header: header.to_string(),
+            msgnum,
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_find_diff() {
+        assert_eq!(find_diff(DIFF), Some(0));
+        assert_eq!(find_diff(&format!("Hi,\n\nfix:\n{}", DIFF)), Some(10));
+        assert_eq!(
+            find_diff("--- a/foo\n+++ b/foo\n@@ -1 +1 @@\n-a\n+b\n"),
+            Some(0)
+        );
+        assert_eq!(find_diff("--- Original message ---\nhello\n"), None);
+        assert_eq!(find_diff("diff --git a/x b/x\nBinary files differ\n"), None);
+    }
+
+    #[test]
+    fn test_inline_patch() {
+        let body = format!("Hi,\n\nthe fix:\n\n{}\n-- \nAlice\n", DIFF);
+        let patches = patches_in_message(&log(
+            5,
+            "From: Alice <alice@example.com>\nDate: Tue, 2 Jan 2024 10:00:00 +0000\nSubject: Re: foo: crashes",
+            &body,
+        ));
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].source, PatchSource::Inline);
+        assert_eq!(patches[0].content, format!("{}\n", DIFF));
+        assert_eq!(
+            patches[0].author.as_deref(),
+            Some("Alice <alice@example.com>")
+        );
+        assert_eq!(
+            patches[0].date.as_deref(),
+            Some("Tue, 2 Jan 2024 10:00:00 +0000")
+        );
+        assert_eq!(patches[0].msgnum, 5);
+        assert_eq!(patches[0].file_name(), "0005-foo-crashes.patch");
+    }
+
+    #[test]
+    fn test_format_patch_mail() {
+        let body = format!(
+            "Rename x to y.\n---\n foo.c | 2 +-\n\n{}-- \n2.43.0\n",
+            DIFF
+        );
+        let patches = patches_in_message(&log(
+            10,
+            "From: Bob <bob@example.com>\nDate: Wed, 3 Jan 2024 10:00:00 +0000\nSubject: [PATCH] Rename x",
+            &body,
+        ));
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].source, PatchSource::FormatPatch);
+        assert!(patches[0]
+            .content
+            .starts_with("From: Bob <bob@example.com>\nDate: Wed, 3 Jan 2024 10:00:00 +0000\nSubject: [PATCH] Rename x\n\nRename x to y.\n"));
+        assert!(patches[0].content.ends_with("+int y;\n"));
+    }
+
+    #[test]
+    fn test_attached_patches() {
+        let header = "From: Carol <carol@example.com>\nSubject: foo: patch\nContent-Type: multipart/mixed; boundary=\"b\"";
+        let body = "--b\n\
+Content-Type: text/plain\n\
+\n\
+Patches attached.\n\
+--b\n\
+Content-Type: text/plain\n\
+Content-Disposition: attachment; filename=\"fix.debdiff\"\n\
+\n\
+not really a diff\n\
+--b\n\
+Content-Type: text/plain\n\
+Content-Disposition: attachment; filename=\"0001-Rename.patch\"\n\
+\n\
+From 0123456789abcdef0123456789abcdef01234567 Mon Sep 17 00:00:00 2001\n\
+From: Carol <carol@example.com>\n\
+Subject: [PATCH] Rename\n\
+\n\
+---\n\
+--b\n\
+Content-Type: text/plain\n\
+Content-Disposition: attachment; filename=\"notes.txt\"\n\
+\n\
+Just notes.\n\
+--b--\n";
+        let patches = patches_in_message(&log(15, header, body));
+        assert_eq!(patches.len(), 2);
+        assert_eq!(patches[0].source, PatchSource::Attachment);
+        assert_eq!(patches[0].filename.as_deref(), Some("fix.debdiff"));
+        assert_eq!(patches[0].file_name(), "0015-fix.debdiff");
+        assert_eq!(patches[1].source, PatchSource::FormatPatch);
+        assert_eq!(patches[1].filename.as_deref(), Some("0001-Rename.patch"));
+    }
+
+    #[test]
+    fn test_find_patches() {
+        let logs = vec![
+            log(
+                5,
+                "From: a@example.com\nSubject: foo: bug",
+                "No patch here.\n",
+            ),
+            log(10, "From: b@example.com\nSubject: Re: foo: bug", DIFF),
+        ];
+        let patches = find_patches(&logs);
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].msgnum, 10);
+    }
+
+    #[test]
+    fn test_write_to() {
+        let td = std::env::temp_dir().join(format!("debbugs-patches-{}", std::process::id()));
+        std::fs::create_dir_all(&td).unwrap();
+        let patch = Patch {
+            msgnum: 42,
+            author: None,
+            date: None,
+            subject: None,
+            filename: Some("../evil.patch".to_string()),
+            source: PatchSource::Attachment,
+            content: DIFF.to_string(),
+        };
+        let path = patch.write_to(&td).unwrap();
+        assert_eq!(path, td.join("0042-_evil.patch"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), DIFF);
+        std::fs::remove_dir_all(&td).unwrap();
+    }
+}
@@ -0,0 +1,276 @@
+//! Release-critical bugs
+//!
+//! A bug is release-critical (RC) if its severity is serious or higher and it
+//! is still open. When suites are given, it additionally has to be present in
+//! at least one of them without being ignored for that release through a
+//! `<codename>-ignore` tag, see [`crate::suites`].
+//!
+//! The clients look up the bugs of source packages or maintainers with
+//! `rc_bugs_for_packages` and `rc_bugs_for_maintainer`; [`rc_status`] and
+//! [`rc_bugs`] do the evaluation for data that has already been retrieved.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use debbugs::blocking::Debbugs;
+//! use debbugs::suites::Suite;
+//!
+//! let client = Debbugs::default();
+//! let testing = [Suite::new("testing", "forky")];
+//! for bug in client.rc_bugs_for_packages(&["foo", "bar"], &testing).unwrap() {
+//!     println!("#{} in {}: {}", bug.bug, bug.package, bug.reasons.iter()
+//!         .map(|r| r.to_string()).collect::<Vec<_>>().join(", "));
+//! }
+//! ```
+
+use crate::suites::{applicability, SuiteStatus, SuiteVersions};
+use crate::versions::VersionHistory;
+use crate::{BugId, BugReport, Pending, Severity};
+use debversion::Version;
+use std::collections::HashMap;
+
+/// Why a bug counts as release-critical
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum RcReason {
+    /// The severity of the bug is release-critical
+    Severity(Severity),
+    /// The bug is present in versions of the package in a suite
+    AffectsSuite {
+        /// The name of the suite
+        suite: String,
+        /// The affected versions
+        versions: Vec<Version>,
+    },
+}
+
+impl std::fmt::Display for RcReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RcReason::Severity(severity) => write!(f, "severity {}", severity),
+            RcReason::AffectsSuite { suite, versions } => write!(
+                f,
+                "affects {} ({})",
+                suite,
+                versions
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// A release-critical bug
+#[derive(Debug)]
+pub struct RcBug {
+    /// The bug number
+    pub bug: BugId,
+    /// The source package the bug was evaluated for
+    pub package: String,
+    /// Why the bug counts as release-critical
+    pub reasons: Vec<RcReason>,
+    /// The suites that would be affected, but are ignored through `-ignore` tags
+    pub ignored_in: Vec<String>,
+    /// The status of the bug
+    pub report: BugReport,
+}
+
+/// Returns the source package a bug report is about
+///
+/// This is the source package the BTS recorded, or the package itself if it
+/// is a source package (`src:foo`).
+pub fn source_package(report: &BugReport) -> Option<String> {
+    report
+        .source
+        .as_deref()
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .or_else(|| {
+            report
+                .package
+                .as_deref()
+                .map(|p| p.strip_prefix("src:").unwrap_or(p).to_string())
+        })
+}
+
+/// Returns whether a bug has a release-critical severity and is still open
+///
+/// Bugs that are tagged pending still count, since the fix has not been
+/// uploaded yet.
+pub fn is_rc_candidate(report: &BugReport) -> bool {
+    let severity = report
+        .severity
+        .as_deref()
+        .and_then(|s| s.to_lowercase().parse::<Severity>().ok());
+    let done = report.done.as_deref().is_some_and(|d| !d.is_empty());
+    severity.is_some_and(|s| s.is_release_critical())
+        && !done
+        && report.pending != Some(Pending::Done)
+        && report.archived != Some(true)
+}
+
+/// Returns why a bug is release-critical, or `None` if it is not
+///
+/// `suites` are the versions of `package` in the suites of interest; if it is
+/// empty, versions are not taken into account.
+pub fn rc_status(
+    bug: BugId,
+    report: &BugReport,
+    package: &str,
+    suites: &[SuiteVersions],
+    history: Option<&dyn VersionHistory>,
+) -> Option<RcBug> {
+    if !is_rc_candidate(report) {
+        return None;
+    }
+    let severity: Severity = report.severity.as_deref()?.to_lowercase().parse().ok()?;
+
+    let mut reasons = vec![RcReason::Severity(severity)];
+    let mut ignored_in = vec![];
+    if !suites.is_empty() {
+        for entry in applicability(report, package, suites, history) {
+            match entry.status {
+                SuiteStatus::Affected => reasons.push(RcReason::AffectsSuite {
+                    suite: entry.suite.name,
+                    versions: entry.affected_versions,
+                }),
+                SuiteStatus::Ignored => ignored_in.push(entry.suite.name),
+                _ => {}
+            }
+        }
+        if reasons.len() == 1 {
+            return None;
+        }
+    }
+
+    Some(RcBug {
+        bug,
+        package: package.to_string(),
+        reasons,
+        ignored_in,
+        report: report.clone(),
+    })
+}
+
+/// Returns the release-critical bugs among `reports`, ordered by bug number
+///
+/// `suites` maps source packages to their versions in the suites of interest.
+/// Bugs in packages that are missing from it are evaluated without taking
+/// versions into account, unless `require_versions` is set, in which case
+/// they are skipped.
+pub fn rc_bugs(
+    reports: &HashMap<BugId, BugReport>,
+    suites: &HashMap<String, Vec<SuiteVersions>>,
+    require_versions: bool,
+    history: Option<&dyn VersionHistory>,
+) -> Vec<RcBug> {
+    let mut ret: Vec<RcBug> = reports
+        .iter()
+        .filter_map(|(bug, report)| {
+            let package = source_package(report)?;
+            let versions = match suites.get(&package) {
+                Some(versions) => versions.as_slice(),
+                None if require_versions => return None,
+                None => &[],
+            };
+            rc_status(*bug, report, &package, versions, history)
+        })
+        .collect();
+    ret.sort_by_key(|bug| bug.bug);
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::suites::Suite;
+
+    fn testing(versions: &[&str]) -> Vec<SuiteVersions> {
+        vec![SuiteVersions {
+            suite: Suite::new("testing", "forky"),
+            versions: versions.iter().map(|v| v.parse().unwrap()).collect(),
+        }]
+    }
+
+    #[test]
+    fn test_rc_status_severity() {
+        let serious = BugReport::from_fields("<severity>serious</severity><source>foo</source>");
+        let bug = rc_status(1, &serious, "foo", &[], None).unwrap();
+        assert_eq!(bug.reasons, vec![RcReason::Severity(Severity::Serious)]);
+        assert_eq!(bug.reasons[0].to_string(), "severity serious");
+
+        let important = BugReport::from_fields("<severity>important</severity>");
+        assert!(rc_status(1, &important, "foo", &[], None).is_none());
+        let done =
+            BugReport::from_fields("<severity>grave</severity><done>jane@example.com</done>");
+        assert!(rc_status(1, &done, "foo", &[], None).is_none());
+        let pending_done =
+            BugReport::from_fields("<severity>grave</severity><pending>done</pending>");
+        assert!(rc_status(1, &pending_done, "foo", &[], None).is_none());
+        let pending_fixed =
+            BugReport::from_fields("<severity>grave</severity><pending>pending-fixed</pending>");
+        assert!(rc_status(1, &pending_fixed, "foo", &[], None).is_some());
+    }
+
+    #[test]
+    fn test_rc_status_suites() {
+        let versioned = BugReport::from_fields(
+            "<severity>critical</severity>\
+             <found_versions><item>foo/1.0-1</item></found_versions>\
+             <fixed_versions><item>foo/1.2-1</item></fixed_versions>",
+        );
+        let bug = rc_status(1, &versioned, "foo", &testing(&["1.1-1"]), None).unwrap();
+        assert_eq!(
+            bug.reasons[1],
+            RcReason::AffectsSuite {
+                suite: "testing".to_string(),
+                versions: vec!["1.1-1".parse().unwrap()]
+            }
+        );
+        assert_eq!(bug.reasons[1].to_string(), "affects testing (1.1-1)");
+        // Fixed in testing
+        assert!(rc_status(1, &versioned, "foo", &testing(&["1.2-1"]), None).is_none());
+        // Not in testing
+        assert!(rc_status(1, &versioned, "foo", &testing(&[]), None).is_none());
+
+        let ignored =
+            BugReport::from_fields("<severity>serious</severity><tags>forky-ignore</tags>");
+        assert!(rc_status(1, &ignored, "foo", &testing(&["1.1-1"]), None).is_none());
+    }
+
+    #[test]
+    fn test_rc_bugs() {
+        let reports = HashMap::from([
+            (
+                3,
+                BugReport::from_fields("<severity>serious</severity><package>src:foo</package>"),
+            ),
+            (
+                1,
+                BugReport::from_fields("<severity>grave</severity><source>bar</source>"),
+            ),
+            (
+                2,
+                BugReport::from_fields("<severity>normal</severity><source>foo</source>"),
+            ),
+            (
+                4,
+                BugReport::from_fields("<severity>serious</severity><source>baz</source>"),
+            ),
+        ]);
+        let suites = HashMap::from([
+            ("foo".to_string(), testing(&["1.0-1"])),
+            ("bar".to_string(), testing(&["1.0-1"])),
+        ]);
+        let bugs = rc_bugs(&reports, &suites, false, None);
+        assert_eq!(
+            bugs.iter()
+                .map(|b| (b.bug, b.package.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(1, "bar"), (3, "foo"), (4, "baz")]
+        );
+        let bugs = rc_bugs(&reports, &suites, true, None);
+        assert_eq!(bugs.len(), 2);
+    }
+}
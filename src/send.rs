@@ -0,0 +1,385 @@
+//! Sending mail to the BTS
+//!
+//! [`MailSender`] abstracts over how messages built with [`crate::submit`] and
+//! [`crate::control`] are delivered. [`Sendmail`] hands them to a local
+//! sendmail-compatible MTA, `Smtp` (behind the `smtp` feature) talks to an SMTP
+//! server directly, and [`MemorySender`] and [`FileSender`] only record them,
+//! for tests and dry runs.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use debbugs::control::{Command, ControlMessage};
+//! use debbugs::mail::BtsAddresses;
+//! use debbugs::send::{MailSender, Sendmail};
+//!
+//! let addresses = BtsAddresses::default();
+//! let mail = ControlMessage::new()
+//!     .command(Command::NoOwner { bug: 1000 })
+//!     .mail("jane@example.com", &addresses.control(), "Releasing #1000")
+//!     .unwrap();
+//! Sendmail::default().send(&mail).unwrap();
+//! ```
+
+use crate::mail::Mail;
+use crate::Error;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Mutex;
+
+/// The default location of the sendmail binary
+pub const DEFAULT_SENDMAIL: &str = "/usr/sbin/sendmail";
+
+/// A way of delivering mail
+pub trait MailSender {
+    /// Sends a message to all of its recipients
+    fn send(&self, mail: &Mail) -> Result<(), Error>;
+}
+
+/// Sends mail through a local sendmail-compatible binary
+#[derive(Debug, Clone)]
+pub struct Sendmail {
+    program: PathBuf,
+    args: Vec<OsString>,
+}
+
+impl Default for Sendmail {
+    fn default() -> Self {
+        Sendmail::new(DEFAULT_SENDMAIL)
+    }
+}
+
+impl Sendmail {
+    /// Creates a sender that runs `program` (e.g. "/usr/sbin/sendmail" or "msmtp")
+    pub fn new<P: Into<PathBuf>>(program: P) -> Self {
+        Sendmail {
+            program: program.into(),
+            args: vec![],
+        }
+    }
+
+    /// Adds an extra argument to pass to the program, e.g. an account name
+    pub fn arg<S: Into<OsString>>(mut self, arg: S) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+}
+
+impl MailSender for Sendmail {
+    /// Pipes the message into the program
+    ///
+    /// The envelope sender is passed with `-f` and the recipients as
+    /// arguments, so the program does not have to parse the headers.
+    fn send(&self, mail: &Mail) -> Result<(), Error> {
+        let mut child = std::process::Command::new(&self.program)
+            .args(&self.args)
+            .arg("-i")
+            .arg("-f")
+            .arg(mail.sender())
+            .arg("--")
+            .args(mail.recipients())
+            .stdin(Stdio::piped())
+            .spawn()?;
+        let written = child
+            .stdin
+            .take()
+            .map(|mut stdin| stdin.write_all(mail.to_rfc5322().as_bytes()))
+            .unwrap_or(Ok(()));
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(Error::MailError(format!(
+                "{} exited with {}",
+                self.program.display(),
+                status
+            )));
+        }
+        written?;
+        Ok(())
+    }
+}
+
+/// How the connection to an SMTP server is secured
+#[cfg(feature = "smtp")]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum SmtpSecurity {
+    /// No encryption, on port 25 by default
+    None,
+    /// Upgrade the connection with STARTTLS, on port 587 by default
+    #[default]
+    StartTls,
+    /// Implicit TLS, on port 465 by default
+    Tls,
+}
+
+/// Sends mail to an SMTP server
+#[cfg(feature = "smtp")]
+#[derive(Clone)]
+pub struct Smtp {
+    host: String,
+    port: Option<u16>,
+    security: SmtpSecurity,
+    credentials: Option<(String, String)>,
+}
+
+#[cfg(feature = "smtp")]
+impl std::fmt::Debug for Smtp {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Smtp")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("security", &self.security)
+            .field(
+                "username",
+                &self.credentials.as_ref().map(|(username, _)| username),
+            )
+            .finish()
+    }
+}
+
+#[cfg(feature = "smtp")]
+fn smtp_error<E: std::fmt::Display>(err: E) -> Error {
+    Error::MailError(err.to_string())
+}
+
+#[cfg(feature = "smtp")]
+impl Smtp {
+    /// Creates a sender for the SMTP server at `host`, using STARTTLS
+    pub fn new(host: &str) -> Self {
+        Smtp {
+            host: host.to_string(),
+            port: None,
+            security: SmtpSecurity::default(),
+            credentials: None,
+        }
+    }
+
+    /// Sets the port to connect to, instead of the default for the security mode
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Sets how the connection is secured
+    pub fn security(mut self, security: SmtpSecurity) -> Self {
+        self.security = security;
+        self
+    }
+
+    /// Sets the username and password to authenticate with
+    pub fn credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    fn transport(&self) -> Result<lettre::SmtpTransport, Error> {
+        let mut builder = match self.security {
+            SmtpSecurity::None => lettre::SmtpTransport::builder_dangerous(&self.host),
+            SmtpSecurity::StartTls => {
+                lettre::SmtpTransport::starttls_relay(&self.host).map_err(smtp_error)?
+            }
+            SmtpSecurity::Tls => lettre::SmtpTransport::relay(&self.host).map_err(smtp_error)?,
+        };
+        if let Some(port) = self.port {
+            builder = builder.port(port);
+        }
+        if let Some((username, password)) = &self.credentials {
+            builder =
+                builder.credentials(lettre::transport::smtp::authentication::Credentials::new(
+                    username.clone(),
+                    password.clone(),
+                ));
+        }
+        Ok(builder.build())
+    }
+}
+
+#[cfg(feature = "smtp")]
+impl MailSender for Smtp {
+    fn send(&self, mail: &Mail) -> Result<(), Error> {
+        use lettre::Transport;
+
+        let recipients = mail
+            .recipients()
+            .iter()
+            .map(|r| r.parse())
+            .collect::<Result<Vec<lettre::Address>, _>>()
+            .map_err(smtp_error)?;
+        let sender = mail.sender().parse().map_err(smtp_error)?;
+        let envelope =
+            lettre::address::Envelope::new(Some(sender), recipients).map_err(smtp_error)?;
+        let message = mail.to_rfc5322().replace('\n', "\r\n");
+        self.transport()?
+            .send_raw(&envelope, message.as_bytes())
+            .map_err(smtp_error)?;
+        Ok(())
+    }
+}
+
+/// Collects sent messages in memory, for tests
+#[derive(Debug, Default)]
+pub struct MemorySender {
+    sent: Mutex<Vec<Mail>>,
+}
+
+impl MemorySender {
+    /// Creates an empty sender
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the messages sent so far
+    pub fn sent(&self) -> Vec<Mail> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+impl MailSender for MemorySender {
+    fn send(&self, mail: &Mail) -> Result<(), Error> {
+        self.sent.lock().unwrap().push(mail.clone());
+        Ok(())
+    }
+}
+
+/// Writes messages to files instead of sending them, for dry runs
+///
+/// Every message is written to `<message-id>.eml` in the directory, which is
+/// created if necessary.
+#[derive(Debug, Clone)]
+pub struct FileSender {
+    dir: PathBuf,
+}
+
+impl FileSender {
+    /// Creates a sender writing into `dir`
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        FileSender { dir: dir.into() }
+    }
+
+    /// Returns the path a message is written to
+    pub fn path(&self, mail: &Mail) -> PathBuf {
+        let name: String = mail
+            .message_id
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || "@.-_".contains(c) {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("{}.eml", name))
+    }
+}
+
+impl MailSender for FileSender {
+    fn send(&self, mail: &Mail) -> Result<(), Error> {
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.path(mail), mail.to_rfc5322())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mail() -> Mail {
+        let mut mail = Mail::new(
+            "Jane <jane@example.com>",
+            "control@bugs.debian.org",
+            "Releasing #1000",
+        );
+        mail.cc.push("1000@bugs.debian.org".to_string());
+        mail.message_id = "1234/5@example.com".to_string();
+        mail.body = "noowner 1000\nthanks\n".to_string();
+        mail
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("debbugs-send-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_memory_sender() {
+        let sender = MemorySender::new();
+        sender.send(&mail()).unwrap();
+        assert_eq!(sender.sent(), vec![mail()]);
+    }
+
+    #[test]
+    fn test_file_sender() {
+        let td = temp_dir("file");
+        let sender = FileSender::new(&td);
+        let mail = mail();
+        sender.send(&mail).unwrap();
+        let path = sender.path(&mail);
+        assert_eq!(path, td.join("1234_5@example.com.eml"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), mail.to_rfc5322());
+        std::fs::remove_dir_all(&td).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sendmail() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let td = temp_dir("sendmail");
+        std::fs::create_dir_all(&td).unwrap();
+        let script = td.join("sendmail");
+        std::fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\necho \"$@\" > {0}/args\ncat > {0}/message\n",
+                td.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mail = mail();
+        Sendmail::new(&script).arg("-t").send(&mail).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(td.join("args")).unwrap(),
+            "-t -i -f jane@example.com -- control@bugs.debian.org 1000@bugs.debian.org\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(td.join("message")).unwrap(),
+            mail.to_rfc5322()
+        );
+        std::fs::remove_dir_all(&td).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sendmail_failure() {
+        assert!(matches!(
+            Sendmail::new("false").send(&mail()),
+            Err(Error::MailError(_))
+        ));
+        assert!(matches!(
+            Sendmail::new("/nonexistent/sendmail").send(&mail()),
+            Err(Error::IoError(_))
+        ));
+    }
+
+    #[cfg(feature = "smtp")]
+    #[test]
+    fn test_smtp_debug_hides_password() {
+        let smtp = Smtp::new("smtp.example.com")
+            .port(2525)
+            .credentials("jane", "secret");
+        let debug = format!("{:?}", smtp);
+        assert!(debug.contains("jane"));
+        assert!(!debug.contains("secret"));
+    }
+
+    #[cfg(feature = "smtp")]
+    #[test]
+    fn test_smtp_connection_refused() {
+        let smtp = Smtp::new("127.0.0.1").port(1).security(SmtpSecurity::None);
+        assert!(matches!(smtp.send(&mail()), Err(Error::MailError(_))));
+    }
+}
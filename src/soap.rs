@@ -1,8 +1,8 @@
-use debversion::Version;
-use lazy_regex::regex_is_match;
 use maplit::hashmap;
 
+use crate::versions::{parse_qualified_version, QualifiedVersion};
 use crate::BugId;
+use debversion::Version;
 
 use std::collections::HashMap;
 use xmltree::{Element, XMLNode};
@@ -10,10 +10,10 @@ use xmltree::{Element, XMLNode};
 #[allow(dead_code)]
 pub const XMLNS_SOAP: &str = "http://xml.apache.org/xml-soap";
 pub const XMLNS_SOAPENV: &str = "http://schemas.xmlsoap.org/soap/envelope/";
+pub const XMLNS_SOAPENV12: &str = "http://www.w3.org/2003/05/soap-envelope";
 pub const XMLNS_SOAPENC: &str = "http://schemas.xmlsoap.org/soap/encoding/";
 pub const XMLNS_XSI: &str = "http://www.w3.org/1999/XMLSchema-instance";
 pub const XMLNS_XSD: &str = "http://www.w3.org/1999/XMLSchema";
-pub const XMLNS_DEBBUGS: &str = "Debbugs/SOAP";
 
 #[derive(Debug, PartialEq)]
 pub struct Fault {
@@ -33,6 +33,14 @@ impl std::fmt::Display for Fault {
     }
 }
 
+/// Returns whether an element is in the SOAP 1.1 or SOAP 1.2 envelope namespace
+fn is_soap_envelope(elem: &Element) -> bool {
+    matches!(
+        elem.namespace.as_deref(),
+        Some(XMLNS_SOAPENV) | Some(XMLNS_SOAPENV12)
+    )
+}
+
 fn parse_bool(s: &str) -> Result<bool, String> {
     match s {
         "1" => Ok(true),
@@ -45,13 +53,13 @@ pub(crate) fn parse_fault(input: &str) -> Result<Fault, String> {
     // Parse the input XML string into an Element
     let root = Element::parse(input.as_bytes()).map_err(|e| e.to_string())?;
 
-    if root.name != "Envelope" || root.namespace.as_deref() != Some(XMLNS_SOAPENV) {
+    if root.name != "Envelope" || !is_soap_envelope(&root) {
         return Err("Root element is not a valid soap:Envelope".into());
     }
 
     let body_elem = root.get_child("Body").ok_or("soap:Body not found")?;
 
-    if body_elem.namespace.as_deref() != Some(XMLNS_SOAPENV) {
+    if !is_soap_envelope(body_elem) {
         return Err(format!(
             "Namespace for soap:Body is incorrect: {:?}",
             body_elem.namespace
@@ -183,13 +191,13 @@ fn parse_response_envelope(input: &str, name: &str) -> Result<xmltree::Element,
     // Parse the input XML string into an Element
     let root = Element::parse(input.as_bytes()).map_err(|e| e.to_string())?;
 
-    if root.name != "Envelope" || root.namespace.as_deref() != Some(XMLNS_SOAPENV) {
+    if root.name != "Envelope" || !is_soap_envelope(&root) {
         return Err("Root element is not a valid soap:Envelope".into());
     }
 
     let body_elem = root.get_child("Body").ok_or("soap:Body not found")?;
 
-    if body_elem.namespace.as_deref() != Some(XMLNS_SOAPENV) {
+    if !is_soap_envelope(body_elem) {
         return Err(format!(
             "Namespace for soap:Body is incorrect: {:?}",
             body_elem.namespace
@@ -204,47 +212,48 @@ fn parse_response_envelope(input: &str, name: &str) -> Result<xmltree::Element,
         .cloned()
 }
 
-pub(crate) fn parse_newest_bugs_response(input: &str) -> Result<Vec<i32>, String> {
-    let response_elem = parse_response_envelope(input, "newest_bugs")?;
-
-    let array_elem = response_elem
-        .get_child("Array")
-        .ok_or("soapenc:Array not found")?;
-
-    if array_elem.namespace.as_deref() != Some(XMLNS_SOAPENC) {
-        return Err(format!(
-            "Namespace for soapenc:Array is incorrect: {:?}",
-            array_elem.namespace
-        ));
-    }
+/// Returns the element holding the return value of a SOAP response
+///
+/// SOAP::Lite names this element after an internal counter (e.g. `s-gensym3`)
+/// or after its type (e.g. `soapenc:Array`), and both differ between server
+/// versions, so the first child element is used regardless of its name.
+/// Returns `None` if the response has no return value.
+fn return_value(response: &Element) -> Option<&Element> {
+    response.children.iter().find_map(|c| c.as_element())
+}
 
-    match array_elem.attributes.get("arrayType") {
-        None => return Err("soapenc:Array does not have soapenc:arrayType attribute".to_string()),
-        Some(value) => {
-            if !regex_is_match!(r"xsd:int\[[0-9]+\]", value) && value != "xsd:anyType[0]" {
-                return Err(format!(
-                    "soapenc:Array has incorrect soapenc:arrayType attribute: {}",
-                    value
-                ));
-            }
-        }
-    }
+/// Returns the members of a SOAP-encoded array or struct
+///
+/// Accessor names of array members are not significant in SOAP encoding, so
+/// all child elements are returned.
+fn members(elem: &Element) -> impl Iterator<Item = &Element> {
+    elem.children.iter().filter_map(|c| c.as_element())
+}
 
-    // Extract the integers from the item elements
-    let mut integers = Vec::new();
-    for item in array_elem.children.iter() {
-        if let xmltree::XMLNode::Element(e) = item {
-            if e.name == "item" {
-                if let Some(text) = e.get_text() {
-                    if let Ok(num) = text.parse() {
-                        integers.push(num);
-                    }
-                }
-            }
-        }
+/// Parses a SOAP-encoded array of scalars, skipping malformed items
+///
+/// A single value that is not wrapped in an array is accepted as well.
+fn parse_array<T: std::str::FromStr>(value: Option<&Element>) -> Vec<T> {
+    let value = match value {
+        Some(value) => value,
+        None => return vec![],
+    };
+    if value.children.iter().any(|c| c.as_element().is_some()) {
+        members(value)
+            .filter_map(|item| item.get_text()?.trim().parse().ok())
+            .collect()
+    } else {
+        value
+            .get_text()
+            .and_then(|text| text.trim().parse().ok())
+            .into_iter()
+            .collect()
     }
+}
 
-    Ok(integers)
+pub(crate) fn parse_newest_bugs_response(input: &str) -> Result<Vec<i32>, String> {
+    let response_elem = parse_response_envelope(input, "newest_bugs")?;
+    Ok(parse_array(return_value(&response_elem)))
 }
 
 #[test]
@@ -458,6 +467,25 @@ fn test_parse_get_bug_log_response() {
     assert_eq!(bug_log[0].msgnum, 1);
 }
 
+#[test]
+fn test_buglog_raw_header() {
+    let log = BugLog {
+        header: "From: Alice <alice@example.com>\nSubject: a long\n  subject\nmessage-id: <1@example.com>".to_string(),
+        msgnum: 5,
+        body: "".to_string(),
+    };
+    assert_eq!(
+        log.raw_header("from").as_deref(),
+        Some("Alice <alice@example.com>")
+    );
+    assert_eq!(log.raw_header("Subject").as_deref(), Some("a long subject"));
+    assert_eq!(
+        log.raw_header("Message-ID").as_deref(),
+        Some("<1@example.com>")
+    );
+    assert_eq!(log.raw_header("To"), None);
+}
+
 #[test]
 fn test_parse_get_bugs_response() {
     let xml = r###"<?xml version="1.0" encoding="UTF-8"?>
@@ -532,6 +560,182 @@ fn test_parse_get_usertag_response() {
     assert_eq!(usertags["tag2"], vec![789]);
 }
 
+#[test]
+fn test_parse_get_status_response_apache_map() {
+    // Synthetic, in the shape bugs.debian.org uses: the map is an apachens:Map
+    let xml = r###"<?xml version="1.0" encoding="UTF-8"?><soap:Envelope soap:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/" xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/" xmlns:soapenc="http://schemas.xmlsoap.org/soap/encoding/" xmlns:xsd="http://www.w3.org/2001/XMLSchema" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"><soap:Body><get_statusResponse xmlns="Debbugs/SOAP"><s-gensym3 xsi:type="apachens:Map" xmlns:apachens="http://xml.apache.org/xml-soap"><item><key xsi:type="xsd:int">1000</key><value><package xsi:type="xsd:string">foo</package><bug_num xsi:type="xsd:int">1000</bug_num><found_versions soapenc:arrayType="xsd:string[2]" xsi:type="soapenc:Array"><item xsi:type="xsd:string">1.0-1</item><item xsi:type="xsd:string">foo/1.0-2</item></found_versions><date xsi:type="xsd:int">1700000000</date><acked_by soapenc:arrayType="xsd:string[2]" xsi:type="soapenc:Array"><item xsi:type="xsd:string">a</item><item xsi:type="xsd:string">b</item></acked_by></value></item></s-gensym3></get_statusResponse></soap:Body></soap:Envelope>"###;
+
+    let statuses = parse_get_status_response(xml).unwrap();
+    let report = &statuses[&1000];
+    assert_eq!(report.package.as_deref(), Some("foo"));
+    assert_eq!(
+        report.found_versions,
+        Some(vec![
+            (None, Some("1.0-1".parse().unwrap())),
+            (Some("foo".to_string()), Some("1.0-2".parse().unwrap()))
+        ])
+    );
+    assert_eq!(report.extra["date"], "1700000000");
+    assert_eq!(report.extra["acked_by"], "a b");
+    assert!(!report.extra.contains_key("package"));
+}
+
+#[test]
+fn test_parse_get_status_response_old_soap_lite() {
+    // Synthetic: older SOAP::Lite releases use a namespace prefix, a different
+    // gensym counter and name the map members after the encoding
+    let xml = r###"<?xml version="1.0" encoding="UTF-8"?>
+<SOAP-ENV:Envelope xmlns:SOAP-ENV="http://schemas.xmlsoap.org/soap/envelope/" xmlns:SOAP-ENC="http://schemas.xmlsoap.org/soap/encoding/">
+  <SOAP-ENV:Body>
+    <namesp1:get_statusResponse xmlns:namesp1="urn:Debbugs/SOAP">
+      <s-gensym9>
+        <SOAP-ENC:item>
+          <key> 2000 </key>
+          <value>
+            <severity>serious</severity>
+            <mergedwith>2001 2002</mergedwith>
+          </value>
+        </SOAP-ENC:item>
+      </s-gensym9>
+    </namesp1:get_statusResponse>
+  </SOAP-ENV:Body>
+</SOAP-ENV:Envelope>"###;
+
+    let statuses = parse_get_status_response(xml).unwrap();
+    let report = &statuses[&2000];
+    assert_eq!(report.severity.as_deref(), Some("serious"));
+    assert_eq!(report.mergedwith, Some(vec![2001, 2002]));
+    assert!(report.extra.is_empty());
+}
+
+#[test]
+fn test_parse_get_status_response_array() {
+    // Reports returned as an array carry their bug number themselves
+    let xml = r###"<?xml version="1.0" encoding="UTF-8"?>
+<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope">
+  <soap:Body>
+    <get_statusResponse xmlns="Debbugs/SOAP">
+      <soapenc:Array xmlns:soapenc="http://schemas.xmlsoap.org/soap/encoding/">
+        <item><bug_num>3000</bug_num><package>emacs</package></item>
+        <item><bug_num>3001</bug_num><package>guix</package></item>
+      </soapenc:Array>
+    </get_statusResponse>
+  </soap:Body>
+</soap:Envelope>"###;
+
+    let statuses = parse_get_status_response(xml).unwrap();
+    assert_eq!(statuses.len(), 2);
+    assert_eq!(statuses[&3001].package.as_deref(), Some("guix"));
+}
+
+#[test]
+fn test_parse_empty_responses() {
+    let empty = |name: &str, value: &str| {
+        format!(
+            r###"<?xml version="1.0" encoding="UTF-8"?>
+<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/">
+  <soap:Body>
+    <{0}Response xmlns="Debbugs/SOAP">{1}</{0}Response>
+  </soap:Body>
+</soap:Envelope>"###,
+            name, value
+        )
+    };
+
+    assert!(parse_get_status_response(&empty("get_status", ""))
+        .unwrap()
+        .is_empty());
+    assert!(parse_get_status_response(&empty(
+        "get_status",
+        r#"<s-gensym3 xsi:type="apachens:Map" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:apachens="http://xml.apache.org/xml-soap"/>"#
+    ))
+    .unwrap()
+    .is_empty());
+    assert!(
+        parse_get_usertag_response(&empty("get_usertag", "<s-gensym3/>"))
+            .unwrap()
+            .is_empty()
+    );
+    assert!(parse_get_bugs_response(&empty(
+        "get_bugs",
+        r#"<soapenc:Array xmlns:soapenc="http://schemas.xmlsoap.org/soap/encoding/" soapenc:arrayType="xsd:anyType[0]"/>"#
+    ))
+    .unwrap()
+    .is_empty());
+    assert!(parse_get_bug_log_response(&empty("get_bug_log", ""))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_parse_array_variations() {
+    // A different array type, namespace and item name, and a bare scalar
+    let xml = r###"<?xml version="1.0" encoding="UTF-8"?>
+<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/">
+  <soap:Body>
+    <newest_bugsResponse xmlns="Debbugs/SOAP">
+      <Array xmlns="http://www.w3.org/2003/05/soap-encoding">
+        <int>5</int>
+        <int>six</int>
+        <int> 7 </int>
+      </Array>
+    </newest_bugsResponse>
+  </soap:Body>
+</soap:Envelope>"###;
+    assert_eq!(parse_newest_bugs_response(xml).unwrap(), vec![5, 7]);
+
+    let xml = r###"<?xml version="1.0" encoding="UTF-8"?>
+<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/">
+  <soap:Body>
+    <get_bugsResponse xmlns="Debbugs/SOAP"><s-gensym3>42</s-gensym3></get_bugsResponse>
+  </soap:Body>
+</soap:Envelope>"###;
+    assert_eq!(parse_get_bugs_response(xml).unwrap(), vec![42]);
+}
+
+#[test]
+fn test_parse_get_usertag_response_key_value() {
+    let xml = r###"<?xml version="1.0" encoding="UTF-8"?>
+<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/">
+  <soap:Body>
+    <get_usertagResponse xmlns="Debbugs/SOAP">
+      <s-gensym5>
+        <item><key>ftbfs+gcc</key><value><item>1</item><item>2</item></value></item>
+        <item><key>single</key><value>3</value></item>
+      </s-gensym5>
+    </get_usertagResponse>
+  </soap:Body>
+</soap:Envelope>"###;
+
+    let usertags = parse_get_usertag_response(xml).unwrap();
+    assert_eq!(usertags["ftbfs+gcc"], vec![1, 2]);
+    assert_eq!(usertags["single"], vec![3]);
+}
+
+#[test]
+fn test_parse_get_bug_log_response_unknown_fields() {
+    let xml = r###"<?xml version="1.0" encoding="UTF-8"?>
+<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/">
+  <soap:Body>
+    <get_bug_logResponse xmlns="Debbugs/SOAP">
+      <s-gensym7>
+        <item>
+          <header>Subject: test</header>
+          <msgnum>5</msgnum>
+          <body>hello</body>
+          <spam_score>0</spam_score>
+        </item>
+      </s-gensym7>
+    </get_bug_logResponse>
+  </soap:Body>
+</soap:Envelope>"###;
+
+    let logs = parse_get_bug_log_response(xml).unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].msgnum, 5);
+    assert_eq!(logs[0].body, "hello");
+}
+
 #[test]
 fn test_parse_response_envelope_invalid() {
     let invalid_xml = r###"<?xml version="1.0" encoding="UTF-8"?>
@@ -619,6 +823,10 @@ fn test_bug_report_from_xml_comprehensive() {
     assert_eq!(bug_report.package, Some("test-package".to_string()));
     assert_eq!(bug_report.last_modified, Some(1234567890));
     assert_eq!(bug_report.tags, Some("patch,security".to_string())); // Tags are stored as a comma-separated string
+    assert_eq!(
+        bug_report.tag_list(),
+        vec![crate::Tag::Patch, crate::Tag::Security]
+    );
     assert_eq!(bug_report.pending, Some(crate::Pending::Pending));
     assert_eq!(bug_report.done, Some("fixed in version 1.2".to_string())); // done field content
     assert_eq!(bug_report.archived, Some(false));
@@ -691,7 +899,7 @@ fn test_bug_report_from_xml_invalid_values() {
     assert!(bug_report.done.is_none()); // Not provided in test XML
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 /// Detailed information about a bug report
 ///
 /// Contains comprehensive metadata about a bug including its status, severity,
@@ -719,7 +927,7 @@ pub struct BugReport {
     /// Whether the bug has been archived (old/resolved bugs)
     pub archived: Option<bool>,
     /// Versions of the package where this bug was found to exist
-    pub found_versions: Option<Vec<Version>>,
+    pub found_versions: Option<Vec<QualifiedVersion>>,
     /// Email address of the person who marked this bug as done/resolved
     pub done: Option<String>,
     /// Severity level of the bug (e.g., "serious", "important", "normal", "minor", "wishlist")
@@ -727,7 +935,7 @@ pub struct BugReport {
     /// Name of the package this bug affects
     pub package: Option<String>,
     /// Versions of the package where this bug has been fixed
-    pub fixed_versions: Option<Vec<(Option<String>, Option<Version>)>>,
+    pub fixed_versions: Option<Vec<QualifiedVersion>>,
     /// Email address of the person who originally reported this bug
     pub originator: Option<String>,
     /// Comma-separated list of bug IDs that this bug blocks
@@ -763,6 +971,65 @@ pub struct BugReport {
     pub log_modified: Option<u32>,
     /// Source package name (for binary packages built from source)
     pub source: Option<String>,
+    /// Fields returned by the server that are not known to this crate
+    ///
+    /// Lists are joined with spaces.
+    pub extra: HashMap<String, String>,
+}
+
+/// The fields of a bug report that are parsed into [`BugReport`]
+const KNOWN_BUG_REPORT_FIELDS: &[&str] = &[
+    "pending",
+    "msgid",
+    "owner",
+    "keywords",
+    "affects",
+    "unarchived",
+    "forwarded",
+    "summary",
+    "bug_num",
+    "archived",
+    "found_versions",
+    "done",
+    "severity",
+    "package",
+    "fixed_versions",
+    "originator",
+    "blocks",
+    "found_date",
+    "outlook",
+    "id",
+    "found",
+    "fixed",
+    "last_modified",
+    "tags",
+    "subject",
+    "location",
+    "mergedwith",
+    "blockedby",
+    "fixed_date",
+    "log_modified",
+    "source",
+];
+
+impl BugReport {
+    /// Parses a bug report from the fields of a `get_status` item, e.g.
+    /// `<severity>serious</severity><source>foo</source>`
+    #[cfg(test)]
+    pub(crate) fn from_fields(xml: &str) -> Self {
+        BugReport::from(&Element::parse(format!("<value>{}</value>", xml).as_bytes()).unwrap())
+    }
+
+    /// Returns the tags of the bug, skipping ones that are not known
+    pub fn tag_list(&self) -> Vec<crate::Tag> {
+        self.tags
+            .as_deref()
+            .unwrap_or_default()
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|t| !t.is_empty())
+            .filter_map(|t| t.parse().ok())
+            .collect()
+    }
 }
 
 impl std::fmt::Display for BugReport {
@@ -782,13 +1049,6 @@ impl std::fmt::Display for BugReport {
     }
 }
 
-fn parse_version(input: &str) -> (Option<String>, Option<Version>) {
-    match input.split_once('/') {
-        None => (None, input.parse().ok()),
-        Some((package, version)) => (Some(package.to_string()), version.parse().ok()),
-    }
-}
-
 // Hide the deprecated warnings, since we intentionally still populate the deprecated fields
 #[allow(deprecated)]
 impl From<&xmltree::Element> for BugReport {
@@ -805,7 +1065,22 @@ impl From<&xmltree::Element> for BugReport {
         ) -> Option<T> {
             item.get_child(child_name)
                 .and_then(|e| e.get_text())
-                .and_then(|text| text.parse().ok())
+                .and_then(|text| text.trim().parse().ok())
+        }
+
+        // Helper function to render a field as text, joining lists with spaces
+        fn flatten_text(element: &xmltree::Element) -> String {
+            if element.children.iter().any(|c| c.as_element().is_some()) {
+                members(element)
+                    .map(flatten_text)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            } else {
+                element
+                    .get_text()
+                    .map(|s| s.into_owned())
+                    .unwrap_or_default()
+            }
         }
 
         // Helper function to parse items from a list element
@@ -814,10 +1089,7 @@ impl From<&xmltree::Element> for BugReport {
             F: Fn(&str) -> Option<T>,
         {
             element.map(|e| {
-                e.children
-                    .iter()
-                    .filter_map(|c| c.as_element())
-                    .filter(|c| c.name == "item")
+                members(e)
                     .filter_map(|c| c.get_text().and_then(|text| parser(text.as_ref())))
                     .collect()
             })
@@ -836,7 +1108,7 @@ impl From<&xmltree::Element> for BugReport {
             blocks: get_text_owned(item.get_child("blocks")),
             found_date: parse_list_items(item.get_child("found_date"), |s| s.parse().ok()),
             fixed_versions: parse_list_items(item.get_child("fixed_versions"), |s| {
-                Some(parse_version(s))
+                Some(parse_qualified_version(s))
             }),
             outlook: get_text_owned(item.get_child("outlook")),
             done: get_text_owned(item.get_child("done")),
@@ -849,7 +1121,7 @@ impl From<&xmltree::Element> for BugReport {
                 .and_then(|e| e.get_text())
                 .and_then(|t| parse_bool(t.as_ref()).ok()),
             found_versions: parse_list_items(item.get_child("found_versions"), |s| {
-                s.parse::<Version>().ok()
+                Some(parse_qualified_version(s))
             }),
             found: item.get_child("found").is_some(),
             fixed: item.get_child("fixed").is_some(),
@@ -872,6 +1144,10 @@ impl From<&xmltree::Element> for BugReport {
             severity: get_text_owned(item.get_child("severity")),
             blockedby: get_text_owned(item.get_child("blockedby")),
             fixed_date: parse_list_items(item.get_child("fixed_date"), |s| s.parse().ok()),
+            extra: members(item)
+                .filter(|e| !KNOWN_BUG_REPORT_FIELDS.contains(&e.name.as_str()))
+                .map(|e| (e.name.clone(), flatten_text(e)))
+                .collect(),
         }
     }
 }
@@ -896,6 +1172,33 @@ impl BugLog {
         let (headers, _ix_body) = mailparse::parse_headers(self.header.as_bytes())?;
         Ok(headers)
     }
+
+    /// Returns the raw value of the first header with the given name
+    ///
+    /// Folded header lines are unfolded, but encoded words are not decoded.
+    pub(crate) fn raw_header(&self, name: &str) -> Option<String> {
+        let mut value: Option<String> = None;
+        for line in self.header.lines() {
+            if line.starts_with([' ', '\t']) {
+                if let Some(value) = value.as_mut() {
+                    if !value.is_empty() {
+                        value.push(' ');
+                    }
+                    value.push_str(line.trim());
+                }
+                continue;
+            }
+            if value.is_some() {
+                break;
+            }
+            if let Some((key, v)) = line.split_once(':') {
+                if key.trim().eq_ignore_ascii_case(name) {
+                    value = Some(v.trim().to_string());
+                }
+            }
+        }
+        value
+    }
 }
 
 fn parse_buglog(item: &xmltree::Element) -> Result<BugLog, String> {
@@ -908,8 +1211,8 @@ fn parse_buglog(item: &xmltree::Element) -> Result<BugLog, String> {
                 "header" => {
                     header = e.get_text().map(|s| s.into_owned());
                 }
-                "msg_num" => {
-                    msgnum = e.get_text().and_then(|s| s.parse().ok());
+                "msg_num" | "msgnum" => {
+                    msgnum = e.get_text().and_then(|s| s.trim().parse().ok());
                 }
                 "body" => {
                     body = e.get_text().map(|s| s.into_owned());
@@ -920,7 +1223,7 @@ fn parse_buglog(item: &xmltree::Element) -> Result<BugLog, String> {
                     }
                 }
                 n => {
-                    return Err(format!("Unknown element: {}", n));
+                    log::debug!("Ignoring unknown element in bug log: {}", n);
                 }
             }
         }
@@ -935,45 +1238,12 @@ fn parse_buglog(item: &xmltree::Element) -> Result<BugLog, String> {
 pub(crate) fn parse_get_bug_log_response(input: &str) -> Result<Vec<BugLog>, String> {
     let response_elem = parse_response_envelope(input, "get_bug_log")?;
 
-    let array_elem = response_elem
-        .get_child("Array")
-        .ok_or("soapenc:Array not found")?;
-
-    if array_elem.namespace.as_deref() != Some(XMLNS_SOAPENC) {
-        return Err(format!(
-            "Namespace for soapenc:Array is incorrect: {:?}",
-            array_elem.namespace
-        ));
-    }
-
-    match array_elem.attributes.get("arrayType") {
-        None => return Err("soapenc:Array does not have soapenc:arrayType attribute".to_string()),
-        Some(value) => {
-            if !regex_is_match!(r"xsd:ur-type\[[0-9]+\]", value) && value != "xsd:anyType[0]" {
-                return Err(format!(
-                    "soapenc:Array has incorrect soapenc:arrayType attribute: {}",
-                    value
-                ));
-            }
-        }
-    }
-
-    if array_elem.attributes.get("type") != Some(&"soapenc:Array".to_string()) {
-        return Err(format!(
-            "soapenc:Array does not have xsi:type attribute: {:?}",
-            array_elem.attributes.get("type")
-        ));
+    match return_value(&response_elem) {
+        None => Ok(vec![]),
+        // A single message that is not wrapped in an array
+        Some(value) if value.get_child("header").is_some() => Ok(vec![parse_buglog(value)?]),
+        Some(value) => members(value).map(parse_buglog).collect(),
     }
-
-    let mut ret = vec![];
-    for item in array_elem.children.iter() {
-        if let xmltree::XMLNode::Element(e) = item {
-            if e.name == "item" {
-                ret.push(parse_buglog(e)?);
-            }
-        }
-    }
-    Ok(ret)
 }
 
 trait ToArgXml {
@@ -1179,45 +1449,7 @@ pub(crate) fn get_bugs_request(query: &SearchQuery) -> xmltree::Element {
 
 pub(crate) fn parse_get_bugs_response(input: &str) -> Result<Vec<crate::BugId>, String> {
     let response_elem = parse_response_envelope(input, "get_bugs")?;
-
-    let array_elem = response_elem
-        .get_child("Array")
-        .ok_or("soapenc:Array not found")?;
-
-    if array_elem.namespace.as_deref() != Some(XMLNS_SOAPENC) {
-        return Err(format!(
-            "Namespace for soapenc:Array is incorrect: {:?}",
-            array_elem.namespace
-        ));
-    }
-
-    match array_elem.attributes.get("arrayType") {
-        None => return Err("soapenc:Array does not have soapenc:arrayType attribute".to_string()),
-        Some(value) => {
-            if !regex_is_match!(r"xsd:int\[[0-9]+\]", value) && value != "xsd:anyType[0]" {
-                return Err(format!(
-                    "soapenc:Array has incorrect soapenc:arrayType attribute: {}",
-                    value
-                ));
-            }
-        }
-    }
-
-    // Extract the integers from the item elements
-    let mut integers = Vec::new();
-    for item in array_elem.children.iter() {
-        if let xmltree::XMLNode::Element(e) = item {
-            if e.name == "item" {
-                if let Some(text) = e.get_text() {
-                    if let Ok(num) = text.parse() {
-                        integers.push(num);
-                    }
-                }
-            }
-        }
-    }
-
-    Ok(integers)
+    Ok(parse_array(return_value(&response_elem)))
 }
 
 pub(crate) fn get_status_request(bug_ids: &[BugId]) -> xmltree::Element {
@@ -1229,39 +1461,37 @@ pub(crate) fn get_status_request(bug_ids: &[BugId]) -> xmltree::Element {
 pub(crate) fn parse_get_status_response(input: &str) -> Result<HashMap<BugId, BugReport>, String> {
     let response_elem = parse_response_envelope(input, "get_status")?;
 
-    if response_elem.namespace.as_deref() != Some(XMLNS_DEBBUGS) {
-        return Err(format!(
-            "Namespace for get_statusResponse is incorrect: {:?}",
-            response_elem.namespace
-        ));
-    }
-
-    let container = response_elem
-        .get_child("s-gensym3")
-        .ok_or("s-gensym3 not found")?;
-
     let mut ret = HashMap::new();
-    for item in container.children.iter() {
-        if let xmltree::XMLNode::Element(e) = item {
-            if e.name == "item" {
-                if e.namespace.as_deref() != Some(XMLNS_DEBBUGS) {
-                    return Err(format!(
-                        "Namespace for item is incorrect: {:?}",
-                        e.namespace
-                    ));
-                }
+    let container = match return_value(&response_elem) {
+        Some(container) => container,
+        None => return Ok(ret),
+    };
+
+    // A single bug report that is not wrapped in a map
+    let entries: Vec<&Element> = if container.get_child("bug_num").is_some() {
+        vec![container]
+    } else {
+        members(container).collect()
+    };
 
-                let key = e
-                    .get_child("key")
-                    .ok_or("key not found")?
+    for entry in entries {
+        match (entry.get_child("key"), entry.get_child("value")) {
+            (Some(key), Some(value)) => {
+                let key = key
                     .get_text()
                     .ok_or("key has no text")?
+                    .trim()
                     .parse::<BugId>()
                     .map_err(|_| "Invalid BugId format")?;
-
-                let value = BugReport::from(e.get_child("value").ok_or("value not found")?);
-
-                ret.insert(key, value);
+                ret.insert(key, BugReport::from(value));
+            }
+            _ => {
+                // Arrays of bug reports carry the bug number in the report itself
+                let report = BugReport::from(entry);
+                let key = report
+                    .bug_num
+                    .ok_or("bug report has neither a key nor a bug_num")?;
+                ret.insert(key, report);
             }
         }
     }
@@ -1283,29 +1513,99 @@ pub(crate) fn parse_get_usertag_response(
 ) -> Result<HashMap<String, Vec<crate::BugId>>, String> {
     let response_elem = parse_response_envelope(input, "get_usertag")?;
 
-    let container = response_elem
-        .get_child("s-gensym3")
-        .ok_or("s-gensym3 not found")?;
-
     let mut ret = HashMap::new();
+    let container = match return_value(&response_elem) {
+        Some(container) => container,
+        None => return Ok(ret),
+    };
 
-    for child in container.children.iter() {
-        if let Some(e) = child.as_element() {
-            let mut ids = vec![];
-            for item in e.children.iter() {
-                if let xmltree::XMLNode::Element(e) = item {
-                    if e.name == "item" {
-                        if let Some(text) = e.get_text() {
-                            if let Ok(id) = text.parse::<BugId>() {
-                                ids.push(id);
-                            }
-                        }
-                    }
-                }
+    for entry in members(container) {
+        // Maps are either encoded as structs named after the tags, or as key/value items
+        let (tag, value) = match (entry.get_child("key"), entry.get_child("value")) {
+            (Some(key), Some(value)) => {
+                (key.get_text().unwrap_or_default().trim().to_string(), value)
             }
-            ret.insert(e.name.clone(), ids);
-        }
+            _ => (entry.name.clone(), entry),
+        };
+        ret.insert(tag, parse_array(Some(value)));
     }
 
     Ok(ret)
 }
+
+pub(crate) fn get_versions_request(
+    package: &str,
+    dists: &[&str],
+    archs: &[&str],
+) -> xmltree::Element {
+    let mut params = Vec::new();
+    add_arg_xml(&mut params, "package");
+    add_arg_xml(&mut params, package);
+    if !dists.is_empty() {
+        add_arg_xml(&mut params, "dist");
+        add_arg_xml(&mut params, dists);
+    }
+    if !archs.is_empty() {
+        add_arg_xml(&mut params, "arch");
+        add_arg_xml(&mut params, archs);
+    }
+    build_request_envelope("get_versions", params)
+}
+
+pub(crate) fn parse_get_versions_response(input: &str) -> Result<Vec<Version>, String> {
+    let response_elem = parse_response_envelope(input, "get_versions")?;
+    let mut versions: Vec<Version> = parse_array(return_value(&response_elem));
+    versions.sort();
+    versions.dedup();
+    Ok(versions)
+}
+
+#[test]
+fn test_get_versions_request() {
+    let args = |request: xmltree::Element| {
+        let body = request.children[1].as_element().unwrap().clone();
+        let get_versions = body.children[0].as_element().unwrap().clone();
+        assert_eq!(get_versions.name, "get_versions");
+        get_versions
+            .children
+            .iter()
+            .filter_map(|c| c.as_element())
+            .map(|e| e.get_text().unwrap_or_default().into_owned())
+            .collect::<Vec<_>>()
+    };
+
+    // package, dist and arch keys with their values; the lists have no text
+    let request = get_versions_request("foo", &["unstable", "testing"], &["source"]);
+    assert_eq!(
+        args(request),
+        vec!["package", "foo", "dist", "", "arch", ""]
+    );
+
+    let request = get_versions_request("foo", &[], &[]);
+    assert_eq!(args(request), vec!["package", "foo"]);
+}
+
+#[test]
+fn test_parse_get_versions_response() {
+    let xml = r###"<?xml version="1.0" encoding="UTF-8"?>
+<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/" xmlns:soapenc="http://schemas.xmlsoap.org/soap/encoding/" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+  <soap:Body>
+    <get_versionsResponse xmlns="Debbugs/SOAP">
+      <soapenc:Array soapenc:arrayType="xsd:string[3]" xsi:type="soapenc:Array">
+        <item xsi:type="xsd:string">1.2-1</item>
+        <item xsi:type="xsd:string">1.0-1+deb12u1</item>
+        <item xsi:type="xsd:string">1.2-1</item>
+      </soapenc:Array>
+    </get_versionsResponse>
+  </soap:Body>
+</soap:Envelope>"###;
+
+    let versions = parse_get_versions_response(xml).unwrap();
+    assert_eq!(
+        versions,
+        vec![
+            "1.0-1+deb12u1".parse::<Version>().unwrap(),
+            "1.2-1".parse().unwrap()
+        ]
+    );
+}
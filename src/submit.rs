@@ -0,0 +1,351 @@
+//! Composition of new bug reports for submit@
+//!
+//! [`BugSubmission`] builds the message reportbug would send: the report body is
+//! preceded by pseudo-headers that tell the BTS which package the bug is in, its
+//! severity and tags, and any control commands to run once the bug is created.
+//!
+//! See <https://www.debian.org/Bugs/Reporting> for the pseudo-headers the BTS
+//! understands.
+//!
+//! # Examples
+//!
+//! ```
+//! use debbugs::submit::BugSubmission;
+//! use debbugs::{Severity, Tag};
+//!
+//! let mail = BugSubmission::new("CI <ci@example.com>", "src:foo", "foo: FTBFS with gcc-15")
+//!     .version("1.0-1".parse().unwrap())
+//!     .severity(Severity::Serious)
+//!     .tag(Tag::Ftbfs)
+//!     .body("The package fails to build from source.")
+//!     .attach("build.log", "text/plain", b"error: ...".to_vec())
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(mail.to, vec!["submit@bugs.debian.org"]);
+//! assert!(mail.body.starts_with("Source: foo\nVersion: 1.0-1\nSeverity: serious\nTags: ftbfs\n\n"));
+//! ```
+
+use crate::control::{validate_package, validate_text, validate_word, Command};
+use crate::mail::{bare_address, Attachment, BtsAddresses, Mail};
+use crate::{Error, Severity, Tag};
+use debversion::Version;
+
+/// A new bug report, to be mailed to submit@
+#[derive(Debug, Clone)]
+pub struct BugSubmission {
+    from: String,
+    to: String,
+    package: String,
+    subject: String,
+    version: Option<Version>,
+    severity: Option<Severity>,
+    tags: Vec<Tag>,
+    user: Option<String>,
+    usertags: Vec<String>,
+    control: Vec<Command>,
+    cc: Vec<String>,
+    body: String,
+    attachments: Vec<Attachment>,
+}
+
+fn validate_address(what: &str, address: &str) -> Result<(), Error> {
+    validate_text(what, address)?;
+    if !bare_address(address).contains('@') {
+        return Err(Error::ValidationError(format!(
+            "{} is not an email address: {:?}",
+            what, address
+        )));
+    }
+    Ok(())
+}
+
+impl BugSubmission {
+    /// Creates a bug report against a package, to be submitted to bugs.debian.org
+    ///
+    /// Source packages are prefixed with `src:`, e.g. "src:foo". Use
+    /// [`BugSubmission::addresses`] to submit to another debbugs instance.
+    pub fn new(from: &str, package: &str, subject: &str) -> Self {
+        BugSubmission {
+            from: from.to_string(),
+            to: BtsAddresses::default().submit(),
+            package: package.to_string(),
+            subject: subject.to_string(),
+            version: None,
+            severity: None,
+            tags: vec![],
+            user: None,
+            usertags: vec![],
+            control: vec![],
+            cc: vec![],
+            body: String::new(),
+            attachments: vec![],
+        }
+    }
+
+    /// Submits to the submit@ address of another debbugs instance
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use debbugs::instance::Instance;
+    /// use debbugs::submit::BugSubmission;
+    ///
+    /// let mail = BugSubmission::new("jane@example.com", "emacs", "crash on startup")
+    ///     .addresses(&Instance::gnu().addresses())
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(mail.to, vec!["submit@debbugs.gnu.org"]);
+    /// ```
+    pub fn addresses(mut self, addresses: &BtsAddresses) -> Self {
+        self.to = addresses.submit();
+        self
+    }
+
+    /// Sets the address to submit to, e.g. maintonly@ or quiet@
+    pub fn to(mut self, address: &str) -> Self {
+        self.to = address.to_string();
+        self
+    }
+
+    /// Sets the version the bug was found in
+    pub fn version(mut self, version: Version) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Sets the severity of the bug
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = Some(severity);
+        self
+    }
+
+    /// Adds a tag to the bug
+    pub fn tag(mut self, tag: Tag) -> Self {
+        if !self.tags.contains(&tag) {
+            self.tags.push(tag);
+        }
+        self
+    }
+
+    /// Sets usertags for the bug, on behalf of `user`
+    pub fn usertags<S: Into<String>>(
+        mut self,
+        user: &str,
+        tags: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.user = Some(user.to_string());
+        self.usertags = tags.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Adds a control command to run once the bug is created
+    ///
+    /// Use `-1` as the bug number to refer to the new bug.
+    pub fn control(mut self, command: Command) -> Self {
+        self.control.push(command);
+        self
+    }
+
+    /// Adds an address the report should be forwarded to (`X-Debbugs-Cc`)
+    pub fn cc(mut self, address: &str) -> Self {
+        self.cc.push(address.to_string());
+        self
+    }
+
+    /// Sets the text of the report
+    pub fn body(mut self, body: &str) -> Self {
+        self.body = body.to_string();
+        self
+    }
+
+    /// Attaches a file to the report
+    pub fn attach(mut self, filename: &str, mime_type: &str, data: Vec<u8>) -> Self {
+        self.attachments.push(Attachment {
+            filename: filename.to_string(),
+            mime_type: mime_type.to_string(),
+            data,
+        });
+        self
+    }
+
+    /// Checks that the report can be submitted
+    pub fn validate(&self) -> Result<(), Error> {
+        validate_address("Sender", &self.from)?;
+        validate_address("Submit address", &self.to)?;
+        validate_package(&self.package)?;
+        validate_text("Subject", &self.subject)?;
+        if let Some(user) = &self.user {
+            validate_word("User", user)?;
+            if self.usertags.is_empty() {
+                return Err(Error::ValidationError(
+                    "usertags requires at least one tag".to_string(),
+                ));
+            }
+            for tag in self.usertags.iter() {
+                validate_word("Usertag", tag)?;
+            }
+        }
+        for command in self.control.iter() {
+            command.validate()?;
+        }
+        for address in self.cc.iter() {
+            validate_address("X-Debbugs-Cc address", address)?;
+        }
+        for attachment in self.attachments.iter() {
+            validate_text("Attachment file name", &attachment.filename)?;
+            validate_word("Attachment MIME type", &attachment.mime_type)?;
+        }
+        Ok(())
+    }
+
+    /// Renders the pseudo-headers that start the body of the report
+    pub fn pseudo_headers(&self) -> String {
+        let mut headers = String::new();
+        match self.package.strip_prefix("src:") {
+            Some(source) => headers.push_str(&format!("Source: {}\n", source)),
+            None => headers.push_str(&format!("Package: {}\n", self.package)),
+        }
+        if let Some(version) = &self.version {
+            headers.push_str(&format!("Version: {}\n", version));
+        }
+        if let Some(severity) = &self.severity {
+            headers.push_str(&format!("Severity: {}\n", severity));
+        }
+        if !self.tags.is_empty() {
+            let tags = self
+                .tags
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+            headers.push_str(&format!("Tags: {}\n", tags));
+        }
+        if let Some(user) = &self.user {
+            headers.push_str(&format!("User: {}\n", user));
+            headers.push_str(&format!("Usertags: {}\n", self.usertags.join(" ")));
+        }
+        for command in self.control.iter() {
+            headers.push_str(&format!("Control: {}\n", command));
+        }
+        if !self.cc.is_empty() {
+            headers.push_str(&format!("X-Debbugs-Cc: {}\n", self.cc.join(", ")));
+        }
+        headers
+    }
+
+    /// Validates the report and builds the message to send to submit@
+    pub fn build(&self) -> Result<Mail, Error> {
+        self.validate()?;
+        let mut mail = Mail::new(&self.from, &self.to, &self.subject);
+        mail.body = format!("{}\n{}", self.pseudo_headers(), self.body);
+        mail.attachments = self.attachments.clone();
+        Ok(mail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::TagAction;
+
+    #[test]
+    fn test_pseudo_headers() {
+        let submission = BugSubmission::new("jane@example.com", "foo", "foo: crashes")
+            .version("1.0-1".parse().unwrap())
+            .severity(Severity::Important)
+            .tag(Tag::Patch)
+            .tag(Tag::Release("trixie".to_string()))
+            .tag(Tag::Patch)
+            .usertags("debian-qa@lists.debian.org", ["ftbfs-gcc-15"])
+            .control(Command::Block {
+                bug: -1,
+                blockers: vec![1000],
+            })
+            .control(Command::Affects {
+                bug: -1,
+                action: TagAction::Add,
+                packages: vec!["bar".to_string()],
+            })
+            .cc("bob@example.com")
+            .cc("Carol <carol@example.com>");
+        assert_eq!(
+            submission.pseudo_headers(),
+            "Package: foo\n\
+             Version: 1.0-1\n\
+             Severity: important\n\
+             Tags: patch trixie\n\
+             User: debian-qa@lists.debian.org\n\
+             Usertags: ftbfs-gcc-15\n\
+             Control: block -1 by 1000\n\
+             Control: affects -1 + bar\n\
+             X-Debbugs-Cc: bob@example.com, Carol <carol@example.com>\n"
+        );
+    }
+
+    #[test]
+    fn test_build() {
+        let mail = BugSubmission::new("Jane <jane@example.com>", "src:foo", "foo: crashes")
+            .to("maintonly@bugs.debian.org")
+            .body("It crashes.\n")
+            .build()
+            .unwrap();
+        assert_eq!(mail.from, "Jane <jane@example.com>");
+        assert_eq!(mail.to, vec!["maintonly@bugs.debian.org"]);
+        assert_eq!(mail.subject, "foo: crashes");
+        assert_eq!(mail.body, "Source: foo\n\nIt crashes.\n");
+        assert!(mail.attachments.is_empty());
+
+        // The pseudo-headers end up at the start of the rendered body
+        let rendered = mail.to_rfc5322();
+        let (_, body) = rendered.split_once("\n\n").unwrap();
+        assert_eq!(body, "Source: foo\n\nIt crashes.\n");
+    }
+
+    #[test]
+    fn test_build_attachments() {
+        let mail = BugSubmission::new("jane@example.com", "foo", "foo: crashes")
+            .attach("fix.patch", "text/x-diff", b"--- a\n+++ b\n".to_vec())
+            .build()
+            .unwrap();
+        assert_eq!(mail.attachments.len(), 1);
+        let rendered = mail.to_rfc5322();
+        assert!(rendered.contains("Content-Type: multipart/mixed;"));
+        assert!(rendered.contains("Content-Disposition: inline\n\nPackage: foo\n"));
+    }
+
+    #[test]
+    fn test_validate() {
+        let valid = || BugSubmission::new("jane@example.com", "foo", "foo: crashes");
+        valid().validate().unwrap();
+        assert!(matches!(
+            BugSubmission::new("jane", "foo", "foo: crashes").validate(),
+            Err(Error::ValidationError(_))
+        ));
+        assert!(
+            BugSubmission::new("jane@example.com", "Foo Bar", "foo: crashes")
+                .validate()
+                .is_err()
+        );
+        assert!(BugSubmission::new("jane@example.com", "foo", "")
+            .validate()
+            .is_err());
+        assert!(valid().cc("not an address").validate().is_err());
+        assert!(valid()
+            .usertags("debian-qa@lists.debian.org", Vec::<String>::new())
+            .validate()
+            .is_err());
+        assert!(valid()
+            .usertags("debian-qa@lists.debian.org", ["two words"])
+            .validate()
+            .is_err());
+        assert!(valid()
+            .control(Command::Merge { bugs: vec![-1] })
+            .validate()
+            .is_err());
+        assert!(valid()
+            .attach("build.log", "text plain", vec![])
+            .validate()
+            .is_err());
+        assert!(valid().build().is_ok());
+    }
+}
@@ -0,0 +1,233 @@
+//! Which suites of the archive a bug affects
+//!
+//! The versions of a source package in each suite, as returned by
+//! `get_versions`, are combined with the found and fixed versions of a bug and
+//! its release tags: a bug tagged with release codenames (e.g. "sid" or
+//! "trixie") only applies to those releases, and a `<codename>-ignore` tag
+//! marks it as not blocking that release even though it is present.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use debbugs::blocking::Debbugs;
+//! use debbugs::suites::{applicability, Suite};
+//!
+//! let client = Debbugs::default();
+//! let suites = [Suite::new("unstable", "sid"), Suite::new("testing", "forky")];
+//! let versions = client.get_suite_versions("foo", &suites).unwrap();
+//! let reports = client.get_status(&[1000]).unwrap();
+//! for entry in applicability(&reports[&1000], "foo", &versions, None) {
+//!     println!("{}: {}", entry.suite.name, entry.status);
+//! }
+//! ```
+
+use crate::versions::{BugPresence, VersionHistory};
+use crate::{BugReport, Tag};
+use debversion::Version;
+
+/// A suite of the archive
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Suite {
+    /// The name to query versions with, e.g. "testing" or "trixie-backports"
+    pub name: String,
+    /// The codename release tags refer to, e.g. "forky" or "trixie"
+    pub codename: String,
+}
+
+impl Suite {
+    /// Creates a suite
+    pub fn new(name: &str, codename: &str) -> Self {
+        Suite {
+            name: name.to_string(),
+            codename: codename.to_string(),
+        }
+    }
+}
+
+/// The versions of a source package in a suite
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SuiteVersions {
+    /// The suite
+    pub suite: Suite,
+    /// The versions of the package in the suite; empty if it is not in the suite
+    pub versions: Vec<Version>,
+}
+
+/// How a bug applies to a suite
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SuiteStatus {
+    /// The package is not in the suite
+    NotInSuite,
+    /// The bug is tagged for other releases only
+    OtherRelease,
+    /// None of the versions in the suite have the bug
+    Unaffected,
+    /// A version in the suite has the bug, but it is ignored for the release
+    Ignored,
+    /// A version in the suite has the bug
+    Affected,
+}
+
+impl std::fmt::Display for SuiteStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            SuiteStatus::NotInSuite => "not in suite",
+            SuiteStatus::OtherRelease => "other release",
+            SuiteStatus::Unaffected => "unaffected",
+            SuiteStatus::Ignored => "ignored",
+            SuiteStatus::Affected => "affected",
+        })
+    }
+}
+
+impl SuiteStatus {
+    /// Returns whether the bug is present in the suite, ignored or not
+    pub fn is_present(&self) -> bool {
+        matches!(self, SuiteStatus::Ignored | SuiteStatus::Affected)
+    }
+}
+
+/// How a bug applies to one suite
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SuiteApplicability {
+    /// The suite
+    pub suite: Suite,
+    /// How the bug applies to it
+    pub status: SuiteStatus,
+    /// The versions in the suite that have the bug
+    pub affected_versions: Vec<Version>,
+}
+
+/// Returns the codename a release tag refers to
+fn release_codename(tag: &Tag) -> Option<&str> {
+    match tag {
+        Tag::Sid => Some("sid"),
+        Tag::Experimental => Some("experimental"),
+        Tag::Release(codename) => Some(codename),
+        _ => None,
+    }
+}
+
+/// Decides how a bug applies to a single suite
+pub fn suite_status(
+    report: &BugReport,
+    package: &str,
+    suite: &SuiteVersions,
+    history: Option<&dyn VersionHistory>,
+) -> SuiteApplicability {
+    let tags = report.tag_list();
+    let codename = suite.suite.codename.as_str();
+    let affected_versions: Vec<Version> = suite
+        .versions
+        .iter()
+        .filter(|v| report.presence(Some(package), v, history) == BugPresence::Found)
+        .cloned()
+        .collect();
+
+    let mut release_tags = tags.iter().filter_map(release_codename).peekable();
+    let status = if suite.versions.is_empty() {
+        SuiteStatus::NotInSuite
+    } else if release_tags.peek().is_some() && !release_tags.any(|c| c == codename) {
+        SuiteStatus::OtherRelease
+    } else if affected_versions.is_empty() {
+        SuiteStatus::Unaffected
+    } else if tags.contains(&Tag::Ignore(codename.to_string())) {
+        SuiteStatus::Ignored
+    } else {
+        SuiteStatus::Affected
+    };
+
+    SuiteApplicability {
+        suite: suite.suite.clone(),
+        status,
+        affected_versions,
+    }
+}
+
+/// Decides how a bug applies to each of the suites
+pub fn applicability(
+    report: &BugReport,
+    package: &str,
+    suites: &[SuiteVersions],
+    history: Option<&dyn VersionHistory>,
+) -> Vec<SuiteApplicability> {
+    suites
+        .iter()
+        .map(|suite| suite_status(report, package, suite, history))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suites() -> Vec<SuiteVersions> {
+        let suite = |name: &str, codename: &str, versions: &[&str]| SuiteVersions {
+            suite: Suite::new(name, codename),
+            versions: versions.iter().map(|v| v.parse().unwrap()).collect(),
+        };
+        vec![
+            suite("unstable", "sid", &["1.2-1"]),
+            suite("testing", "forky", &["1.1-1"]),
+            suite("stable", "trixie", &["1.0-1"]),
+            suite("oldstable", "bookworm", &[]),
+        ]
+    }
+
+    fn statuses(report: &BugReport) -> Vec<SuiteStatus> {
+        applicability(report, "foo", &suites(), None)
+            .into_iter()
+            .map(|a| a.status)
+            .collect()
+    }
+
+    const VERSIONS: &str = "<found_versions><item>foo/1.1-1</item></found_versions>\
+                            <fixed_versions><item>foo/1.2-1</item></fixed_versions>";
+
+    #[test]
+    fn test_versions() {
+        let report = BugReport::from_fields(VERSIONS);
+        assert_eq!(
+            statuses(&report),
+            vec![
+                SuiteStatus::Unaffected,
+                SuiteStatus::Affected,
+                SuiteStatus::Unaffected,
+                SuiteStatus::NotInSuite
+            ]
+        );
+        let testing = suite_status(&report, "foo", &suites()[1], None);
+        assert_eq!(testing.affected_versions, vec!["1.1-1".parse().unwrap()]);
+        assert!(testing.status.is_present());
+    }
+
+    #[test]
+    fn test_unversioned() {
+        assert_eq!(
+            statuses(&BugReport::from_fields("")),
+            vec![
+                SuiteStatus::Affected,
+                SuiteStatus::Affected,
+                SuiteStatus::Affected,
+                SuiteStatus::NotInSuite
+            ]
+        );
+    }
+
+    #[test]
+    fn test_release_tags() {
+        let tagged = BugReport::from_fields("<tags>sid trixie forky-ignore</tags>");
+        assert_eq!(
+            statuses(&tagged),
+            vec![
+                SuiteStatus::Affected,
+                SuiteStatus::OtherRelease,
+                SuiteStatus::Affected,
+                SuiteStatus::NotInSuite
+            ]
+        );
+        let ignored = BugReport::from_fields(&format!("<tags>forky-ignore</tags>{}", VERSIONS));
+        assert_eq!(statuses(&ignored)[1], SuiteStatus::Ignored);
+        assert!(statuses(&ignored)[1].is_present());
+    }
+}
@@ -0,0 +1,282 @@
+//! Reconstruction of discussion threads from bug logs
+//!
+//! Bug logs are returned as a flat list of messages ordered by message number.
+//! This module arranges them into a tree using the `Message-ID`, `In-Reply-To`
+//! and `References` headers, the way a mail client would.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use debbugs::blocking::Debbugs;
+//!
+//! let client = Debbugs::default();
+//! let logs = client.get_bug_log(1000).unwrap();
+//! for thread in debbugs::thread::build_threads(&logs) {
+//!     for (depth, log) in thread.flatten() {
+//!         println!("{}#{}", "  ".repeat(depth), log.msgnum);
+//!     }
+//! }
+//! ```
+
+use crate::mail::message_ids;
+use crate::BugLog;
+use mailparse::MailHeaderMap;
+use std::collections::HashMap;
+
+/// A message in a thread, together with the replies to it
+#[derive(Debug, Clone)]
+pub struct ThreadNode<'a> {
+    /// The message itself
+    pub log: &'a BugLog,
+    /// The Message-ID of the message, without angle brackets
+    pub message_id: Option<String>,
+    /// Whether the message refers to a parent message that is not in the log
+    pub orphan: bool,
+    /// Replies to this message, ordered by message number
+    pub children: Vec<ThreadNode<'a>>,
+}
+
+impl<'a> ThreadNode<'a> {
+    /// Returns the messages in this thread in depth-first order, with their depth
+    pub fn flatten(&self) -> Vec<(usize, &'a BugLog)> {
+        let mut ret = Vec::new();
+        self.flatten_into(0, &mut ret);
+        ret
+    }
+
+    fn flatten_into(&self, depth: usize, ret: &mut Vec<(usize, &'a BugLog)>) {
+        ret.push((depth, self.log));
+        for child in self.children.iter() {
+            child.flatten_into(depth + 1, ret);
+        }
+    }
+
+    /// Returns the number of messages in this thread, including this one
+    pub fn message_count(&self) -> usize {
+        1 + self
+            .children
+            .iter()
+            .map(|c| c.message_count())
+            .sum::<usize>()
+    }
+}
+
+struct Threading {
+    message_id: Option<String>,
+    /// Candidate parents, most likely first
+    parents: Vec<String>,
+}
+
+fn threading_headers(log: &BugLog) -> Threading {
+    let headers = match log.headers() {
+        Ok(headers) => headers,
+        Err(_) => {
+            return Threading {
+                message_id: None,
+                parents: vec![],
+            }
+        }
+    };
+    let message_id = headers
+        .get_first_value("Message-ID")
+        .and_then(|v| message_ids(&v).into_iter().next());
+    let mut parents = headers
+        .get_first_value("In-Reply-To")
+        .map(|v| message_ids(&v))
+        .unwrap_or_default();
+    if let Some(references) = headers.get_first_value("References") {
+        parents.extend(message_ids(&references).into_iter().rev());
+    }
+    Threading {
+        message_id,
+        parents,
+    }
+}
+
+/// Arranges bug log messages into threads
+///
+/// Each message is attached to its closest ancestor that is present in the log,
+/// looking at `In-Reply-To` first and then `References` from the most recent
+/// entry backwards. Messages without a known ancestor become thread roots; if
+/// they did refer to an ancestor, they are marked as orphans. Duplicate messages
+/// (by Message-ID) are attached to the first occurrence.
+///
+/// Roots and replies are ordered by message number.
+pub fn build_threads(logs: &[BugLog]) -> Vec<ThreadNode<'_>> {
+    let mut logs: Vec<&BugLog> = logs.iter().collect();
+    logs.sort_by_key(|log| log.msgnum);
+
+    let threading: Vec<Threading> = logs.iter().map(|log| threading_headers(log)).collect();
+
+    let mut by_id: HashMap<&str, usize> = HashMap::new();
+    for (i, t) in threading.iter().enumerate() {
+        if let Some(id) = t.message_id.as_deref() {
+            by_id.entry(id).or_insert(i);
+        }
+    }
+
+    let mut parent: Vec<Option<usize>> = threading
+        .iter()
+        .enumerate()
+        .map(|(i, t)| {
+            let first = t
+                .message_id
+                .as_deref()
+                .and_then(|id| by_id.get(id).copied());
+            if let Some(first) = first.filter(|first| *first != i) {
+                return Some(first);
+            }
+            t.parents
+                .iter()
+                .filter_map(|p| by_id.get(p.as_str()).copied())
+                .find(|p| *p != i)
+        })
+        .collect();
+
+    // Break any cycles, so that every message ends up in exactly one thread
+    for i in 0..parent.len() {
+        let mut seen = vec![i];
+        let mut current = parent[i];
+        while let Some(p) = current {
+            if seen.contains(&p) {
+                parent[i] = None;
+                break;
+            }
+            seen.push(p);
+            current = parent[p];
+        }
+    }
+
+    let mut children: Vec<Vec<usize>> = vec![vec![]; logs.len()];
+    let mut roots = Vec::new();
+    for (i, p) in parent.iter().enumerate() {
+        match p {
+            Some(p) => children[*p].push(i),
+            None => roots.push(i),
+        }
+    }
+
+    fn build<'a>(
+        i: usize,
+        logs: &[&'a BugLog],
+        threading: &[Threading],
+        parent: &[Option<usize>],
+        children: &[Vec<usize>],
+    ) -> ThreadNode<'a> {
+        ThreadNode {
+            log: logs[i],
+            message_id: threading[i].message_id.clone(),
+            orphan: parent[i].is_none() && !threading[i].parents.is_empty(),
+            children: children[i]
+                .iter()
+                .map(|c| build(*c, logs, threading, parent, children))
+                .collect(),
+        }
+    }
+
+    roots
+        .into_iter()
+        .map(|i| build(i, &logs, &threading, &parent, &children))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BugId;
+
+    fn log(msgnum: BugId, header: &str) -> BugLog {
+        BugLog {
+            header: header.to_string(),
+            msgnum,
+            body: "".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build_threads() {
+        let logs = vec![
+            log(5, "Message-ID: <report@example.com>"),
+            log(
+                10,
+                "Message-ID: <reply1@example.com>\nIn-Reply-To: <report@example.com>",
+            ),
+            log(
+                15,
+                "Message-ID: <reply2@example.com>\nReferences: <report@example.com>\n <reply1@example.com>",
+            ),
+            log(
+                20,
+                "Message-ID: <reply3@example.com>\nReferences: <report@example.com> <missing@example.com>",
+            ),
+            log(
+                25,
+                "Message-ID: <orphan@example.com>\nIn-Reply-To: <elsewhere@example.com>",
+            ),
+            log(30, "Subject: no threading headers"),
+        ];
+        let threads = build_threads(&logs);
+        assert_eq!(threads.len(), 3);
+
+        let root = &threads[0];
+        assert_eq!(root.log.msgnum, 5);
+        assert_eq!(root.message_id.as_deref(), Some("report@example.com"));
+        assert!(!root.orphan);
+        assert_eq!(root.message_count(), 4);
+        assert_eq!(
+            root.flatten()
+                .iter()
+                .map(|(depth, log)| (*depth, log.msgnum))
+                .collect::<Vec<_>>(),
+            vec![(0, 5), (1, 10), (2, 15), (1, 20)]
+        );
+
+        assert_eq!(threads[1].log.msgnum, 25);
+        assert!(threads[1].orphan);
+        assert_eq!(threads[2].log.msgnum, 30);
+        assert!(!threads[2].orphan);
+    }
+
+    #[test]
+    fn test_build_threads_duplicate() {
+        let logs = vec![
+            log(5, "Message-ID: <a@example.com>"),
+            log(10, "Message-ID: <a@example.com>"),
+        ];
+        let threads = build_threads(&logs);
+        assert_eq!(threads.len(), 1);
+        assert_eq!(threads[0].children[0].log.msgnum, 10);
+    }
+
+    #[test]
+    fn test_build_threads_cycle() {
+        let logs = vec![
+            log(
+                5,
+                "Message-ID: <a@example.com>\nIn-Reply-To: <b@example.com>",
+            ),
+            log(
+                10,
+                "Message-ID: <b@example.com>\nIn-Reply-To: <a@example.com>",
+            ),
+        ];
+        let threads = build_threads(&logs);
+        assert_eq!(threads.len(), 1);
+        assert_eq!(threads[0].message_count(), 2);
+    }
+
+    #[test]
+    fn test_build_threads_unordered() {
+        let logs = vec![
+            log(
+                10,
+                "Message-ID: <b@example.com>\nIn-Reply-To: <a@example.com>",
+            ),
+            log(5, "Message-ID: <a@example.com>"),
+        ];
+        let threads = build_threads(&logs);
+        assert_eq!(threads.len(), 1);
+        assert_eq!(threads[0].log.msgnum, 5);
+        assert_eq!(threads[0].children[0].log.msgnum, 10);
+    }
+}
@@ -0,0 +1,948 @@
+//! Usertags and usercategories
+//!
+//! Users of the BTS can attach their own tags to bugs (usertags) and define
+//! usercategories: views that group bugs into sections, like the `ordering=`
+//! option of pkgreport. A usercategory consists of one or more sections, each
+//! with a list of selections such as `tag=needs-review` or `severity=serious`;
+//! with several sections, bugs are grouped by each of them in turn.
+//!
+//! Usercategories are defined with control messages (see
+//! [`crate::control::Command::Usercategory`]); [`usercategories`] collects the
+//! definitions of a user from parsed control messages, and
+//! [`Usercategory::categorise`] groups bugs according to one of them.
+//!
+//! The BTS stores the usertags and usercategories of every user in a
+//! `.usertags` file in its spool; these are not available over SOAP, but
+//! [`parse_usertags_file`] reads them from a copy of the spool, e.g. on a
+//! mirror.
+//!
+//! [`UsertagIndex`] answers the reverse of `get_usertag`: which usertags of a
+//! set of users are attached to a given bug.
+//!
+//! # Examples
+//!
+//! ```
+//! use debbugs::control::parse_control;
+//! use debbugs::usertags::usercategories;
+//!
+//! let body = "\
+//! user qa@example.com
+//! usercategory reviews
+//!  * Review state [tag=]
+//!   + Needs review [needs-review]
+//!   + Reviewed [reviewed]
+//!   + Other
+//! thanks
+//! ";
+//! let categories = usercategories(&parse_control(body, None), "qa@example.com");
+//! assert_eq!(categories[0].name, "reviews");
+//! assert_eq!(categories[0].sections[0].selections.len(), 3);
+//! ```
+
+use crate::control::{CategorySection, CategorySelection, Command, ParsedCommand};
+use crate::{BugId, BugReport, Error};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// A usercategory: a view that groups bugs into sections
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Usercategory {
+    /// The name of the usercategory, as used in `ordering=`
+    pub name: String,
+    /// Whether the usercategory is hidden from the list of orderings
+    pub hidden: bool,
+    /// The sections, each grouping the bugs of the one before
+    pub sections: Vec<CategorySection>,
+}
+
+/// A group of bugs that share a selection in every section of a usercategory
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BugGroup {
+    /// The titles of the selections, one per section
+    pub titles: Vec<String>,
+    /// The bugs in the group, sorted
+    pub bugs: Vec<BugId>,
+}
+
+/// Bugs grouped according to a usercategory
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Categorised {
+    /// The groups, in the order of their selections
+    pub groups: Vec<BugGroup>,
+    /// The bugs that do not match any selection of one of the sections
+    pub other: Vec<BugId>,
+}
+
+fn section(title: &str, prefix: &str, selections: &[(&str, Option<&str>)]) -> CategorySection {
+    CategorySection {
+        title: title.to_string(),
+        prefix: Some(prefix.to_string()),
+        selections: selections
+            .iter()
+            .map(|(title, selection)| CategorySelection {
+                title: title.to_string(),
+                order: None,
+                selection: selection.map(|s| s.to_string()),
+            })
+            .collect(),
+    }
+}
+
+impl Usercategory {
+    /// Returns the usercategory defined by a usercategory command
+    pub fn from_command(command: &Command) -> Option<Self> {
+        match command {
+            Command::Usercategory {
+                name,
+                hidden,
+                sections,
+            } => Some(Usercategory {
+                name: name.clone(),
+                hidden: *hidden,
+                sections: sections.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The built-in `status` usercategory of the BTS
+    pub fn status() -> Self {
+        Usercategory {
+            name: "status".to_string(),
+            hidden: false,
+            sections: vec![section(
+                "Status",
+                "pending=",
+                &[
+                    ("Outstanding", Some("pending")),
+                    ("Forwarded", Some("forwarded")),
+                    ("Pending Upload", Some("pending-fixed")),
+                    ("Fixed in NMU", Some("fixed")),
+                    ("Resolved", Some("done")),
+                    ("From other Branch", Some("absent")),
+                    ("Unknown Pending Status", None),
+                ],
+            )],
+        }
+    }
+
+    /// The built-in `severity` usercategory of the BTS
+    pub fn severity() -> Self {
+        Usercategory {
+            name: "severity".to_string(),
+            hidden: false,
+            sections: vec![section(
+                "Severity",
+                "severity=",
+                &[
+                    ("Critical", Some("critical")),
+                    ("Grave", Some("grave")),
+                    ("Serious", Some("serious")),
+                    ("Important", Some("important")),
+                    ("Normal", Some("normal")),
+                    ("Minor", Some("minor")),
+                    ("Wishlist", Some("wishlist")),
+                    ("Unknown Severity", None),
+                ],
+            )],
+        }
+    }
+
+    /// The built-in `classification` usercategory of the BTS
+    pub fn classification() -> Self {
+        let mut section = section(
+            "Classification",
+            "",
+            &[
+                ("Will Not Fix", Some("pending=pending+tag=wontfix")),
+                (
+                    "More information needed",
+                    Some("pending=pending+tag=moreinfo"),
+                ),
+                ("Patch Available", Some("pending=pending+tag=patch")),
+                ("Confirmed", Some("pending=pending+tag=confirmed")),
+                ("Unclassified", None),
+            ],
+        );
+        section.prefix = None;
+        for (selection, order) in section.selections.iter_mut().zip([2, 3, 4, 1, 5]) {
+            selection.order = Some(order);
+        }
+        Usercategory {
+            name: "classification".to_string(),
+            hidden: false,
+            sections: vec![section],
+        }
+    }
+
+    /// The default view of pkgreport: bugs grouped by status, severity and
+    /// classification
+    pub fn normal() -> Self {
+        Self::combine(
+            "normal",
+            &[Self::status(), Self::severity(), Self::classification()],
+        )
+    }
+
+    /// Returns the built-in usercategory of the BTS with the given name
+    ///
+    /// These are `status`, `severity`, `classification`, `normal` and
+    /// `oldview` (status and severity).
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "status" => Some(Self::status()),
+            "severity" => Some(Self::severity()),
+            "classification" => Some(Self::classification()),
+            "normal" => Some(Self::normal()),
+            "oldview" => Some(Self::combine(
+                "oldview",
+                &[Self::status(), Self::severity()],
+            )),
+            _ => None,
+        }
+    }
+
+    fn combine(name: &str, categories: &[Usercategory]) -> Self {
+        Usercategory {
+            name: name.to_string(),
+            hidden: false,
+            sections: categories
+                .iter()
+                .flat_map(|c| c.sections.iter().cloned())
+                .collect(),
+        }
+    }
+
+    /// Groups bugs according to the usercategory
+    ///
+    /// In every section, a bug goes to the first selection it matches, or to
+    /// the selection without an expression if there is one. Groups are ordered
+    /// by the explicit order of their selections, falling back to the order
+    /// in which they are listed.
+    ///
+    /// # Arguments
+    ///
+    /// * `reports` - The bugs to group
+    /// * `usertags` - The usertags of the user, as returned by `get_usertag`;
+    ///   they can be selected with `tag=` like regular tags
+    pub fn categorise(
+        &self,
+        reports: &HashMap<BugId, BugReport>,
+        usertags: &HashMap<String, Vec<BugId>>,
+    ) -> Categorised {
+        let usertags = by_bug(usertags);
+        let mut groups: BTreeMap<Vec<(u32, usize)>, Vec<BugId>> = BTreeMap::new();
+        let mut other = vec![];
+        let no_usertags = HashSet::new();
+        for (bug, report) in reports {
+            let usertags = usertags.get(bug).unwrap_or(&no_usertags);
+            let key: Option<Vec<(u32, usize)>> = self
+                .sections
+                .iter()
+                .map(|section| {
+                    let index = select(section, report, usertags)?;
+                    let order = section.selections[index].order.unwrap_or(index as u32);
+                    Some((order, index))
+                })
+                .collect();
+            match key {
+                Some(key) => groups.entry(key).or_default().push(*bug),
+                None => other.push(*bug),
+            }
+        }
+        other.sort();
+        Categorised {
+            groups: groups
+                .into_iter()
+                .map(|(key, mut bugs)| {
+                    bugs.sort();
+                    BugGroup {
+                        titles: key
+                            .iter()
+                            .zip(self.sections.iter())
+                            .map(|((_, index), section)| section.selections[*index].title.clone())
+                            .collect(),
+                        bugs,
+                    }
+                })
+                .collect(),
+            other,
+        }
+    }
+}
+
+impl Categorised {
+    /// Renders the groups as text, listing the subject of every bug
+    ///
+    /// Every group is introduced by the titles of its selections.
+    pub fn render(&self, reports: &HashMap<BugId, BugReport>) -> String {
+        fn bug_line(out: &mut String, bug: BugId, reports: &HashMap<BugId, BugReport>) {
+            let subject = reports
+                .get(&bug)
+                .and_then(|r| r.subject.as_deref())
+                .unwrap_or_default();
+            out.push_str(&format!("  #{}: {}\n", bug, subject));
+        }
+        fn count(bugs: &[BugId]) -> String {
+            match bugs.len() {
+                1 => "1 bug".to_string(),
+                n => format!("{} bugs", n),
+            }
+        }
+        let mut out = String::new();
+        for group in self.groups.iter() {
+            out.push_str(&format!(
+                "{} ({})\n",
+                group.titles.join(" / "),
+                count(&group.bugs)
+            ));
+            for bug in group.bugs.iter() {
+                bug_line(&mut out, *bug, reports);
+            }
+        }
+        if !self.other.is_empty() {
+            out.push_str(&format!("Other ({})\n", count(&self.other)));
+            for bug in self.other.iter() {
+                bug_line(&mut out, *bug, reports);
+            }
+        }
+        out
+    }
+}
+
+/// Returns the usercategories `user` defined in a sequence of control commands
+///
+/// Later definitions replace earlier ones with the same name, and definitions
+/// without sections remove the usercategory, as they do in the BTS. Commands
+/// from several messages can be concatenated, in the order they were sent.
+pub fn usercategories(commands: &[ParsedCommand], user: &str) -> Vec<Usercategory> {
+    let mut ret: Vec<Usercategory> = vec![];
+    for command in commands {
+        if command.user.as_deref() != Some(user) {
+            continue;
+        }
+        let category = match command
+            .command
+            .as_ref()
+            .ok()
+            .and_then(Usercategory::from_command)
+        {
+            Some(category) => category,
+            None => continue,
+        };
+        let existing = ret.iter().position(|c| c.name == category.name);
+        match (existing, category.sections.is_empty()) {
+            (Some(i), true) => {
+                ret.remove(i);
+            }
+            (Some(i), false) => ret[i] = category,
+            (None, true) => {}
+            (None, false) => ret.push(category),
+        }
+    }
+    ret
+}
+
+/// The usertags of a set of users, indexed by bug
+///
+/// `get_usertag` goes from a user to their tags and bugs; the index answers
+/// the reverse question, which usertags are attached to a bug and by whom.
+/// The usertags of every user are only retrieved once, so lookups for many
+/// bugs need one request per user; the clients fill the index with
+/// `load_usertags`.
+#[derive(Debug, Default, Clone)]
+pub struct UsertagIndex {
+    users: BTreeMap<String, HashMap<BugId, HashSet<String>>>,
+}
+
+impl UsertagIndex {
+    /// Creates an empty index
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the usertags of a user, as returned by `get_usertag`
+    ///
+    /// Usertags previously added for the user are replaced.
+    pub fn insert(&mut self, user: &str, usertags: &HashMap<String, Vec<BugId>>) {
+        self.users.insert(user.to_string(), by_bug(usertags));
+    }
+
+    /// Returns whether the usertags of `user` are in the index
+    pub fn contains_user(&self, user: &str) -> bool {
+        self.users.contains_key(user)
+    }
+
+    /// Returns the users in the index, sorted
+    pub fn users(&self) -> impl Iterator<Item = &str> {
+        self.users.keys().map(|u| u.as_str())
+    }
+
+    /// Removes the usertags of `user`, so they are retrieved again
+    pub fn invalidate(&mut self, user: &str) {
+        self.users.remove(user);
+    }
+
+    /// Returns the usertags of a bug, by user
+    ///
+    /// Users without usertags on the bug are left out; the tags are sorted.
+    pub fn lookup(&self, bug: BugId) -> BTreeMap<String, Vec<String>> {
+        self.users
+            .iter()
+            .filter_map(|(user, bugs)| {
+                let mut tags: Vec<String> = bugs.get(&bug)?.iter().cloned().collect();
+                tags.sort();
+                Some((user.clone(), tags))
+            })
+            .collect()
+    }
+
+    /// Returns the usertags `user` attached to a bug, sorted
+    pub fn usertags(&self, user: &str, bug: BugId) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .users
+            .get(user)
+            .and_then(|bugs| bugs.get(&bug))
+            .map(|tags| tags.iter().cloned().collect())
+            .unwrap_or_default();
+        tags.sort();
+        tags
+    }
+
+    /// Returns the bugs that have usertags of any of the users, sorted
+    pub fn bugs(&self) -> Vec<BugId> {
+        let mut bugs: Vec<BugId> = self
+            .users
+            .values()
+            .flat_map(|bugs| bugs.keys().copied())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        bugs.sort();
+        bugs
+    }
+}
+
+/// Inverts the usertags of a user, from tag to bugs to bug to tags
+fn by_bug(usertags: &HashMap<String, Vec<BugId>>) -> HashMap<BugId, HashSet<String>> {
+    let mut ret: HashMap<BugId, HashSet<String>> = HashMap::new();
+    for (tag, bugs) in usertags {
+        for bug in bugs {
+            ret.entry(*bug).or_default().insert(tag.clone());
+        }
+    }
+    ret
+}
+
+/// Returns the index of the selection of `section` that a bug belongs to
+fn select(
+    section: &CategorySection,
+    report: &BugReport,
+    usertags: &HashSet<String>,
+) -> Option<usize> {
+    let prefix = section.prefix.as_deref().unwrap_or_default();
+    section
+        .selections
+        .iter()
+        .position(|s| {
+            s.selection
+                .as_deref()
+                .is_some_and(|s| matches(&format!("{}{}", prefix, s), report, usertags))
+        })
+        .or_else(|| {
+            section
+                .selections
+                .iter()
+                .position(|s| s.selection.is_none())
+        })
+}
+
+/// Returns whether a bug matches a selection like `pending=pending+tag=patch`
+///
+/// Like in debbugs, every `field=value` part joined with `+` has to match, and
+/// parts without a field are ignored. As the section prefix is prepended to
+/// the whole selection, it only applies to the first part.
+fn matches(selection: &str, report: &BugReport, usertags: &HashSet<String>) -> bool {
+    selection.split('+').all(|part| {
+        let (field, value) = match part.split_once('=') {
+            Some(part) => part,
+            None => return true,
+        };
+        match field {
+            "tag" => {
+                usertags.contains(value)
+                    || report
+                        .tags
+                        .as_deref()
+                        .unwrap_or_default()
+                        .split(|c: char| c == ',' || c.is_whitespace())
+                        .any(|t| t == value)
+            }
+            "severity" => report.severity.as_deref().unwrap_or("normal") == value,
+            "pending" => report
+                .pending
+                .map_or(value == "pending", |p| p.to_string() == value),
+            "package" => report
+                .package
+                .as_deref()
+                .unwrap_or_default()
+                .split(',')
+                .any(|p| p.trim() == value),
+            "source" => report.source.as_deref() == Some(value),
+            _ => match report.extra.get(field) {
+                Some(actual) => actual == value,
+                None => {
+                    log::debug!("Unknown field in usercategory selection: {}", selection);
+                    false
+                }
+            },
+        }
+    })
+}
+
+/// The usertags and usercategories of a user, as stored by the BTS
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct UsertagsFile {
+    /// The usertags, mapping tags to bugs like `get_usertag`
+    pub usertags: HashMap<String, Vec<BugId>>,
+    /// The usercategories, in the order they are defined
+    pub categories: Vec<Usercategory>,
+}
+
+/// A usercategory from a `.usertags` file: its name, whether it is hidden and its
+/// sections, or the names of the usercategories they refer to
+type RawCategory = (String, bool, Vec<Result<CategorySection, String>>);
+
+/// Parses the `.usertags` file the BTS keeps for a user
+///
+/// The file consists of stanzas separated by empty lines: `Tag:` stanzas
+/// list the bugs with a usertag, `Category:` stanzas define usercategories
+/// whose sections are given by `Cat1:`, `Cat1-Options:` and `Cat1-Order:`
+/// fields. A section that just names another usercategory refers to the
+/// sections of that one, or of a built-in one such as `status`.
+pub fn parse_usertags_file(text: &str) -> Result<UsertagsFile, Error> {
+    let mut usertags: HashMap<String, Vec<BugId>> = HashMap::new();
+    let mut categories: Vec<RawCategory> = vec![];
+    for stanza in text.split("\n\n") {
+        let fields = parse_stanza(stanza);
+        let get = |name: &str| {
+            fields
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.as_str())
+        };
+        if let Some(tag) = get("Tag") {
+            let bugs = get("Bugs")
+                .unwrap_or_default()
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|b| !b.is_empty())
+                .map(|b| {
+                    b.parse()
+                        .map_err(|_| Error::ParseError(format!("Invalid bug number: {:?}", b)))
+                })
+                .collect::<Result<Vec<BugId>, Error>>()?;
+            usertags.entry(tag.to_string()).or_default().extend(bugs);
+        } else if let Some(name) = get("Category") {
+            // Sections without options refer to another usercategory by name
+            let mut sections = vec![];
+            for i in 1.. {
+                let title = match get(&format!("Cat{}", i)) {
+                    Some(title) => title,
+                    None => break,
+                };
+                sections.push(match get(&format!("Cat{}-Options", i)) {
+                    Some(options) => Ok(parse_section(
+                        title,
+                        options,
+                        get(&format!("Cat{}-Order", i)),
+                    )?),
+                    None => Err(title.to_string()),
+                });
+            }
+            let hidden = get("Hidden").is_some_and(|h| h.eq_ignore_ascii_case("yes"));
+            categories.push((name.to_string(), hidden, sections));
+        }
+    }
+
+    fn resolve(categories: &[RawCategory], name: &str, depth: usize) -> Vec<CategorySection> {
+        let sections = match categories.iter().find(|(n, _, _)| n == name) {
+            Some((_, _, sections)) if depth < 8 => sections,
+            _ => {
+                return Usercategory::builtin(name)
+                    .map(|c| c.sections)
+                    .unwrap_or_else(|| {
+                        log::debug!("Unknown usercategory referenced: {}", name);
+                        vec![]
+                    })
+            }
+        };
+        sections
+            .iter()
+            .flat_map(|section| match section {
+                Ok(section) => vec![section.clone()],
+                Err(reference) => resolve(categories, reference, depth + 1),
+            })
+            .collect()
+    }
+
+    Ok(UsertagsFile {
+        usertags,
+        categories: categories
+            .iter()
+            .map(|(name, hidden, _)| Usercategory {
+                name: name.clone(),
+                hidden: *hidden,
+                sections: resolve(&categories, name, 0),
+            })
+            .collect(),
+    })
+}
+
+/// Splits a stanza into its fields, joining continuation lines
+fn parse_stanza(stanza: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = vec![];
+    for line in stanza.lines() {
+        if line.starts_with(char::is_whitespace) {
+            if let Some((_, value)) = fields.last_mut() {
+                value.push('\n');
+                value.push_str(line.trim());
+            }
+        } else if let Some((key, value)) = line.split_once(':') {
+            fields.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    fields
+}
+
+/// Parses the options of a section in a `.usertags` file
+///
+/// Every option is a selection, optionally followed by `= title`; the
+/// `default` option gives the title for bugs that match none of them.
+fn parse_section(
+    title: &str,
+    options: &str,
+    order: Option<&str>,
+) -> Result<CategorySection, Error> {
+    let mut selections = vec![];
+    let mut default = None;
+    for option in options.lines().map(str::trim).filter(|o| !o.is_empty()) {
+        let (selection, title) = match option.split_once(" = ") {
+            Some((selection, title)) => (selection.trim(), Some(title.trim())),
+            None => (option, None),
+        };
+        if selection == "default" {
+            default = title.map(|t| t.to_string());
+            continue;
+        }
+        selections.push(CategorySelection {
+            title: title.unwrap_or(selection).to_string(),
+            order: None,
+            selection: Some(selection.to_string()),
+        });
+    }
+    if let Some(default) = default {
+        selections.push(CategorySelection {
+            title: default,
+            order: None,
+            selection: None,
+        });
+    }
+    if let Some(order) = order {
+        for (selection, order) in selections.iter_mut().zip(order.split(',')) {
+            selection.order = Some(order.trim().parse().map_err(|_| {
+                Error::ParseError(format!("Invalid usercategory order: {:?}", order))
+            })?);
+        }
+    }
+    Ok(CategorySection {
+        title: title.to_string(),
+        prefix: None,
+        selections,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::parse_control;
+
+    #[test]
+    fn test_usercategories() {
+        let first = "\
+user qa@example.com
+usercategory reviews [hidden]
+ * Review state [tag=]
+  + Needs review [2:needs-review]
+  + Other
+usercategory obsolete
+ * Old [tag=]
+  + Old [old]
+user other@example.com
+usercategory mine
+ * Mine
+  + Mine [mine]
+thanks
+";
+        let second = "\
+user qa@example.com
+usercategory obsolete
+usercategory reviews
+ * Review state [tag=]
+  + Needs review [needs-review]
+thanks
+";
+        let mut commands = parse_control(first, None);
+        let categories = usercategories(&commands, "qa@example.com");
+        assert_eq!(
+            categories
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["reviews", "obsolete"]
+        );
+        assert!(categories[0].hidden);
+        assert_eq!(categories[0].sections[0].selections[0].order, Some(2));
+
+        commands.extend(parse_control(second, None));
+        let categories = usercategories(&commands, "qa@example.com");
+        assert_eq!(categories.len(), 1);
+        assert!(!categories[0].hidden);
+        assert_eq!(categories[0].sections[0].selections.len(), 1);
+        assert_eq!(
+            usercategories(&commands, "other@example.com")[0].name,
+            "mine"
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        let patched =
+            BugReport::from_fields("<tags>patch moreinfo</tags><severity>serious</severity>");
+        let none = HashSet::new();
+        assert!(matches("tag=patch", &patched, &none));
+        assert!(matches("tag=patch+severity=serious", &patched, &none));
+        assert!(!matches("tag=patch+tag=wontfix", &patched, &none));
+        assert!(!matches("severity=serious+severity=grave", &patched, &none));
+        // Parts without a field are ignored, as they are by debbugs
+        assert!(matches("tag=patch+wontfix", &patched, &none));
+        assert!(matches("pending=pending+tag=moreinfo", &patched, &none));
+        assert!(matches(
+            "tag=needs-review+tag=patch",
+            &patched,
+            &HashSet::from(["needs-review".to_string()])
+        ));
+        assert!(!matches("nosuchfield=x", &patched, &none));
+    }
+
+    #[test]
+    fn test_categorise() {
+        let category = usercategories(
+            &parse_control(
+                "user qa@example.com\n\
+                 usercategory reviews\n \
+                 * Review state [tag=]\n  \
+                 + Reviewed [3:reviewed]\n  \
+                 + Patch needs review [1:needs-review+tag=patch]\n  \
+                 + Needs review [2:needs-review]\n \
+                 * Severity [severity=]\n  \
+                 + Serious [serious]\n  \
+                 + Grave [grave]\n  \
+                 + Other\n",
+                None,
+            ),
+            "qa@example.com",
+        )
+        .remove(0);
+        let reports = HashMap::from([
+            (
+                1,
+                BugReport::from_fields(
+                    "<severity>serious</severity><tags>patch</tags><subject>crash</subject>",
+                ),
+            ),
+            (2, BugReport::from_fields("<severity>normal</severity>")),
+            (3, BugReport::from_fields("<severity>grave</severity>")),
+            (
+                4,
+                BugReport::from_fields("<severity>wishlist</severity><tags>patch</tags>"),
+            ),
+            (5, BugReport::from_fields("<severity>serious</severity>")),
+        ]);
+        let usertags = HashMap::from([
+            ("needs-review".to_string(), vec![1, 3, 5]),
+            ("reviewed".to_string(), vec![2]),
+        ]);
+        let categorised = category.categorise(&reports, &usertags);
+        assert_eq!(
+            categorised,
+            Categorised {
+                groups: vec![
+                    BugGroup {
+                        titles: vec!["Patch needs review".to_string(), "Serious".to_string()],
+                        bugs: vec![1],
+                    },
+                    BugGroup {
+                        titles: vec!["Needs review".to_string(), "Serious".to_string()],
+                        bugs: vec![5],
+                    },
+                    BugGroup {
+                        titles: vec!["Needs review".to_string(), "Grave".to_string()],
+                        bugs: vec![3],
+                    },
+                    BugGroup {
+                        titles: vec!["Reviewed".to_string(), "Other".to_string()],
+                        bugs: vec![2],
+                    },
+                ],
+                other: vec![4],
+            }
+        );
+        assert_eq!(
+            categorised.render(&reports),
+            "Patch needs review / Serious (1 bug)\n  #1: crash\n\
+             Needs review / Serious (1 bug)\n  #5: \n\
+             Needs review / Grave (1 bug)\n  #3: \n\
+             Reviewed / Other (1 bug)\n  #2: \n\
+             Other (1 bug)\n  #4: \n"
+        );
+    }
+
+    #[test]
+    fn test_normal() {
+        let reports = HashMap::from([
+            (
+                1,
+                BugReport::from_fields("<severity>serious</severity><tags>moreinfo patch</tags>"),
+            ),
+            (
+                2,
+                BugReport::from_fields("<severity>minor</severity><pending>forwarded</pending>"),
+            ),
+            (
+                3,
+                BugReport::from_fields("<severity>bogus</severity><pending>done</pending>"),
+            ),
+            (4, BugReport::from_fields("<pending>absent</pending>")),
+        ]);
+        let categorised = Usercategory::normal().categorise(&reports, &HashMap::new());
+        assert!(categorised.other.is_empty());
+        assert_eq!(
+            categorised
+                .groups
+                .iter()
+                .map(|g| g.titles.join(" / "))
+                .collect::<Vec<_>>(),
+            vec![
+                "Outstanding / Serious / More information needed",
+                "Forwarded / Minor / Unclassified",
+                "Resolved / Unknown Severity / Unclassified",
+                "From other Branch / Normal / Unclassified",
+            ]
+        );
+        assert!(Usercategory::builtin("oldview").is_some());
+        assert!(Usercategory::builtin("reviews").is_none());
+    }
+
+    #[test]
+    fn test_parse_usertags_file() {
+        let text = "\
+Tag: needs-review
+Bugs: 1, 2,
+ 3
+
+Tag: reviewed
+Bugs: 4
+
+Category: reviews
+Hidden: yes
+Cat1: Review state
+Cat1-Options:
+ tag=needs-review+tag=patch = Patch needs review
+ tag=needs-review = Needs review
+ default = Other
+Cat1-Order: 2, 1, 3
+Cat2: severity
+
+Category: everything
+Cat1: reviews
+Cat2: nosuchcategory
+";
+        let file = parse_usertags_file(text).unwrap();
+        assert_eq!(file.usertags["needs-review"], vec![1, 2, 3]);
+        assert_eq!(file.usertags["reviewed"], vec![4]);
+        assert_eq!(file.categories.len(), 2);
+
+        let reviews = &file.categories[0];
+        assert_eq!(reviews.name, "reviews");
+        assert!(reviews.hidden);
+        assert_eq!(reviews.sections.len(), 2);
+        assert_eq!(
+            reviews.sections[0].selections,
+            vec![
+                CategorySelection {
+                    title: "Patch needs review".to_string(),
+                    order: Some(2),
+                    selection: Some("tag=needs-review+tag=patch".to_string()),
+                },
+                CategorySelection {
+                    title: "Needs review".to_string(),
+                    order: Some(1),
+                    selection: Some("tag=needs-review".to_string()),
+                },
+                CategorySelection {
+                    title: "Other".to_string(),
+                    order: Some(3),
+                    selection: None,
+                },
+            ]
+        );
+        assert_eq!(reviews.sections[1], Usercategory::severity().sections[0]);
+
+        let everything = &file.categories[1];
+        assert!(!everything.hidden);
+        assert_eq!(everything.sections, reviews.sections);
+
+        assert!(matches!(
+            parse_usertags_file("Tag: foo\nBugs: 1, x\n"),
+            Err(Error::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_usertag_index() {
+        let mut index = UsertagIndex::new();
+        index.insert(
+            "release@example.com",
+            &HashMap::from([
+                ("blocker".to_string(), vec![1, 2]),
+                ("forky-can-defer".to_string(), vec![1]),
+            ]),
+        );
+        index.insert(
+            "qa@example.com",
+            &HashMap::from([("ftbfs".to_string(), vec![1, 3])]),
+        );
+        assert!(index.contains_user("qa@example.com"));
+        assert!(!index.contains_user("other@example.com"));
+        assert_eq!(
+            index.users().collect::<Vec<_>>(),
+            vec!["qa@example.com", "release@example.com"]
+        );
+        assert_eq!(
+            index.lookup(1),
+            BTreeMap::from([
+                ("qa@example.com".to_string(), vec!["ftbfs".to_string()]),
+                (
+                    "release@example.com".to_string(),
+                    vec!["blocker".to_string(), "forky-can-defer".to_string()]
+                ),
+            ])
+        );
+        assert_eq!(index.lookup(3).len(), 1);
+        assert!(index.lookup(4).is_empty());
+        assert_eq!(index.usertags("release@example.com", 2), vec!["blocker"]);
+        assert_eq!(index.bugs(), vec![1, 2, 3]);
+
+        index.invalidate("qa@example.com");
+        assert!(index.lookup(3).is_empty());
+    }
+}
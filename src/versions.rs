@@ -0,0 +1,601 @@
+//! Version tracking of bugs
+//!
+//! The BTS records the versions a bug was found in and fixed in, optionally
+//! qualified with the source package (e.g. "foo/1.0-1"). Whether a bug is
+//! present in some other version follows from these: the nearest recorded
+//! version at or before it decides. Without further information "before" means
+//! a smaller version number; with a [`VersionHistory`], such as the ancestry
+//! recorded in `debian/changelog`, it means an ancestor, which keeps branches
+//! like stable updates and backports apart. [`VersionTree`] provides that
+//! ancestry, built from changelogs or the version data of the BTS.
+//!
+//! # Examples
+//!
+//! ```
+//! use debbugs::versions::{buggy, parse_qualified_version, BugPresence};
+//!
+//! let found = vec![parse_qualified_version("foo/1.0-1")];
+//! let fixed = vec![parse_qualified_version("1.2-1")];
+//! let version = "1.1-1".parse().unwrap();
+//! assert_eq!(buggy(Some("foo"), &version, &found, &fixed, None), BugPresence::Found);
+//! let version = "1.2-2".parse().unwrap();
+//! assert_eq!(buggy(Some("foo"), &version, &found, &fixed, None), BugPresence::Fixed);
+//! ```
+
+use crate::{BugReport, Error};
+use debversion::Version;
+use lazy_regex::regex_captures;
+use std::collections::{HashMap, HashSet};
+
+/// A version as recorded by the BTS, optionally qualified with a source package
+///
+/// The version is `None` if the BTS returned something that is not a valid
+/// Debian version.
+pub type QualifiedVersion = (Option<String>, Option<Version>);
+
+/// Parses a version as recorded by the BTS, e.g. "1.0-1" or "foo/1.0-1"
+pub fn parse_qualified_version(input: &str) -> QualifiedVersion {
+    match input.split_once('/') {
+        None => (None, input.trim().parse().ok()),
+        Some((package, version)) => (Some(package.to_string()), version.trim().parse().ok()),
+    }
+}
+
+/// Whether a bug is present in a particular version
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BugPresence {
+    /// The version is affected by the bug
+    Found,
+    /// The bug was fixed in the version or one of its predecessors
+    Fixed,
+    /// The version predates the versions the bug was found in
+    Absent,
+}
+
+impl std::fmt::Display for BugPresence {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            BugPresence::Found => "found",
+            BugPresence::Fixed => "fixed",
+            BugPresence::Absent => "absent",
+        })
+    }
+}
+
+/// The ancestry of the versions of source packages
+pub trait VersionHistory {
+    /// Returns `version` of `package` followed by the versions it descends
+    /// from, nearest first
+    ///
+    /// Ancestors are qualified with their source package, since packages may
+    /// have been renamed. Returns `None` if the version is not known.
+    fn lineage(&self, package: &str, version: &Version) -> Option<Vec<(String, Version)>>;
+}
+
+/// Returns the versions that apply to `package`, dropping unparseable ones
+///
+/// Unqualified versions apply to any package, and all versions apply if the
+/// package is not known.
+fn applicable<'a>(
+    package: Option<&'a str>,
+    versions: &'a [QualifiedVersion],
+) -> impl Iterator<Item = &'a Version> {
+    versions
+        .iter()
+        .filter_map(move |(p, v)| match (p, package) {
+            (Some(p), Some(package)) if p != package => None,
+            _ => v.as_ref(),
+        })
+}
+
+/// Returns whether `version` of `package` is among `versions`
+fn recorded(versions: &[QualifiedVersion], package: &str, version: &Version) -> bool {
+    versions.iter().any(|(p, v)| {
+        v.as_ref() == Some(version)
+            && match p {
+                Some(p) => p == package,
+                None => true,
+            }
+    })
+}
+
+/// Decides whether a bug with the given found and fixed versions is present in
+/// `version` of the source package `package`
+///
+/// This follows the semantics of the BTS:
+///
+/// - A bug without found or fixed versions is present in every version.
+/// - Otherwise the nearest found or fixed version at or before `version`
+///   decides; if a version is recorded as both, it counts as found.
+/// - If there is no such version, the bug is absent, unless it only has fixed
+///   versions, in which case it is assumed to have been present all along.
+///
+/// If `history` is given and knows `version`, "before" means being an ancestor
+/// in the history; otherwise versions of `package` are compared linearly.
+pub fn buggy(
+    package: Option<&str>,
+    version: &Version,
+    found: &[QualifiedVersion],
+    fixed: &[QualifiedVersion],
+    history: Option<&dyn VersionHistory>,
+) -> BugPresence {
+    let versioned = |versions: &[QualifiedVersion]| versions.iter().any(|(_, v)| v.is_some());
+    if !versioned(found) && !versioned(fixed) {
+        return BugPresence::Found;
+    }
+
+    let lineage = match (history, package) {
+        (Some(history), Some(package)) => history.lineage(package, version),
+        _ => None,
+    };
+
+    let nearest = match lineage {
+        Some(lineage) => lineage.iter().find_map(|(package, version)| {
+            if recorded(found, package, version) {
+                Some(BugPresence::Found)
+            } else if recorded(fixed, package, version) {
+                Some(BugPresence::Fixed)
+            } else {
+                None
+            }
+        }),
+        None => {
+            let newest = |versions| {
+                applicable(package, versions)
+                    .filter(|v| *v <= version)
+                    .max()
+            };
+            match (newest(found), newest(fixed)) {
+                (None, None) => None,
+                (Some(_), None) => Some(BugPresence::Found),
+                (None, Some(_)) => Some(BugPresence::Fixed),
+                (Some(found), Some(fixed)) if found >= fixed => Some(BugPresence::Found),
+                (Some(_), Some(_)) => Some(BugPresence::Fixed),
+            }
+        }
+    };
+
+    nearest.unwrap_or(if versioned(found) {
+        BugPresence::Absent
+    } else {
+        BugPresence::Found
+    })
+}
+
+impl BugReport {
+    /// Decides whether the bug is present in `version` of the source package `package`
+    ///
+    /// See [`buggy`] for the rules that are applied. A bug that was closed
+    /// without recording a fixed version counts as fixed in every version.
+    pub fn presence(
+        &self,
+        package: Option<&str>,
+        version: &Version,
+        history: Option<&dyn VersionHistory>,
+    ) -> BugPresence {
+        let done = self.done.as_deref().is_some_and(|done| !done.is_empty());
+        if done
+            && self
+                .fixed_versions
+                .as_deref()
+                .unwrap_or_default()
+                .is_empty()
+        {
+            return BugPresence::Fixed;
+        }
+        buggy(
+            package,
+            version,
+            self.found_versions.as_deref().unwrap_or_default(),
+            self.fixed_versions.as_deref().unwrap_or_default(),
+            history,
+        )
+    }
+
+    /// Returns whether the bug is present in `version` of the source package `package`
+    pub fn affects_version(
+        &self,
+        package: Option<&str>,
+        version: &Version,
+        history: Option<&dyn VersionHistory>,
+    ) -> bool {
+        self.presence(package, version, history) == BugPresence::Found
+    }
+}
+
+/// The ancestry of source package versions, as tracked by the BTS
+///
+/// The tree is built from `debian/changelog` files, where each entry descends
+/// from the one below it, or from the version data the BTS keeps. Histories of
+/// several branches, e.g. unstable and a backport, are merged on the versions
+/// they have in common. If histories disagree about the parent of a version,
+/// the one added first is kept.
+#[derive(Debug, Default, Clone)]
+pub struct VersionTree {
+    parents: HashMap<(String, Version), (String, Version)>,
+    versions: HashSet<(String, Version)>,
+}
+
+impl VersionTree {
+    /// Creates an empty tree
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a tree from the contents of several `debian/changelog` files
+    pub fn from_changelogs<'a>(
+        changelogs: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, Error> {
+        let mut tree = Self::new();
+        for changelog in changelogs {
+            tree.add_changelog(changelog)?;
+        }
+        Ok(tree)
+    }
+
+    /// Adds a line of descent, oldest version first
+    pub fn add_chain(&mut self, chain: &[(String, Version)]) {
+        for entry in chain.iter() {
+            self.versions.insert(entry.clone());
+        }
+        for pair in chain.windows(2) {
+            let (parent, child) = (&pair[0], &pair[1]);
+            if parent == child {
+                continue;
+            }
+            match self.parents.get(child) {
+                Some(existing) if existing != parent => {
+                    log::debug!(
+                        "Ignoring parent {}/{} of {}/{}, already descends from {}/{}",
+                        parent.0,
+                        parent.1,
+                        child.0,
+                        child.1,
+                        existing.0,
+                        existing.1
+                    );
+                }
+                Some(_) => {}
+                None => {
+                    self.parents.insert(child.clone(), parent.clone());
+                }
+            }
+        }
+    }
+
+    /// Adds the history recorded in a `debian/changelog` file
+    ///
+    /// Lines that are not entry headers are ignored, and parsing stops at the
+    /// trailer some old changelogs carry (e.g. "Local variables:").
+    pub fn add_changelog(&mut self, changelog: &str) -> Result<(), Error> {
+        let mut chain = changelog_entries(changelog)?
+            .into_iter()
+            .map(|(package, version, _)| (package, version))
+            .collect::<Vec<_>>();
+        chain.reverse();
+        self.add_chain(&chain);
+        Ok(())
+    }
+
+    /// Adds version data in the format the BTS stores it in
+    ///
+    /// Every line holds a line of descent of whitespace-separated
+    /// `package/version` entries, oldest first.
+    pub fn add_versions(&mut self, data: &str) -> Result<(), Error> {
+        for line in data.lines() {
+            let chain = line
+                .split_whitespace()
+                .map(|entry| match parse_qualified_version(entry) {
+                    (Some(package), Some(version)) => Ok((package, version)),
+                    _ => Err(Error::ParseError(format!(
+                        "Invalid entry in version data: {}",
+                        entry
+                    ))),
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            self.add_chain(&chain);
+        }
+        Ok(())
+    }
+
+    /// Returns whether the tree knows a version of a package
+    pub fn contains(&self, package: &str, version: &Version) -> bool {
+        self.versions
+            .contains(&(package.to_string(), version.clone()))
+    }
+
+    /// Returns the version a version of a package directly descends from
+    pub fn parent(&self, package: &str, version: &Version) -> Option<&(String, Version)> {
+        self.parents.get(&(package.to_string(), version.clone()))
+    }
+
+    /// Returns whether `version` of `package` descends from, or is, `ancestor`
+    pub fn descends_from(
+        &self,
+        package: &str,
+        version: &Version,
+        ancestor: &(String, Version),
+    ) -> bool {
+        self.lineage(package, version)
+            .is_some_and(|lineage| lineage.contains(ancestor))
+    }
+}
+
+/// Splits a `debian/changelog` file into its entries, newest first
+///
+/// Returns the package, version and text of every entry. Lines before the
+/// first entry header are ignored, and parsing stops at the trailer some old
+/// changelogs carry (e.g. "Local variables:").
+pub(crate) fn changelog_entries(changelog: &str) -> Result<Vec<(String, Version, String)>, Error> {
+    let mut entries: Vec<(String, Version, String)> = vec![];
+    for line in changelog.lines() {
+        if line.starts_with("Local variables:") || line.starts_with("Old Changelog:") {
+            break;
+        }
+        if let Some((_, package, version)) =
+            regex_captures!(r"^(\w[-+0-9a-z.]*) \(([^()]+)\)", line)
+        {
+            let version = version.parse::<Version>().map_err(|_| {
+                Error::ParseError(format!("Invalid version in changelog: {}", version))
+            })?;
+            entries.push((package.to_string(), version, String::new()));
+        } else if let Some((_, _, text)) = entries.last_mut() {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    Ok(entries)
+}
+
+impl VersionHistory for VersionTree {
+    fn lineage(&self, package: &str, version: &Version) -> Option<Vec<(String, Version)>> {
+        let mut current = (package.to_string(), version.clone());
+        if !self.versions.contains(&current) {
+            return None;
+        }
+        let mut lineage = vec![];
+        let mut seen = HashSet::new();
+        while seen.insert(current.clone()) {
+            lineage.push(current.clone());
+            match self.parents.get(&current) {
+                Some(parent) => current = parent.clone(),
+                None => break,
+            }
+        }
+        Some(lineage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions(input: &[&str]) -> Vec<QualifiedVersion> {
+        input.iter().map(|v| parse_qualified_version(v)).collect()
+    }
+
+    fn check(version: &str, found: &[&str], fixed: &[&str]) -> BugPresence {
+        buggy(
+            Some("foo"),
+            &version.parse().unwrap(),
+            &versions(found),
+            &versions(fixed),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_parse_qualified_version() {
+        assert_eq!(
+            parse_qualified_version("1.0-1"),
+            (None, Some("1.0-1".parse().unwrap()))
+        );
+        assert_eq!(
+            parse_qualified_version("foo/1:2.0"),
+            (Some("foo".to_string()), Some("1:2.0".parse().unwrap()))
+        );
+        assert_eq!(
+            parse_qualified_version("foo/"),
+            (Some("foo".to_string()), None)
+        );
+    }
+
+    #[test]
+    fn test_unversioned() {
+        assert_eq!(check("1.0", &[], &[]), BugPresence::Found);
+    }
+
+    #[test]
+    fn test_linear() {
+        let found = ["1.0-1"];
+        let fixed = ["1.2-1"];
+        assert_eq!(check("0.9-1", &found, &fixed), BugPresence::Absent);
+        assert_eq!(check("1.0-1", &found, &fixed), BugPresence::Found);
+        assert_eq!(check("1.1-1", &found, &fixed), BugPresence::Found);
+        assert_eq!(check("1.2-1", &found, &fixed), BugPresence::Fixed);
+        assert_eq!(check("1:0.1-1", &found, &fixed), BugPresence::Fixed);
+    }
+
+    #[test]
+    fn test_linear_regression() {
+        let found = ["1.0-1", "1.5-1"];
+        let fixed = ["1.2-1"];
+        assert_eq!(check("1.3-1", &found, &fixed), BugPresence::Fixed);
+        assert_eq!(check("1.6-1", &found, &fixed), BugPresence::Found);
+        // A version recorded as both found and fixed counts as found
+        assert_eq!(check("1.2-1", &["1.2-1"], &fixed), BugPresence::Found);
+    }
+
+    #[test]
+    fn test_only_fixed() {
+        assert_eq!(check("0.1-1", &[], &["1.2-1"]), BugPresence::Found);
+        assert_eq!(check("1.2-1", &[], &["1.2-1"]), BugPresence::Fixed);
+    }
+
+    #[test]
+    fn test_source_qualification() {
+        // Versions of other source packages are ignored
+        assert_eq!(
+            check("1.0-1", &["bar/1.0-1"], &["foo/0.5-1"]),
+            BugPresence::Fixed
+        );
+        assert_eq!(check("1.0-1", &["bar/1.0-1"], &[]), BugPresence::Absent);
+        // Without a package, all versions apply
+        assert_eq!(
+            buggy(
+                None,
+                &"1.0-1".parse().unwrap(),
+                &versions(&["bar/1.0-1"]),
+                &versions(&["foo/0.5-1"]),
+                None
+            ),
+            BugPresence::Found
+        );
+        // Unparseable versions are ignored
+        assert_eq!(check("1.0-1", &["foo/"], &[]), BugPresence::Found);
+    }
+
+    const UNSTABLE: &str = "\
+foo (1.2-1) unstable; urgency=medium
+
+  * New upstream release.
+
+ -- Jane <jane@example.com>  Mon, 01 Jan 2024 00:00:00 +0000
+
+foo (1.1-1) unstable; urgency=medium
+
+  * New upstream release.
+
+ -- Jane <jane@example.com>  Mon, 01 May 2023 00:00:00 +0000
+
+foo (1.0-1) unstable; urgency=low
+
+  * Initial release.
+
+ -- Jane <jane@example.com>  Sun, 01 Jan 2023 00:00:00 +0000
+";
+
+    const SECURITY: &str = "\
+foo (1.0-1+deb12u1) bookworm-security; urgency=high
+
+  * Fix CVE-2024-0001.
+
+ -- Jane <jane@example.com>  Tue, 02 Jan 2024 00:00:00 +0000
+
+foo (1.0-1) unstable; urgency=low
+
+  * Initial release.
+
+ -- Jane <jane@example.com>  Sun, 01 Jan 2023 00:00:00 +0000
+";
+
+    const BACKPORT: &str = "\
+foo (1.2-1~bpo12+1) bookworm-backports; urgency=medium
+
+  * Rebuild for bookworm-backports.
+
+ -- Jane <jane@example.com>  Wed, 03 Jan 2024 00:00:00 +0000
+
+foo (1.2-1) unstable; urgency=medium
+
+  * New upstream release.
+
+ -- Jane <jane@example.com>  Mon, 01 Jan 2024 00:00:00 +0000
+";
+
+    fn v(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    fn q(s: &str) -> (String, Version) {
+        match parse_qualified_version(s) {
+            (Some(package), Some(version)) => (package, version),
+            _ => panic!("invalid version {}", s),
+        }
+    }
+
+    #[test]
+    fn test_tree_from_changelogs() {
+        let tree = VersionTree::from_changelogs([UNSTABLE, SECURITY, BACKPORT]).unwrap();
+        assert!(tree.contains("foo", &v("1.0-1+deb12u1")));
+        assert!(!tree.contains("foo", &v("0.9-1")));
+        assert_eq!(tree.parent("foo", &v("1.1-1")), Some(&q("foo/1.0-1")));
+        assert_eq!(tree.parent("foo", &v("1.0-1")), None);
+        assert_eq!(
+            tree.lineage("foo", &v("1.2-1~bpo12+1")),
+            Some(vec![
+                q("foo/1.2-1~bpo12+1"),
+                q("foo/1.2-1"),
+                q("foo/1.1-1"),
+                q("foo/1.0-1")
+            ])
+        );
+        assert!(tree.descends_from("foo", &v("1.0-1+deb12u1"), &q("foo/1.0-1")));
+        assert!(!tree.descends_from("foo", &v("1.0-1+deb12u1"), &q("foo/1.1-1")));
+        assert_eq!(tree.lineage("bar", &v("1.0-1")), None);
+    }
+
+    #[test]
+    fn test_tree_from_versions() {
+        let mut tree = VersionTree::new();
+        tree.add_versions("oldfoo/0.9-1 foo/1.0-1 foo/1.1-1\nfoo/1.0-1 foo/1.0-1+deb12u1\n")
+            .unwrap();
+        assert_eq!(tree.parent("foo", &v("1.0-1")), Some(&q("oldfoo/0.9-1")));
+        assert_eq!(
+            tree.parent("foo", &v("1.0-1+deb12u1")),
+            Some(&q("foo/1.0-1"))
+        );
+        assert!(matches!(
+            tree.add_versions("foo/1.0-1 1.1-1"),
+            Err(Error::ParseError(_))
+        ));
+        assert!(matches!(
+            tree.add_changelog("foo (1.0 beta) unstable; urgency=low\n"),
+            Err(Error::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_history() {
+        let tree = VersionTree::from_changelogs([UNSTABLE, SECURITY, BACKPORT]).unwrap();
+        let found = versions(&["foo/1.0-1"]);
+        let fixed = versions(&["foo/1.0-1+deb12u1", "foo/1.2-1"]);
+        let check = |version: &str| buggy(Some("foo"), &v(version), &found, &fixed, Some(&tree));
+        assert_eq!(check("1.0-1+deb12u1"), BugPresence::Fixed);
+        // Linearly 1.1-1 comes after the security fix, but it does not descend from it
+        assert_eq!(check("1.1-1"), BugPresence::Found);
+        assert_eq!(check("1.2-1"), BugPresence::Fixed);
+        assert_eq!(check("1.2-1~bpo12+1"), BugPresence::Fixed);
+        // Versions the tree does not know fall back to linear ordering
+        assert_eq!(check("1.3-1"), BugPresence::Fixed);
+        assert_eq!(check("0.9-1"), BugPresence::Absent);
+
+        let report = BugReport::from_fields(
+            "<found_versions><item>foo/1.0-1</item></found_versions>\
+             <fixed_versions><item>foo/1.0-1+deb12u1</item></fixed_versions>",
+        );
+        assert!(report.affects_version(Some("foo"), &v("1.1-1"), Some(&tree)));
+        assert!(!report.affects_version(Some("foo"), &v("1.1-1"), None));
+
+        let closed = BugReport::from_fields(
+            "<found_versions><item>foo/1.0-1</item></found_versions>\
+             <done>jane@example.com</done>",
+        );
+        assert_eq!(
+            closed.presence(Some("foo"), &v("1.1-1"), Some(&tree)),
+            BugPresence::Fixed
+        );
+    }
+
+    #[test]
+    fn test_history_renamed_source() {
+        let mut tree = VersionTree::new();
+        tree.add_versions("oldfoo/0.9-1 foo/1.0-1 foo/1.1-1")
+            .unwrap();
+        let found = versions(&["oldfoo/0.9-1"]);
+        let fixed = versions(&["foo/1.1-1"]);
+        let check = |version: &str| buggy(Some("foo"), &v(version), &found, &fixed, Some(&tree));
+        assert_eq!(check("1.0-1"), BugPresence::Found);
+        assert_eq!(check("1.1-1"), BugPresence::Fixed);
+    }
+}
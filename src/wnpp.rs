@@ -0,0 +1,356 @@
+//! Work-needing and prospective packages (WNPP)
+//!
+//! Bugs against the `wnpp` pseudo-package track packages that are being
+//! packaged, requested, orphaned or up for adoption. Their subjects follow the
+//! pattern `KIND: package -- short description`, and requests for packaging
+//! carry the details of the software in the body, in the template reportbug
+//! generates.
+//!
+//! See <https://www.debian.org/devel/wnpp/> for the meaning of the kinds.
+//!
+//! # Examples
+//!
+//! ```
+//! use debbugs::wnpp::{WnppKind, WnppSubject};
+//!
+//! let subject: WnppSubject = "ITP: rust-foo -- Foo library for Rust".parse().unwrap();
+//! assert_eq!(subject.kind, WnppKind::Itp);
+//! assert_eq!(subject.package, "rust-foo");
+//! assert_eq!(subject.description.as_deref(), Some("Foo library for Rust"));
+//! ```
+
+use crate::mail::bare_address;
+use crate::{BugId, BugReport, Error};
+use lazy_regex::regex_captures;
+
+/// The pseudo-package WNPP bugs are filed against
+pub const WNPP_PACKAGE: &str = "wnpp";
+
+/// The kind of a WNPP bug
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum WnppKind {
+    /// Intent to package
+    Itp,
+    /// Request for package
+    Rfp,
+    /// Request for adoption
+    Rfa,
+    /// Orphaned
+    O,
+    /// Request for help
+    Rfh,
+    /// Intent to adopt
+    Ita,
+}
+
+impl WnppKind {
+    /// All kinds
+    pub const ALL: [WnppKind; 6] = [
+        WnppKind::Itp,
+        WnppKind::Rfp,
+        WnppKind::Rfa,
+        WnppKind::O,
+        WnppKind::Rfh,
+        WnppKind::Ita,
+    ];
+
+    /// Returns whether the kind is about a package that is already in Debian
+    pub fn is_existing_package(&self) -> bool {
+        matches!(
+            self,
+            WnppKind::Rfa | WnppKind::O | WnppKind::Rfh | WnppKind::Ita
+        )
+    }
+}
+
+impl std::str::FromStr for WnppKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "ITP" => Ok(WnppKind::Itp),
+            "RFP" => Ok(WnppKind::Rfp),
+            "RFA" => Ok(WnppKind::Rfa),
+            "O" => Ok(WnppKind::O),
+            "RFH" => Ok(WnppKind::Rfh),
+            "ITA" => Ok(WnppKind::Ita),
+            _ => Err(Error::ParseError(format!("Unknown WNPP kind: {}", s))),
+        }
+    }
+}
+
+impl std::fmt::Display for WnppKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            WnppKind::Itp => "ITP",
+            WnppKind::Rfp => "RFP",
+            WnppKind::Rfa => "RFA",
+            WnppKind::O => "O",
+            WnppKind::Rfh => "RFH",
+            WnppKind::Ita => "ITA",
+        })
+    }
+}
+
+/// The parsed subject of a WNPP bug
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct WnppSubject {
+    /// The kind of the bug
+    pub kind: WnppKind,
+    /// The name of the package
+    pub package: String,
+    /// The short description of the package
+    pub description: Option<String>,
+}
+
+impl std::str::FromStr for WnppSubject {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (_, kind, package, description) =
+            regex_captures!(r"^\s*([A-Za-z]+):\s*(\S+)(?:\s+--?\s*(.*))?$", s)
+                .ok_or_else(|| Error::ParseError(format!("Not a WNPP subject: {}", s)))?;
+        let description = description.trim();
+        Ok(WnppSubject {
+            kind: kind.parse()?,
+            package: package.to_string(),
+            description: if description.is_empty() {
+                None
+            } else {
+                Some(description.to_string())
+            },
+        })
+    }
+}
+
+impl std::fmt::Display for WnppSubject {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.kind, self.package)?;
+        if let Some(description) = &self.description {
+            write!(f, " -- {}", description)?;
+        }
+        Ok(())
+    }
+}
+
+/// The details of a package from the body of an ITP or RFP
+///
+/// Fields that are missing, or left at their template placeholder, are `None`.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct PackageDetails {
+    /// The name of the package
+    pub package_name: Option<String>,
+    /// The upstream version
+    pub version: Option<String>,
+    /// The upstream author or contact
+    pub upstream_author: Option<String>,
+    /// The homepage of the software
+    pub url: Option<String>,
+    /// The license of the software
+    pub license: Option<String>,
+    /// The programming language the software is written in
+    pub programming_language: Option<String>,
+    /// The short description
+    pub description: Option<String>,
+}
+
+impl PackageDetails {
+    /// Extracts the details from the body of a bug report
+    ///
+    /// The fields are looked up in the template reportbug generates, e.g.
+    /// `* URL             : https://example.com/`.
+    pub fn parse(body: &str) -> Self {
+        let mut details = PackageDetails::default();
+        for line in body.lines() {
+            let (_, key, value) =
+                match regex_captures!(r"^\s*\*?\s*([A-Za-z][A-Za-z ]*?)\s*:\s*(.*?)\s*$", line) {
+                    Some(captures) => captures,
+                    None => continue,
+                };
+            // Skip empty fields and the placeholders of the template
+            if value.is_empty() || (value.starts_with('<') && value.ends_with('>')) {
+                continue;
+            }
+            let field = match key.to_lowercase().as_str() {
+                "package name" => &mut details.package_name,
+                "version" => &mut details.version,
+                "upstream author" | "upstream contact" => &mut details.upstream_author,
+                "url" => &mut details.url,
+                "license" | "licence" => &mut details.license,
+                "programming lang" | "programming language" => &mut details.programming_language,
+                "description" => &mut details.description,
+                _ => continue,
+            };
+            if field.is_none() {
+                *field = Some(value.to_string());
+            }
+        }
+        details
+    }
+}
+
+/// A WNPP bug
+#[derive(Debug, Clone)]
+pub struct WnppBug {
+    /// The bug number
+    pub bug: BugId,
+    /// The parsed subject
+    pub subject: WnppSubject,
+    /// The status of the bug
+    pub report: BugReport,
+}
+
+impl WnppBug {
+    /// Parses the subject of a bug report, returning `None` if it is not a WNPP subject
+    pub fn from_report(bug: BugId, report: BugReport) -> Option<Self> {
+        let subject = report.subject.as_deref()?.parse().ok()?;
+        Some(WnppBug {
+            bug,
+            subject,
+            report,
+        })
+    }
+}
+
+/// Returns whether two addresses are the same, ignoring display names and case
+fn same_address(a: &str, b: &str) -> bool {
+    bare_address(a).eq_ignore_ascii_case(bare_address(b))
+}
+
+/// Returns the bugs of the given kinds
+pub fn of_kinds<'a>(
+    bugs: &'a [WnppBug],
+    kinds: &'a [WnppKind],
+) -> impl Iterator<Item = &'a WnppBug> {
+    bugs.iter().filter(|b| kinds.contains(&b.subject.kind))
+}
+
+/// Returns the packages `maintainer` has orphaned or put up for adoption
+///
+/// These are the O and RFA bugs `maintainer` filed, as maintainers give up
+/// their packages by filing these bugs themselves. Packages orphaned on
+/// behalf of the maintainer, e.g. O bugs the QA or MIA teams filed for an
+/// inactive maintainer, are not returned, as their originator is the team.
+pub fn orphaned_by<'a>(
+    bugs: &'a [WnppBug],
+    maintainer: &'a str,
+) -> impl Iterator<Item = &'a WnppBug> {
+    of_kinds(bugs, &[WnppKind::O, WnppKind::Rfa]).filter(move |b| {
+        b.report
+            .originator
+            .as_deref()
+            .is_some_and(|o| same_address(o, maintainer))
+    })
+}
+
+/// Returns the packages `owner` intends to package or adopt
+///
+/// These are the ITP and ITA bugs owned by `owner`.
+pub fn owned_by<'a>(bugs: &'a [WnppBug], owner: &'a str) -> impl Iterator<Item = &'a WnppBug> {
+    of_kinds(bugs, &[WnppKind::Itp, WnppKind::Ita]).filter(move |b| {
+        b.report
+            .owner
+            .as_deref()
+            .is_some_and(|o| same_address(o, owner))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_subject() {
+        let subject: WnppSubject = "O: foo -- frobnicates widgets".parse().unwrap();
+        assert_eq!(
+            subject,
+            WnppSubject {
+                kind: WnppKind::O,
+                package: "foo".to_string(),
+                description: Some("frobnicates widgets".to_string()),
+            }
+        );
+        assert_eq!(subject.to_string(), "O: foo -- frobnicates widgets");
+
+        let subject: WnppSubject = "rfa: bar - a tool".parse().unwrap();
+        assert_eq!(subject.kind, WnppKind::Rfa);
+        assert_eq!(subject.description.as_deref(), Some("a tool"));
+
+        let subject: WnppSubject = "ITA: baz".parse().unwrap();
+        assert_eq!(subject.package, "baz");
+        assert_eq!(subject.description, None);
+        assert!(subject.kind.is_existing_package());
+
+        assert!(matches!(
+            "foo: crashes on startup".parse::<WnppSubject>(),
+            Err(Error::ParseError(_))
+        ));
+        assert!("No colon here".parse::<WnppSubject>().is_err());
+    }
+
+    #[test]
+    fn test_package_details() {
+        let body = "\
+Package: wnpp
+Severity: wishlist
+Owner: Jane Doe <jane@example.com>
+
+* Package name    : rust-foo
+  Version         : 1.2.3
+  Upstream Author : John Smith <john@example.org>
+* URL             : https://example.org/foo
+* License         : MIT or Apache-2.0
+  Programming Lang: Rust
+  Description     : Foo library for Rust
+  Section         : <section>
+
+It does foo.
+";
+        assert_eq!(
+            PackageDetails::parse(body),
+            PackageDetails {
+                package_name: Some("rust-foo".to_string()),
+                version: Some("1.2.3".to_string()),
+                upstream_author: Some("John Smith <john@example.org>".to_string()),
+                url: Some("https://example.org/foo".to_string()),
+                license: Some("MIT or Apache-2.0".to_string()),
+                programming_language: Some("Rust".to_string()),
+                description: Some("Foo library for Rust".to_string()),
+            }
+        );
+
+        let template =
+            "* Package name    : foo\n  Version         : x.y.z\n* URL             : <homepage>\n";
+        let details = PackageDetails::parse(template);
+        assert_eq!(details.url, None);
+        assert_eq!(details.package_name.as_deref(), Some("foo"));
+    }
+
+    fn bug(bug: BugId, xml: &str) -> WnppBug {
+        WnppBug::from_report(bug, BugReport::from_fields(xml)).unwrap()
+    }
+
+    #[test]
+    fn test_queries() {
+        let bugs = vec![
+            bug(1, "<subject>O: foo -- foo</subject><originator>Jane &lt;JANE@example.com&gt;</originator>"),
+            bug(2, "<subject>RFA: bar -- bar</subject><originator>jane@example.com</originator>"),
+            bug(3, "<subject>RFA: baz -- baz</subject><originator>bob@example.com</originator>"),
+            bug(4, "<subject>ITP: qux -- qux</subject><originator>jane@example.com</originator><owner>jane@example.com</owner>"),
+            bug(5, "<subject>ITA: baz -- baz</subject><owner>Jane &lt;jane@example.com&gt;</owner>"),
+        ];
+        let ids = |bugs: Vec<&WnppBug>| bugs.iter().map(|b| b.bug).collect::<Vec<_>>();
+        assert_eq!(
+            ids(orphaned_by(&bugs, "jane@example.com").collect()),
+            vec![1, 2]
+        );
+        assert_eq!(
+            ids(owned_by(&bugs, "jane@example.com").collect()),
+            vec![4, 5]
+        );
+        assert_eq!(ids(of_kinds(&bugs, &[WnppKind::Rfa]).collect()), vec![2, 3]);
+
+        let report = BugReport::from_fields("<subject>foo: broken</subject>");
+        assert!(WnppBug::from_report(6, report).is_none());
+    }
+}